@@ -1,17 +1,10 @@
-// Many integer and float conversions are done in this file, we want to get rid of them
-// TODO #![warn(clippy::as_conversions)]
-
-#![allow(clippy::float_arithmetic)]
-#![allow(clippy::cast_possible_truncation)]
-#![allow(clippy::cast_precision_loss)]
-#![allow(clippy::cast_sign_loss)]
+#![allow(clippy::module_name_repetitions)]
 
+use crate::can::config::TransceiverDelayCompensation;
 use crate::log::info;
-// The following import is needed when f32::abs is not available (tricore toolchain)
-#[allow(unused_imports)]
-use crate::util::F32Abs;
 
 /// CAN bit timing configuration
+#[derive(Debug, Clone)]
 pub enum BitTimingConfig {
     Auto(AutoBitTiming),
     Manual(NominalBitTiming),
@@ -24,6 +17,7 @@ impl Default for BitTimingConfig {
 }
 
 /// Fast CAN bit timing configuration
+#[derive(Debug, Clone)]
 pub enum FastBitTimingConfig {
     Auto(AutoBitTiming),
     Manual(DataBitTiming),
@@ -35,170 +29,323 @@ impl Default for FastBitTimingConfig {
     }
 }
 
-// TODO Default values are not valid
-/// Automatic bit timing configuration
-#[derive(Default)]
+/// Automatic bit timing configuration.
+///
+/// The default is 500 kbit/s with the sample point at 80% and the largest
+/// synchronization jump width.
+#[derive(Debug, Clone)]
 pub struct AutoBitTiming {
     /// Baud rate in bps
     pub baud_rate: u32,
-    /// Sample point in 1/10th of a percent (e.g. 8000 = 80%)
+    /// Sample point in 1/100th of a percent (e.g. 8000 = 80%), in range [5000, 9000]
     pub sample_point: u16,
-    /// Synchronization jump width in time quanta
+    /// Synchronization jump width in time quanta, 0 selects the length of phase segment 2
     pub sync_jump_width: u16,
 }
 
-pub(super) const NBTP_NBRP_MSK: i32 = 0x1ff;
-pub(super) const NBTP_NTSEG1_MSK: i32 = 0xff;
-pub(super) const NBTP_NTSEG2_MSK: i32 = 0x7f;
+impl Default for AutoBitTiming {
+    fn default() -> Self {
+        Self {
+            baud_rate: 500_000,
+            sample_point: 8_000,
+            sync_jump_width: 0,
+        }
+    }
+}
 
-pub(super) const DBTP_DBRP_MSK: i32 = 0x1f;
-pub(super) const DBTP_DTSEG1_MSK: i32 = 0x1f;
-pub(super) const DBTP_DTSEG2_MSK: i32 = 0xf;
+/// Candidates and acceptance criteria of the baud rate detection, see
+/// [`crate::can::Node::detect_baud_rate`]
+#[derive(Clone, Copy)]
+pub struct BaudRateDetection<'a> {
+    /// Nominal bit timings, tried in order
+    pub candidates: &'a [BitTimingConfig],
+    /// Data phase bit timings, tried in order with the detected nominal bit timing on a
+    /// CAN FD node. Only frames with bit rate switching are judged, leave empty to skip.
+    pub fast_candidates: &'a [FastBitTimingConfig],
+    /// Transmitter delay compensation applied with the data phase bit timings
+    pub delay_compensation: TransceiverDelayCompensation,
+    /// Frames to receive without any error for a candidate to match, counted in the Rx
+    /// FIFOs and dedicated Rx buffers
+    pub frames: u32,
+    /// Time in microseconds to listen with a candidate before it is rejected for lack of
+    /// frames, measured with the internal timestamp counter. The candidate is also
+    /// rejected after a number of register polls bounded by this time, in case the
+    /// counter does not advance.
+    pub listen_time: u32,
+}
 
-pub(super) struct BestBaudRate {
-    pub(super) tbaud: i32,
-    pub(super) brp: i32,
+/// Bit timings found by the baud rate detection
+#[derive(Debug, Clone)]
+pub struct DetectedBaudRate {
+    pub baud_rate: BitTimingConfig,
+    /// `None` without data phase candidates, or if none of them matched
+    pub fast_baud_rate: Option<FastBitTimingConfig>,
 }
 
-pub(super) fn get_best_baud_rate(
-    brp_msk: i32,
-    tseg1_msk: i32,
-    tseg2_msk: i32,
-    module_freq: f32,
-    baudrate: u32,
-) -> BestBaudRate {
-    // Search for best baudrate
-
-    let max_brp = brp_msk + 1;
-    let min_brp = 1;
-
-    let max_tbaud = (tseg1_msk + 1) + (tseg2_msk + 1) + 1;
-    let min_tbaud = 8;
-
-    let mut best_error = baudrate as f32;
-    let mut best_brp = 1;
-    let mut best_tbaud = 8;
-    let mut tmp_brp = 1;
-    let mut tmp_tbaud: i32 = 0;
-
-    while tmp_brp <= max_brp {
-        let f_quanta = module_freq / tmp_brp as f32;
-        tmp_tbaud = (f_quanta / baudrate as f32) as i32;
-
-        if tmp_tbaud == 0 {
-            // Avoid division by 0
-            break;
-        }
+/// Reason why a bit timing cannot be used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitTimingError {
+    /// The baud rate is 0
+    InvalidBaudRate,
+    /// The requested sample point is outside the recommended range [50%, 90%]
+    InvalidSamplePoint,
+    /// The synchronization jump width is longer than phase segment 2
+    InvalidSyncJumpWidth,
+    /// No prescaler gives a bit of at least 8 time quanta within the register ranges
+    /// and a baud rate error below [`MAX_BIT_RATE_ERROR_PPM`]
+    BaudRateNotReachable,
+    /// The sample point cannot be placed within one time quantum of the request
+    SamplePointNotReachable,
+    /// A manual bit timing field is outside the range of its register field
+    OutOfRange,
+    /// The data phase is faster than [`TDC_REQUIRED_ABOVE_BAUD_RATE`] and the transmitter
+    /// delay compensation is disabled
+    DelayCompensationRequired,
+    /// The transmitter delay compensation needs a data bit rate prescaler of 1 or 2, and an
+    /// offset and filter window in range [0, 127]
+    InvalidDelayCompensation,
+}
 
-        let temp_baudrate = f_quanta / tmp_tbaud as f32;
-        let error = (temp_baudrate - baudrate as f32).abs();
+/// Largest accepted deviation of the achieved baud rate, in parts per million
+pub const MAX_BIT_RATE_ERROR_PPM: u32 = 5_000;
 
-        if tmp_tbaud < min_tbaud {
-            // Below the minimum allowed limits, break is required otherwise
-            // TSEG1 and TSEG2 may result in negative values
-            break;
-        }
+/// Data phase baud rate above which the transmitter delay compensation is required
+pub const TDC_REQUIRED_ABOVE_BAUD_RATE: u32 = 2_000_000;
 
-        if (tmp_tbaud <= max_tbaud) && (best_error >= error) {
-            best_brp = tmp_brp;
-            best_tbaud = tmp_tbaud;
-            best_error = error;
+/// Largest value of the TDCR offset and filter window fields
+const MAX_TDC_VALUE: u32 = 127;
+/// Largest data bit rate prescaler usable with the transmitter delay compensation
+const MAX_TDC_PRESCALER: u32 = 2;
 
-            if (tmp_tbaud <= 20) && (error < 0.1) {
-                // Optimal condition
-                break;
-            }
-        }
-        tmp_brp += 1;
+const MIN_SAMPLE_POINT: u16 = 5_000;
+const MAX_SAMPLE_POINT: u16 = 9_000;
+const MIN_TIME_QUANTA_PER_BIT: u32 = 8;
+
+/// Ranges of the bit timing fields, in time quanta (register value + 1)
+struct BitTimingLimits {
+    max_brp: u32,
+    min_tseg1: u32,
+    max_tseg1: u32,
+    max_tseg2: u32,
+    max_sjw: u32,
+}
+
+/// NBTP field ranges
+const NOMINAL_LIMITS: BitTimingLimits = BitTimingLimits {
+    max_brp: 512,
+    min_tseg1: 2,
+    max_tseg1: 256,
+    max_tseg2: 128,
+    max_sjw: 128,
+};
+
+/// DBTP field ranges
+const DATA_LIMITS: BitTimingLimits = BitTimingLimits {
+    max_brp: 32,
+    min_tseg1: 1,
+    max_tseg1: 32,
+    max_tseg2: 16,
+    max_sjw: 16,
+};
+
+/// Bit timing found by the solver, with the achieved values
+#[derive(Debug, Clone, Copy)]
+pub struct BitTimingSolution<T> {
+    pub timing: T,
+    /// Achieved baud rate in bps, rounded to the nearest integer
+    pub baud_rate: u32,
+    /// Deviation of the achieved baud rate from the requested one, in parts per million
+    pub error_ppm: u32,
+    /// Achieved sample point in 1/100th of a percent
+    pub sample_point: u16,
+    /// Synchronization jump width in time quanta
+    pub sync_jump_width: u16,
+}
+
+/// Bit timing in time quanta, before conversion to register values
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segments {
+    brp: u32,
+    tseg1: u32,
+    tseg2: u32,
+}
+
+impl Segments {
+    fn time_quanta(&self) -> u32 {
+        1 + self.tseg1 + self.tseg2
     }
 
-    if (best_brp == 0) && (tmp_brp == (max_brp + 1)) {
-        // force max brp and tbaud
-        best_brp = max_brp;
-        best_tbaud = max_tbaud;
+    /// Sample point in 1/100th of a percent
+    fn sample_point(&self) -> u32 {
+        (1 + self.tseg1) * 10_000 / self.time_quanta()
     }
+}
 
-    if (best_brp == 0) && (tmp_tbaud < min_tbaud) {
-        // force min brp and tbaud
-        best_brp = min_brp;
-        best_tbaud = min_tbaud;
+/// Split a bit of `time_quanta` into the segments closest to the sample point, `None`
+/// if the fields cannot hold a sample point within one time quantum of the request
+fn split_bit(time_quanta: u32, sample_point: u16, limits: &BitTimingLimits) -> Option<(u32, u32)> {
+    // number of time quanta up to the sample point, rounded to the nearest
+    let before_sample = (time_quanta * u32::from(sample_point) + 5_000) / 10_000;
+    let tseg2 = time_quanta
+        .checked_sub(before_sample)?
+        .clamp(1, limits.max_tseg2);
+    let tseg1 = time_quanta.checked_sub(1 + tseg2)?;
+
+    if !(limits.min_tseg1..=limits.max_tseg1).contains(&tseg1) {
+        return None;
     }
 
-    BestBaudRate {
-        tbaud: best_tbaud,
-        brp: best_brp,
+    let achieved = (1 + tseg1) * 10_000 / time_quanta;
+    let error = achieved.abs_diff(u32::from(sample_point));
+    if error > 10_000 / time_quanta {
+        return None;
     }
+
+    Some((tseg1, tseg2))
 }
 
-pub(super) fn get_best_sample_point(
-    tseg1_msk: i32,
-    tseg2_msk: i32,
-    best_tbaud: i32,
-    sample_point: u16,
-) -> (i32, i32) {
-    // Search for best sample point
-
-    // 25% tolerance in sample point as max error
-    let mut best_error = f32::from(sample_point) * 0.25;
-    let max_tseg1 = tseg1_msk + 1;
-    let min_tseg1 = 3;
-    let max_tseg2 = tseg2_msk + 1;
-    let min_tseg2 = 2;
-
-    let max_tseg1 = if best_tbaud < max_tseg1 {
-        best_tbaud
-    } else {
-        max_tseg1
+/// Outcome of a prescaler for the requested baud rate
+enum Prescaler {
+    /// Time quanta per bit and deviation of the achieved baud rate in parts per million
+    Reaches(u32, u32),
+    /// Fewer than 8 time quanta per bit, as with any larger prescaler
+    TooLarge,
+    /// Too many time quanta per bit, or a baud rate error above [`MAX_BIT_RATE_ERROR_PPM`]
+    Misses,
+}
+
+fn try_prescaler(
+    module_freq: u64,
+    baud_rate: u64,
+    brp: u32,
+    limits: &BitTimingLimits,
+) -> Prescaler {
+    let brp_rate = u64::from(brp) * baud_rate;
+    // time quanta per bit, rounded to the nearest
+    let Ok(time_quanta) = u32::try_from((module_freq + brp_rate / 2) / brp_rate) else {
+        return Prescaler::Misses;
     };
-    let mut best_tseg1 = max_tseg1;
 
-    for temp_tseg1 in (min_tseg1..=max_tseg1).rev() {
-        let temp_sample_point = ((temp_tseg1 + 1) * 10000) / best_tbaud;
-        let error = temp_sample_point - i32::from(sample_point);
-        let error = if error < 0 { -error } else { error };
+    if time_quanta < MIN_TIME_QUANTA_PER_BIT {
+        return Prescaler::TooLarge;
+    }
 
-        if best_error > error as f32 {
-            best_tseg1 = temp_tseg1;
-            best_error = error as f32;
-        }
+    if time_quanta > 1 + limits.max_tseg1 + limits.max_tseg2 {
+        return Prescaler::Misses;
+    }
 
-        if temp_sample_point < i32::from(sample_point) {
-            // Least possible error has already occurred
-            break;
+    let achieved_clocks = brp_rate * u64::from(time_quanta);
+    let error_ppm = module_freq.abs_diff(achieved_clocks) * 1_000_000 / achieved_clocks;
+    match u32::try_from(error_ppm) {
+        Ok(error_ppm) if error_ppm <= MAX_BIT_RATE_ERROR_PPM => {
+            Prescaler::Reaches(time_quanta, error_ppm)
         }
+        _ => Prescaler::Misses,
+    }
+}
+
+/// Bit timing for `config`. A `preferred_brp` reaching the baud rate and the sample point
+/// is kept, otherwise the prescaler with the lowest baud rate error is searched.
+fn solve(
+    module_freq: u32,
+    config: &AutoBitTiming,
+    limits: &BitTimingLimits,
+    preferred_brp: Option<u32>,
+) -> Result<BitTimingSolution<Segments>, BitTimingError> {
+    if config.baud_rate == 0 {
+        return Err(BitTimingError::InvalidBaudRate);
     }
 
-    let best_tseg2 = if (best_tbaud - best_tseg1 - 1) > max_tseg2 {
-        // force max tseg2
-        max_tseg2
-    } else {
-        best_tbaud - best_tseg1 - 1
+    if !(MIN_SAMPLE_POINT..=MAX_SAMPLE_POINT).contains(&config.sample_point) {
+        return Err(BitTimingError::InvalidSamplePoint);
+    }
+
+    let module_freq = u64::from(module_freq);
+    let baud_rate = u64::from(config.baud_rate);
+
+    let preferred = preferred_brp
+        .filter(|brp| (1..=limits.max_brp).contains(brp))
+        .and_then(|brp| {
+            let Prescaler::Reaches(time_quanta, error_ppm) =
+                try_prescaler(module_freq, baud_rate, brp, limits)
+            else {
+                return None;
+            };
+            let (tseg1, tseg2) = split_bit(time_quanta, config.sample_point, limits)?;
+            Some((Segments { brp, tseg1, tseg2 }, error_ppm))
+        });
+    let (segments, error_ppm) = match preferred {
+        Some(preferred) => preferred,
+        None => search(module_freq, config, limits)?,
     };
 
-    let best_tseg2 = if best_tseg2 < min_tseg2 {
-        // force min tseg2
-        min_tseg2
-    } else {
-        best_tseg2
+    let sync_jump_width = match u32::from(config.sync_jump_width) {
+        0 => segments.tseg2.min(limits.max_sjw),
+        sjw if sjw <= segments.tseg2 && sjw <= limits.max_sjw => sjw,
+        _ => return Err(BitTimingError::InvalidSyncJumpWidth),
     };
 
-    (best_tseg1, best_tseg2)
+    let bit_clocks = u64::from(segments.brp) * u64::from(segments.time_quanta());
+    let achieved_baud_rate = (module_freq + bit_clocks / 2) / bit_clocks;
+
+    Ok(BitTimingSolution {
+        timing: segments,
+        baud_rate: u32::try_from(achieved_baud_rate).unwrap_or(u32::MAX),
+        error_ppm,
+        sample_point: u16::try_from(segments.sample_point()).unwrap_or(u16::MAX),
+        sync_jump_width: u16::try_from(sync_jump_width).unwrap_or(u16::MAX),
+    })
 }
 
-pub(super) fn get_best_sjw(best_tbaud: u32, best_tseg2: u32, sync_jump_width: u16) -> u32 {
-    let mut best_sjw = 0;
-    let mut best_error = 10000.0;
+/// Segments of the prescaler with the lowest baud rate error, and that error
+fn search(
+    module_freq: u64,
+    config: &AutoBitTiming,
+    limits: &BitTimingLimits,
+) -> Result<(Segments, u32), BitTimingError> {
+    let baud_rate = u64::from(config.baud_rate);
+    let mut best: Option<(Segments, u32)> = None;
+    let mut sample_point_not_reachable = false;
+
+    for brp in 1..=limits.max_brp {
+        let (time_quanta, error_ppm) = match try_prescaler(module_freq, baud_rate, brp, limits) {
+            Prescaler::Reaches(time_quanta, error_ppm) => (time_quanta, error_ppm),
+            // time quanta only decrease with larger prescalers
+            Prescaler::TooLarge => break,
+            Prescaler::Misses => continue,
+        };
+
+        // keep the smallest prescaler among the best candidates, it gives the finest
+        // resolution of the sample point and synchronization jump width
+        if best.is_some_and(|(_, best_error)| best_error <= error_ppm) {
+            continue;
+        }
+
+        let Some((tseg1, tseg2)) = split_bit(time_quanta, config.sample_point, limits) else {
+            sample_point_not_reachable = true;
+            continue;
+        };
 
-    for temp_sjw in 1..=best_tseg2 {
-        let temp_sync_jump_width = (temp_sjw as f32 * 10000.0) / best_tbaud as f32;
-        let error = (temp_sync_jump_width - f32::from(sync_jump_width)).abs();
+        best = Some((Segments { brp, tseg1, tseg2 }, error_ppm));
 
-        if best_error > error {
-            best_sjw = temp_sjw;
-            best_error = error;
+        if error_ppm == 0 {
+            break;
         }
     }
-    best_sjw
+
+    best.ok_or(if sample_point_not_reachable {
+        BitTimingError::SamplePointNotReachable
+    } else {
+        BitTimingError::BaudRateNotReachable
+    })
+}
+
+/// Convert a value in time quanta to its register value, checking the field range
+fn to_register<T: TryFrom<u32>>(value: u32, min: u32, max: u32) -> Result<T, BitTimingError> {
+    if !(min..=max).contains(&value) {
+        return Err(BitTimingError::OutOfRange);
+    }
+    T::try_from(value - 1).map_err(|_| BitTimingError::OutOfRange)
 }
 
 /// Nominal CAN bit timing
@@ -210,6 +357,30 @@ pub struct NominalBitTiming {
     pub(super) tseg2: u8,
 }
 
+impl NominalBitTiming {
+    /// Create a nominal bit timing from the prescaler and the segment lengths in time
+    /// quanta, checking them against the NBTP field ranges
+    pub fn new(brp: u32, sjw: u32, tseg1: u32, tseg2: u32) -> Result<Self, BitTimingError> {
+        let limits = NOMINAL_LIMITS;
+
+        if sjw > tseg2 {
+            return Err(BitTimingError::InvalidSyncJumpWidth);
+        }
+
+        Ok(Self {
+            brp: to_register(brp, 1, limits.max_brp)?,
+            sjw: to_register(sjw, 1, limits.max_sjw)?,
+            tseg1: to_register(tseg1, limits.min_tseg1, limits.max_tseg1)?,
+            tseg2: to_register(tseg2, 1, limits.max_tseg2)?,
+        })
+    }
+
+    /// Number of module clock cycles of a nominal bit
+    pub(super) fn clocks_per_bit(self) -> u32 {
+        (u32::from(self.brp) + 1) * (u32::from(self.tseg1) + u32::from(self.tseg2) + 3)
+    }
+}
+
 /// Data CAN bit timing
 #[derive(Debug, Clone, Copy)]
 pub struct DataBitTiming {
@@ -219,104 +390,394 @@ pub struct DataBitTiming {
     pub(super) tseg2: u8,
 }
 
-pub(super) fn calculate_bit_timing(
-    module_freq: f32,
-    baud_rate: u32,
-    sample_point: u16,
-    sjw: u16,
-) -> NominalBitTiming {
-    info!(
-        "module_freq: {}, baud_rate: {}, sample_point: {}, sync_jump_with: {}",
-        module_freq, baud_rate, sample_point, sjw
-    );
+impl DataBitTiming {
+    /// Create a data bit timing from the prescaler and the segment lengths in time
+    /// quanta, checking them against the DBTP field ranges
+    pub fn new(brp: u32, sjw: u32, tseg1: u32, tseg2: u32) -> Result<Self, BitTimingError> {
+        let limits = DATA_LIMITS;
 
-    /* Set values into node */
-    let best = get_best_baud_rate(
-        NBTP_NBRP_MSK,
-        NBTP_NTSEG1_MSK,
-        NBTP_NTSEG2_MSK,
-        module_freq,
-        baud_rate,
-    );
+        if sjw > tseg2 {
+            return Err(BitTimingError::InvalidSyncJumpWidth);
+        }
 
-    let (best_tseg1, best_tseg2) =
-        get_best_sample_point(NBTP_NTSEG1_MSK, NBTP_NTSEG2_MSK, best.tbaud, sample_point);
-    let best_sjw = get_best_sjw(best.tbaud as u32, best_tseg2 as u32, sjw);
+        Ok(Self {
+            brp: to_register(brp, 1, limits.max_brp)?,
+            sjw: to_register(sjw, 1, limits.max_sjw)?,
+            tseg1: to_register(tseg1, limits.min_tseg1, limits.max_tseg1)?,
+            tseg2: to_register(tseg2, 1, limits.max_tseg2)?,
+        })
+    }
+
+    /// Number of module clock cycles of a data bit
+    pub(super) fn clocks_per_bit(self) -> u32 {
+        (u32::from(self.brp) + 1) * (u32::from(self.tseg1) + u32::from(self.tseg2) + 3)
+    }
+
+    /// Position of the sample point from the start of the bit, in module clock cycles
+    pub(super) fn sample_point_clocks(self) -> u32 {
+        (u32::from(self.brp) + 1) * (u32::from(self.tseg1) + 2)
+    }
+
+    /// Check the transmitter delay compensation against this timing, returning the
+    /// TDCR offset and filter window when it is enabled
+    pub(super) fn delay_compensation(
+        self,
+        module_freq: u32,
+        offset: Option<(u32, u32)>,
+    ) -> Result<Option<(u8, u8)>, BitTimingError> {
+        let baud_rate = module_freq / self.clocks_per_bit();
+
+        let Some((offset, filter_window)) = offset else {
+            if baud_rate > TDC_REQUIRED_ABOVE_BAUD_RATE {
+                return Err(BitTimingError::DelayCompensationRequired);
+            }
+            return Ok(None);
+        };
+
+        if u32::from(self.brp) + 1 > MAX_TDC_PRESCALER
+            || offset > MAX_TDC_VALUE
+            || filter_window > MAX_TDC_VALUE
+        {
+            return Err(BitTimingError::InvalidDelayCompensation);
+        }
 
-    NominalBitTiming {
-        brp: best.brp as u16 - 1,
-        sjw: best_sjw as u8 - 1,
-        tseg1: best_tseg1 as u8 - 1,
-        tseg2: best_tseg2 as u8 - 1,
+        let offset = u8::try_from(offset).map_err(|_| BitTimingError::InvalidDelayCompensation)?;
+        let filter_window =
+            u8::try_from(filter_window).map_err(|_| BitTimingError::InvalidDelayCompensation)?;
+        Ok(Some((offset, filter_window)))
     }
 }
 
-pub(super) fn calculate_fast_bit_timing(
-    module_freq: f32,
-    baud_rate: u32,
-    sample_point: u16,
-    sjw: u16,
-) -> DataBitTiming {
-    /* Set values into node */
-    let best = get_best_baud_rate(
-        DBTP_DBRP_MSK,
-        DBTP_DTSEG1_MSK,
-        DBTP_DTSEG2_MSK,
-        module_freq,
-        baud_rate,
+/// Nominal bit timing selected for an [`AutoBitTiming`] configuration, with the achieved
+/// baud rate, baud rate error, sample point and synchronization jump width. This is the
+/// timing configured for [`BitTimingConfig::Auto`] at the module clock frequency.
+pub fn calculate_bit_timing(
+    module_freq: u32,
+    config: &AutoBitTiming,
+) -> Result<BitTimingSolution<NominalBitTiming>, BitTimingError> {
+    let solution = solve(module_freq, config, &NOMINAL_LIMITS, None)?;
+    let segments = solution.timing;
+
+    info!(
+        "nominal bit timing: {} bps, error {} ppm, sample point {}",
+        solution.baud_rate, solution.error_ppm, solution.sample_point
     );
 
-    let (best_tseg1, best_tseg2) =
-        get_best_sample_point(DBTP_DTSEG1_MSK, DBTP_DTSEG2_MSK, best.tbaud, sample_point);
-    let best_sjw = get_best_sjw(best.tbaud as u32, best_tseg2 as u32, sjw);
+    Ok(BitTimingSolution {
+        timing: NominalBitTiming::new(
+            segments.brp,
+            u32::from(solution.sync_jump_width),
+            segments.tseg1,
+            segments.tseg2,
+        )?,
+        baud_rate: solution.baud_rate,
+        error_ppm: solution.error_ppm,
+        sample_point: solution.sample_point,
+        sync_jump_width: solution.sync_jump_width,
+    })
+}
 
-    DataBitTiming {
-        brp: best.brp as u8 - 1,
-        sjw: best_sjw as u8 - 1,
-        tseg1: best_tseg1 as u8 - 1,
-        tseg2: best_tseg2 as u8 - 1,
-    }
+/// Data bit timing selected for an [`AutoBitTiming`] configuration, with its achieved
+/// values, as configured for [`FastBitTimingConfig::Auto`]. The prescaler of the `nominal`
+/// bit timing is kept when possible as recommended by CiA 601-3, unless the transmitter
+/// delay compensation needs a smaller one.
+pub fn calculate_fast_bit_timing(
+    module_freq: u32,
+    config: &AutoBitTiming,
+    nominal: NominalBitTiming,
+    delay_compensation: TransceiverDelayCompensation,
+) -> Result<BitTimingSolution<DataBitTiming>, BitTimingError> {
+    let nominal_brp = u32::from(nominal.brp) + 1;
+    let preferred_brp = match delay_compensation {
+        TransceiverDelayCompensation::Disabled => Some(nominal_brp),
+        _ => Some(nominal_brp).filter(|brp| *brp <= MAX_TDC_PRESCALER),
+    };
+    let solution = solve(module_freq, config, &DATA_LIMITS, preferred_brp)?;
+    let segments = solution.timing;
+
+    info!(
+        "data bit timing: {} bps, error {} ppm, sample point {}",
+        solution.baud_rate, solution.error_ppm, solution.sample_point
+    );
+
+    Ok(BitTimingSolution {
+        timing: DataBitTiming::new(
+            segments.brp,
+            u32::from(solution.sync_jump_width),
+            segments.tseg1,
+            segments.tseg2,
+        )?,
+        baud_rate: solution.baud_rate,
+        error_ppm: solution.error_ppm,
+        sample_point: solution.sample_point,
+        sync_jump_width: solution.sync_jump_width,
+    })
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    #[test]
-    fn test_get_best_baudrate() {
-        let module_freq = 80000000.0;
-        let baudrate = 500000;
-        let best = get_best_baud_rate(
-            NBTP_NBRP_MSK,
-            NBTP_NTSEG1_MSK,
-            NBTP_NTSEG2_MSK,
-            module_freq,
-            baudrate,
+    const MODULE_FREQUENCIES: [u32; 4] = [20_000_000, 40_000_000, 80_000_000, 100_000_000];
+    const NOMINAL_BAUD_RATES: [u32; 8] = [
+        10_000, 20_000, 50_000, 125_000, 250_000, 500_000, 800_000, 1_000_000,
+    ];
+    const DATA_BAUD_RATES: [u32; 4] = [1_000_000, 2_000_000, 4_000_000, 5_000_000];
+
+    fn auto(baud_rate: u32, sample_point: u16, sync_jump_width: u16) -> AutoBitTiming {
+        AutoBitTiming {
+            baud_rate,
+            sample_point,
+            sync_jump_width,
+        }
+    }
+
+    fn check(module_freq: u32, config: &AutoBitTiming, limits: &BitTimingLimits) {
+        let solution = solve(module_freq, config, limits, None).unwrap();
+        let segments = solution.timing;
+
+        assert!((1..=limits.max_brp).contains(&segments.brp));
+        assert!((limits.min_tseg1..=limits.max_tseg1).contains(&segments.tseg1));
+        assert!((1..=limits.max_tseg2).contains(&segments.tseg2));
+        assert!(segments.time_quanta() >= MIN_TIME_QUANTA_PER_BIT);
+
+        let sjw = u32::from(solution.sync_jump_width);
+        assert!(sjw >= 1 && sjw <= segments.tseg2 && sjw <= limits.max_sjw);
+
+        let bit_clocks = u64::from(segments.brp * segments.time_quanta());
+        let expected_clocks = bit_clocks * u64::from(config.baud_rate);
+        let error_ppm =
+            u64::from(module_freq).abs_diff(expected_clocks) * 1_000_000 / expected_clocks;
+        assert_eq!(u64::from(solution.error_ppm), error_ppm);
+        assert!(solution.error_ppm <= MAX_BIT_RATE_ERROR_PPM);
+        assert_eq!(
+            u64::from(solution.baud_rate),
+            (u64::from(module_freq) + bit_clocks / 2) / bit_clocks
         );
 
-        assert_eq!(best.tbaud, 20);
-        assert_eq!(best.brp, 8);
+        let sample_point_error =
+            u32::from(solution.sample_point).abs_diff(u32::from(config.sample_point));
+        assert!(sample_point_error <= 10_000 / segments.time_quanta());
     }
 
     #[test]
-    fn test_get_best_sample_point() {
-        let sample_point = 8000;
-        let best_tbaud = 20;
+    fn test_nominal_exhaustive() {
+        for module_freq in MODULE_FREQUENCIES {
+            for baud_rate in NOMINAL_BAUD_RATES {
+                for sample_point in (MIN_SAMPLE_POINT..=MAX_SAMPLE_POINT).step_by(250) {
+                    check(
+                        module_freq,
+                        &auto(baud_rate, sample_point, 0),
+                        &NOMINAL_LIMITS,
+                    );
+                    check(
+                        module_freq,
+                        &auto(baud_rate, sample_point, 1),
+                        &NOMINAL_LIMITS,
+                    );
+                }
+            }
+        }
+    }
 
-        let (best_tseg1, best_tseg2) =
-            get_best_sample_point(NBTP_NTSEG1_MSK, NBTP_NTSEG2_MSK, best_tbaud, sample_point);
+    #[test]
+    fn test_data_exhaustive() {
+        // 20 MHz gives fewer than 8 time quanta at 4 and 5 Mbit/s
+        for module_freq in [40_000_000, 80_000_000, 100_000_000] {
+            for baud_rate in DATA_BAUD_RATES {
+                for sample_point in (6_000..=MAX_SAMPLE_POINT).step_by(500) {
+                    check(module_freq, &auto(baud_rate, sample_point, 0), &DATA_LIMITS);
+                }
+            }
+        }
+    }
 
-        assert_eq!(best_tseg1, 15);
-        assert_eq!(best_tseg2, 4);
+    #[test]
+    fn test_nominal_1mbps() {
+        let solution = calculate_bit_timing(80_000_000, &auto(1_000_000, 8_000, 3)).unwrap();
+
+        assert_eq!(solution.baud_rate, 1_000_000);
+        assert_eq!(solution.error_ppm, 0);
+        assert_eq!(solution.sample_point, 8_000);
+        assert_eq!(solution.sync_jump_width, 3);
+
+        let timing = solution.timing;
+        assert_eq!(timing.brp, 0);
+        assert_eq!(timing.sjw, 2);
+        assert_eq!(timing.tseg1, 62);
+        assert_eq!(timing.tseg2, 15);
+        assert_eq!(timing.clocks_per_bit(), 80);
     }
 
     #[test]
-    fn test_get_best_sjw() {
-        let best_tboud = 20;
-        let best_tseg2 = 4;
-        let sync_jump_width = 3;
-        let best_sjw = get_best_sjw(best_tboud, best_tseg2, sync_jump_width);
+    fn test_data_5mbps() {
+        let nominal = NominalBitTiming::new(1, 16, 63, 16).unwrap();
+        let solution = calculate_fast_bit_timing(
+            80_000_000,
+            &auto(5_000_000, 7_500, 0),
+            nominal,
+            TransceiverDelayCompensation::Auto,
+        )
+        .unwrap();
+
+        assert_eq!(solution.baud_rate, 5_000_000);
+        assert_eq!(solution.sample_point, 7_500);
+        assert_eq!(solution.sync_jump_width, 4);
+
+        let timing = solution.timing;
+        assert_eq!(timing.brp, 0);
+        assert_eq!(timing.tseg1, 10);
+        assert_eq!(timing.tseg2, 3);
+        assert_eq!(timing.sjw, 3);
+    }
 
-        assert_eq!(best_sjw, 1);
+    #[test]
+    fn test_smallest_prescaler() {
+        // 500 kbit/s at 80 MHz is reachable with 160 time quanta without prescaler
+        let solution = solve(80_000_000, &auto(500_000, 8_000, 0), &NOMINAL_LIMITS, None).unwrap();
+        assert_eq!(solution.timing.brp, 1);
+        assert_eq!(solution.timing.time_quanta(), 160);
+
+        // 10 kbit/s needs 8000 clocks per bit, more than 385 time quanta
+        let solution = solve(80_000_000, &auto(10_000, 8_000, 0), &NOMINAL_LIMITS, None).unwrap();
+        assert_eq!(solution.timing.brp, 25);
+        assert_eq!(solution.timing.time_quanta(), 320);
+    }
+
+    #[test]
+    fn test_same_prescaler() {
+        // 250 kbit/s at 80 MHz with 80 time quanta of 50 ns, 2 Mbit/s needing 10 of them
+        let nominal = NominalBitTiming::new(4, 16, 63, 16).unwrap();
+        let solution = calculate_fast_bit_timing(
+            80_000_000,
+            &auto(2_000_000, 8_000, 0),
+            nominal,
+            TransceiverDelayCompensation::Disabled,
+        )
+        .unwrap();
+        assert_eq!(solution.timing.brp, 3);
+        assert_eq!(solution.baud_rate, 2_000_000);
+
+        // the transmitter delay compensation needs a prescaler of 1 or 2
+        let solution = calculate_fast_bit_timing(
+            80_000_000,
+            &auto(2_000_000, 8_000, 0),
+            nominal,
+            TransceiverDelayCompensation::Auto,
+        )
+        .unwrap();
+        assert_eq!(solution.timing.brp, 0);
+
+        // 4 Mbit/s needs fewer than 8 time quanta with the nominal prescaler
+        let solution = solve(
+            80_000_000,
+            &auto(4_000_000, 8_000, 0),
+            &DATA_LIMITS,
+            Some(4),
+        );
+        assert_eq!(solution.unwrap().timing.brp, 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let solve_nominal =
+            |config: AutoBitTiming| solve(80_000_000, &config, &NOMINAL_LIMITS, None);
+
+        assert_eq!(
+            solve_nominal(auto(0, 8_000, 0)).unwrap_err(),
+            BitTimingError::InvalidBaudRate
+        );
+        assert_eq!(
+            solve_nominal(auto(500_000, 4_000, 0)).unwrap_err(),
+            BitTimingError::InvalidSamplePoint
+        );
+        assert_eq!(
+            solve_nominal(auto(500_000, 9_500, 0)).unwrap_err(),
+            BitTimingError::InvalidSamplePoint
+        );
+        // bits of at most 512 * 385 clocks
+        assert_eq!(
+            solve_nominal(auto(100, 8_000, 0)).unwrap_err(),
+            BitTimingError::BaudRateNotReachable
+        );
+        // fewer than 8 time quanta per bit
+        assert_eq!(
+            solve_nominal(auto(20_000_000, 8_000, 0)).unwrap_err(),
+            BitTimingError::BaudRateNotReachable
+        );
+        // 11.43 clocks per bit, 3.9% error
+        assert_eq!(
+            solve(80_000_000, &auto(7_000_000, 8_000, 0), &DATA_LIMITS, None).unwrap_err(),
+            BitTimingError::BaudRateNotReachable
+        );
+        // phase segment 2 of 16 time quanta
+        assert_eq!(
+            solve_nominal(auto(1_000_000, 8_000, 17)).unwrap_err(),
+            BitTimingError::InvalidSyncJumpWidth
+        );
+        // phase segment 2 limited to 16 time quanta out of 40
+        assert_eq!(split_bit(40, 5_000, &DATA_LIMITS), None);
+        assert_eq!(split_bit(20, 5_000, &DATA_LIMITS), Some((9, 10)));
+    }
+
+    #[test]
+    fn test_delay_compensation() {
+        // 5 Mbit/s at 80 MHz, 16 clocks per bit with the sample point after 12 clocks
+        let timing = DataBitTiming::new(1, 4, 11, 4).unwrap();
+        assert_eq!(timing.sample_point_clocks(), 12);
+
+        assert_eq!(
+            timing.delay_compensation(80_000_000, None).unwrap_err(),
+            BitTimingError::DelayCompensationRequired
+        );
+        assert_eq!(
+            timing.delay_compensation(80_000_000, Some((12, 0))),
+            Ok(Some((12, 0)))
+        );
+        assert_eq!(
+            timing
+                .delay_compensation(80_000_000, Some((128, 0)))
+                .unwrap_err(),
+            BitTimingError::InvalidDelayCompensation
+        );
+
+        // 2 Mbit/s does not require the compensation
+        assert_eq!(timing.delay_compensation(32_000_000, None), Ok(None));
+
+        // prescaler of 4
+        let timing = DataBitTiming::new(4, 1, 2, 1).unwrap();
+        assert_eq!(
+            timing
+                .delay_compensation(80_000_000, Some((12, 0)))
+                .unwrap_err(),
+            BitTimingError::InvalidDelayCompensation
+        );
+    }
+
+    #[test]
+    fn test_manual() {
+        assert!(NominalBitTiming::new(1, 16, 63, 16).is_ok());
+        assert!(NominalBitTiming::new(512, 128, 256, 128).is_ok());
+        assert_eq!(
+            NominalBitTiming::new(513, 1, 63, 16).unwrap_err(),
+            BitTimingError::OutOfRange
+        );
+        assert_eq!(
+            NominalBitTiming::new(1, 1, 1, 16).unwrap_err(),
+            BitTimingError::OutOfRange
+        );
+        assert_eq!(
+            NominalBitTiming::new(1, 17, 63, 16).unwrap_err(),
+            BitTimingError::InvalidSyncJumpWidth
+        );
+        assert!(DataBitTiming::new(32, 16, 32, 16).is_ok());
+        assert_eq!(
+            DataBitTiming::new(1, 1, 33, 16).unwrap_err(),
+            BitTimingError::OutOfRange
+        );
+        assert_eq!(
+            DataBitTiming::new(0, 1, 10, 3).unwrap_err(),
+            BitTimingError::OutOfRange
+        );
     }
 }
@@ -2,7 +2,8 @@
 
 mod service_request;
 
-use super::can_node::{Node, NodeConfig};
+use super::can_node::{Node, NodeConfig, Pins};
+use super::ram_layout::MESSAGE_RAM_SIZE;
 use crate::can::NodeId;
 use crate::util::wait_nop_cycles;
 use crate::{pac, scu};
@@ -62,7 +63,7 @@ macro_rules! impl_can_module {
 
         impl Module<$ModuleId, $ModuleReg, Enabled> {
             /// Take ownership of a CAN node and configure it
-            pub fn take_node<I>(&mut self, node_id: I, config: NodeConfig) -> Option<Node<$($m)::+::N, $ModuleReg, I, crate::can::can_node::Configurable>> where I: NodeId {
+            pub fn take_node<I>(&mut self, node_id: I, config: NodeConfig) -> Option<Node<$($m)::+::N, $ModuleReg, I, crate::can::can_node::Configurable>> where I: NodeId, $ModuleReg: crate::gpio::alt::CanCommon<I> {
                 let node_index = node_id.as_index();
 
                 #[allow(clippy::indexing_slicing)]
@@ -80,6 +81,39 @@ macro_rules! impl_can_module {
                 Node::<$($m)::+::N, $ModuleReg, I, crate::can::can_node::Configurable>::new(self, node_id, config).ok()
             }
 
+            /// Give back a node taken with [`Self::take_node`], so that it can be taken again.
+            /// The node is stopped in initialization mode and its pins, if connected, are
+            /// returned.
+            pub fn release_node<I, State>(&mut self, node: Node<$($m)::+::N, $ModuleReg, I, State>) -> Option<Pins<$ModuleReg, I>> where I: NodeId, $ModuleReg: crate::gpio::alt::CanCommon<I> {
+                if let Some(flag) = self.nodes_taken.get_mut(I::INDEX) {
+                    *flag = false;
+                }
+
+                node.stop()
+            }
+
+            /// Disable the CAN module. All the nodes must have been released with
+            /// [`Self::release_node`], otherwise the module is given back unchanged.
+            pub fn disable(self) -> Result<Module<$ModuleId, $ModuleReg, Disabled>, Self> {
+                if self.nodes_taken.iter().any(|taken| *taken) {
+                    return Err(self);
+                }
+
+                scu::wdt::clear_cpu_endinit_inline();
+
+                // SAFETY: DISR is a RW bit, bits 2 and 31:4 are written with 0
+                unsafe { $module_reg.clc().modify_atomic(|r| r.disr().set(true)) };
+                // SAFETY: DISS is a RH bit
+                while !unsafe { $module_reg.clc().read() }.diss().get() {}
+
+                scu::wdt::set_cpu_endinit_inline();
+
+                Ok(Module::<$ModuleId, $ModuleReg, Disabled> {
+                    nodes_taken: [false; 4],
+                    _phantom: PhantomData,
+                })
+            }
+
             pub(crate) fn set_clock_source(
                 &self,
                 clock_select: ClockSelect,
@@ -145,9 +179,10 @@ macro_rules! impl_can_module {
             }
 
             pub(crate) fn ram_base_address(&self) -> u32 {
-                // TODO Ugly hack to obtain the ram base addresssize
-                // This is needed because current pac does not provide it
-                ($module_reg.accen0().ptr() as u32) - 33020u32
+                // The pac does not provide the message RAM. In the TC37x memory map each MCMCAN
+                // module occupies 64 KB, CAN0 from 0xF020_0000 and CAN1 from 0xF021_0000, with the
+                // 32 KB message RAM at offset 0 and the module registers from CLC at offset 0x8000.
+                ($module_reg.clc().ptr() as u32) - MESSAGE_RAM_SIZE
             }
         }
     };
@@ -184,3 +219,27 @@ impl From<ClockSource> for u8 {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn enabled<M, R>() -> Module<M, R, Enabled> {
+        Module {
+            nodes_taken: [false; 4],
+            _phantom: PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_ram_base_address() {
+        assert_eq!(
+            enabled::<Module0, pac::can0::Can0>().ram_base_address(),
+            0xF020_0000
+        );
+        assert_eq!(
+            enabled::<Module1, pac::can1::Can1>().ram_base_address(),
+            0xF021_0000
+        );
+    }
+}
@@ -1,8 +1,8 @@
 #![allow(clippy::module_name_repetitions)]
 
 use crate::can::{
-    BitTimingConfig, ClockSource, FastBitTimingConfig, FrameMode, Interrupt, InterruptGroup,
-    InterruptLine, Tos,
+    BitTimingConfig, BusOffRecovery, ClockSource, FastBitTimingConfig, FrameMode, Interrupt,
+    InterruptGroup, InterruptLine, InterruptSet, Tos,
 };
 use crate::cpu::Priority;
 
@@ -14,11 +14,87 @@ pub struct NodeInterruptConfig {
     pub tos: Tos,
 }
 
+/// Routing of an interrupt group to a service request line
+#[derive(Clone, Copy)]
+pub struct InterruptRoute {
+    pub group: InterruptGroup,
+    pub line: InterruptLine,
+    pub priority: Priority,
+    pub tos: Tos,
+}
+
+/// Set of interrupts to enable, with the routing of their groups
+pub struct NodeInterruptsConfig<'a> {
+    pub interrupts: InterruptSet,
+    pub routes: &'a [InterruptRoute],
+}
+
+/// Clock of the timestamp counter
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum TimestampSource {
+    /// The counter is not running, timestamps are always 0
+    #[default]
+    Disabled,
+    /// The counter is incremented every `prescaler` nominal bit times
+    Internal,
+    /// The counter is incremented by the external timestamp clock
+    External,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct TimestampConfig {
+    pub source: TimestampSource,
+    /// Prescaler of the internal source, in range [1, 16]
+    pub prescaler: u8,
+}
+
+/// Transmitter delay compensation of the CAN FD data phase.
+///
+/// Data phases faster than [`crate::can::TDC_REQUIRED_ABOVE_BAUD_RATE`] are only accepted
+/// with the compensation enabled, which needs a data bit rate prescaler of 1 or 2.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum TransceiverDelayCompensation {
+    #[default]
+    Disabled,
+    /// The secondary sample point is placed at the data phase sample point, without
+    /// filter window
+    Auto,
+    /// Offset and filter window in module clock cycles, in range [0, 127]
+    Manual { offset: u8, filter_window: u8 },
+}
+
+/// Operating mode of a node
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OperatingMode {
+    #[default]
+    Normal,
+    /// Bus monitoring: frames are received but the node only sends recessive bits,
+    /// it neither acknowledges frames nor signals errors
+    ListenOnly,
+    /// Restricted operation: frames are received and acknowledged, but no error frame,
+    /// overload frame or data frame is sent
+    Restricted,
+    /// Test mode where transmitted frames are received back internally and nothing is
+    /// driven on the bus
+    InternalLoopback,
+    /// Test mode where transmitted frames are received back internally and also sent on
+    /// the bus, their acknowledge errors being ignored
+    ExternalLoopback,
+    /// Connect the node to the loop-back bus shared by the nodes of the module, instead
+    /// of its pins
+    ModuleLoopback,
+}
+
 #[derive(Default)]
 pub struct NodeConfig {
     pub clock_source: ClockSource,
     pub baud_rate: BitTimingConfig,
     pub fast_baud_rate: FastBitTimingConfig,
-    pub transceiver_delay_offset: u8,
+    pub transceiver_delay_compensation: TransceiverDelayCompensation,
     pub frame_mode: FrameMode,
+    pub operating_mode: OperatingMode,
+    pub bus_off_recovery: BusOffRecovery,
+    pub timestamp: TimestampConfig,
+    /// Byte appended to CAN FD payloads up to the next valid data length
+    pub padding_byte: u8,
 }
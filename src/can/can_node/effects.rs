@@ -1,14 +1,23 @@
 use crate::can::baud_rate::{DataBitTiming, NominalBitTiming};
-use crate::can::can_node::{Interrupt, RxFifoMode, RxSel};
+use crate::can::can_node::{ErrorCounters, Interrupt, ProtocolStatus, RxFifoMode, RxSel};
 use crate::can::msg::{ReadFrom, RxBufferId, TxBufferId};
 use crate::can::{DataFieldSize, TxMode};
 use crate::pac;
 use pac::RegisterValue;
 
+/// Valid bits of the IR and IE registers, bits 20, 21 and 31:30 are reserved
+pub(super) const INTERRUPT_MASK: u32 = 0x3FCF_FFFF;
+
 pub(super) struct NodeEffects<T> {
     reg: T,
 }
 
+/// Release of a read Rx element, so that the node can store a new message in it
+pub(super) trait AcknowledgeRx {
+    /// Acknowledge the FIFO get index, or clear the new data flag of a dedicated Rx buffer
+    fn acknowledge_rx_element(&self, from: ReadFrom, buffer_id: RxBufferId);
+}
+
 macro_rules! impl_can_node_effect {
     ($NodeReg:path) => {
         impl NodeEffects<$NodeReg> {
@@ -113,6 +122,18 @@ macro_rules! impl_can_node_effect {
                 };
             }
 
+            pub(crate) fn enable_tx_buffer_cancellation_interrupt(&self, tx_buffer_id: TxBufferId) {
+                let id: u8 = tx_buffer_id.into();
+                // SAFETY: each bit is RW, tx_buffer_id is in range [0, 31]
+                unsafe {
+                    self.reg.tx().txbciei().modify(|r| {
+                        let mut v = r.get_raw();
+                        v |= 1 << id;
+                        r.set_raw(v)
+                    })
+                };
+            }
+
             #[inline]
             pub(crate) fn set_dedicated_tx_buffers_number(&self, number: u8) {
                 // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_tx after node.effects.enable_configuration_change has been called in Node::new.
@@ -285,12 +306,80 @@ macro_rules! impl_can_node_effect {
                 } {}
             }
 
+            pub(crate) fn set_clock_stop_request(&self, enable: bool) {
+                // SAFETY: bits 11:10 and 31:16 are written with 0, CSR bit is RW
+                unsafe { self.reg.cccri().modify(|r| r.csr().set(enable)) };
+            }
+
+            pub(crate) fn is_clock_stop_acknowledged(&self) -> bool {
+                // SAFETY: CSA bit is RH
+                unsafe { self.reg.cccri().read() }.csa().get()
+            }
+
+            pub(crate) fn has_pending_tx_requests(&self) -> bool {
+                // SAFETY: each bit of TXBRPI is RH
+                unsafe { self.reg.tx().txbrpi().read() }.get_raw() != 0
+            }
+
+            pub(crate) fn is_initialization_enabled(&self) -> bool {
+                // SAFETY: INIT bit is RWH
+                unsafe { self.reg.cccri().read() }.init().get()
+            }
+
+            pub(crate) fn get_protocol_status(&self) -> ProtocolStatus {
+                // SAFETY: each bit of PSRI is at least R, reading resets LEC and DLEC
+                let psr = unsafe { self.reg.psri().read() };
+                ProtocolStatus {
+                    last_error_code: psr.lec().get().into(),
+                    data_last_error_code: psr.dlec().get().into(),
+                    activity: psr.act().get().into(),
+                    error_passive: psr.ep().get(),
+                    warning: psr.ew().get(),
+                    bus_off: psr.bo().get(),
+                    transmitter_delay_compensation_value: psr.tdcv().get(),
+                }
+            }
+
+            pub(crate) fn get_error_counters(&self) -> ErrorCounters {
+                // SAFETY: each bit of ECRI is R, reading resets CEL
+                let ecr = unsafe { self.reg.ecri().read() };
+                ErrorCounters {
+                    transmit: ecr.tec().get(),
+                    receive: ecr.rec().get(),
+                    receive_passive: ecr.rp().get(),
+                    logging: ecr.cel().get(),
+                }
+            }
+
+            pub(crate) fn set_timestamp_counter(&self, prescaler: u8, select: u8) {
+                // SAFETY: write is CCE and INIT protected: called in Node::new after node.effects.enable_configuration_change has been called.
+                // bits 15:2 and 31:20 are written with 0, prescaler is in range [0, 15] and select in range [0, 2]
+                unsafe {
+                    self.reg
+                        .tscci()
+                        .modify(|r| r.tcp().set(prescaler).tss().set(select))
+                };
+            }
+
+            pub(crate) fn get_timestamp_prescaler(&self) -> u8 {
+                // SAFETY: TCP is RW
+                unsafe { self.reg.tscci().read() }.tcp().get()
+            }
+
+            pub(crate) fn get_timestamp_select(&self) -> u8 {
+                // SAFETY: TSS is RW
+                unsafe { self.reg.tscci().read() }.tss().get()
+            }
+
+            pub(crate) fn get_timestamp_counter(&self) -> u16 {
+                // SAFETY: TSC is RH
+                unsafe { self.reg.tscvi().read() }.tsc().get()
+            }
+
             pub(crate) fn set_nominal_bit_timing(&self, timing: &NominalBitTiming) {
                 // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.configure_baud_rate after node.effects.enable_configuration_change has been called in Node::new.
-                // bit 7 is written with 0, TODO timing.brp should be in range [0, 2^9)
-                // timing.sjw should be in range [0, 2^7)
-                // timing.tseg1 should be in range [0, 2^8)
-                // timing.tseg2 should be in range [0, 2^7)
+                // bit 7 is written with 0, timing.brp is in range [0, 2^9), timing.sjw in range [0, 2^7),
+                // timing.tseg1 in range [1, 2^8) and timing.tseg2 in range [0, 2^7), checked by NominalBitTiming::new
                 unsafe {
                     self.reg.nbtpi().modify(|r| {
                         r.nbrp()
@@ -305,12 +394,39 @@ macro_rules! impl_can_node_effect {
                 }
             }
 
+            pub(crate) fn get_nominal_bit_timing(&self) -> NominalBitTiming {
+                // SAFETY: NBRP, NSJW, NTSEG1 and NTSEG2 are RW
+                let nbtp = unsafe { self.reg.nbtpi().read() };
+                NominalBitTiming {
+                    brp: nbtp.nbrp().get(),
+                    sjw: nbtp.nsjw().get(),
+                    tseg1: nbtp.ntseg1().get(),
+                    tseg2: nbtp.ntseg2().get(),
+                }
+            }
+
+            /// Raw DBTP and TDCR values, to be written back with `set_data_bit_timing_registers`
+            pub(crate) fn get_data_bit_timing_registers(&self) -> (u32, u32) {
+                // SAFETY: each bit of DBTPi is at least R
+                let dbtp = unsafe { self.reg.dbtpi().read() }.get_raw();
+                // SAFETY: each bit of TDCRi is at least R
+                let tdcr = unsafe { self.reg.tdcri().read() }.get_raw();
+                (dbtp, tdcr)
+            }
+
+            pub(crate) fn set_data_bit_timing_registers(&self, (dbtp, tdcr): (u32, u32)) {
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.detect_bit_timings after node.effects.enable_configuration_change has been called.
+                // the values have been read from the registers by get_data_bit_timing_registers, reserved bits are written with 0
+                unsafe { self.reg.dbtpi().modify(|r| r.set_raw(dbtp)) };
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.detect_bit_timings after node.effects.enable_configuration_change has been called.
+                // the values have been read from the registers by get_data_bit_timing_registers, reserved bits are written with 0
+                unsafe { self.reg.tdcri().modify(|r| r.set_raw(tdcr)) };
+            }
+
             pub(crate) fn set_data_bit_timing(&self, timing: &DataBitTiming) {
                 // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.configure_fast_baud_rate after node.effects.enable_configuration_change has been called in Node::new.
-                // bits 15:13, 22:21 and 31:24 are written with 0, TODO timing.brp should be in range [0, 2^5)
-                // timing.sjw should be in range [0, 2^4)
-                // timing.tseg1 should be in range [0, 2^5)
-                // timing.tseg2 should be in range [0, 2^4)
+                // bits 15:13, 22:21 and 31:24 are written with 0, timing.brp is in range [0, 2^5), timing.sjw in range [0, 2^4),
+                // timing.tseg1 in range [0, 2^5) and timing.tseg2 in range [0, 2^4), checked by DataBitTiming::new
                 unsafe {
                     self.reg.dbtpi().modify(|r| {
                         r.dbrp()
@@ -353,17 +469,46 @@ macro_rules! impl_can_node_effect {
                 };
             }
 
-            pub(crate) fn set_transceiver_delay_compensation_offset(&self, delay: u8) {
+            pub(crate) fn set_bus_monitoring(&self, enable: bool) {
+                // SAFETY: write is CCE and INIT protected: called in Node::new after node.effects.enable_configuration_change has been called.
+                // bits 11:10 and 31:16 are written with 0, MON bit is RW
+                unsafe { self.reg.cccri().modify(|r| r.mon().set(enable)) };
+            }
+
+            pub(crate) fn is_bus_monitoring(&self) -> bool {
+                // SAFETY: MON bit is RW
+                unsafe { self.reg.cccri().read() }.mon().get()
+            }
+
+            pub(crate) fn set_restricted_operation(&self, enable: bool) {
+                // SAFETY: write is CCE and INIT protected: called in Node::new after node.effects.enable_configuration_change has been called.
+                // bits 11:10 and 31:16 are written with 0, ASM bit is RW
+                unsafe { self.reg.cccri().modify(|r| r.asm().set(enable)) };
+            }
+
+            pub(crate) fn enable_test_loopback(&self) {
+                // SAFETY: write is CCE and INIT protected: called in Node::new after node.effects.enable_configuration_change has been called.
+                // bits 11:10 and 31:16 are written with 0, TEST bit is RW
+                unsafe { self.reg.cccri().modify(|r| r.test().set(true)) };
+                // SAFETY: TEST register is writable since CCCR.TEST has been set, bits 3:0, 31:8 are written with 0, LBCK bit is RW
+                unsafe { self.reg.testi().modify(|r| r.lbck().set(true)) };
+            }
+
+            pub(crate) fn set_transceiver_delay_compensation(&self, offset: u8, filter_window: u8) {
                 // SAFETY: write is CCE and INIT protected: called after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 15:13, 22:21 and 31:24 are written with 0, TDC bit is RW
                 unsafe { self.reg.dbtpi().modify(|r| r.tdc().set(true)) };
                 // SAFETY: write is CCE and INIT protected: called after node.effects.enable_configuration_change has been called in Node::new.
-                // bits 7 and 31:15 are written with 0, TODO delay should be in range [0, 2^7)
-                unsafe { self.reg.tdcri().modify(|r| r.tdco().set(delay)) };
+                // bits 7 and 31:15 are written with 0, offset and filter_window are in range [0, 2^7), checked by DataBitTiming::delay_compensation
+                unsafe {
+                    self.reg
+                        .tdcri()
+                        .modify(|r| r.tdco().set(offset).tdcf().set(filter_window))
+                };
             }
 
             pub(crate) fn enable_interrupt(&self, interrupt: Interrupt) {
-                // SAFETY: bits 20, 21, 29 and 31:30 are written with 0, interrupt is guaranteed to take only allowed values
+                // SAFETY: bits 20, 21 and 31:30 are written with 0, interrupt is guaranteed to take only allowed values
                 unsafe {
                     self.reg.iei().modify(|r| {
                         let mut v = r.get_raw();
@@ -375,7 +520,7 @@ macro_rules! impl_can_node_effect {
 
             #[inline]
             pub(crate) fn clear_interrupt_flag(&self, interrupt: Interrupt) {
-                // SAFETY: bits 20, 21, 29 and 31:30 are written with 0, interrupt is guaranteed to take only allowed values
+                // SAFETY: bits 20, 21 and 31:30 are written with 0, interrupt is guaranteed to take only allowed values
                 unsafe {
                     self.reg.iri().init(|r| {
                         let v = 1 << interrupt as u32;
@@ -384,6 +529,37 @@ macro_rules! impl_can_node_effect {
                 };
             }
 
+            pub(crate) fn enable_interrupts(&self, interrupts: u32) {
+                // SAFETY: bits 20, 21 and 31:30 are written with 0, interrupts is masked with the valid IE bits
+                unsafe {
+                    self.reg.iei().modify(|r| {
+                        let mut v = r.get_raw();
+                        v |= interrupts & INTERRUPT_MASK;
+                        r.set_raw(v)
+                    })
+                };
+            }
+
+            pub(crate) fn get_enabled_interrupts(&self) -> u32 {
+                // SAFETY: each bit of IEI is at least R
+                unsafe { self.reg.iei().read() }.get_raw()
+            }
+
+            pub(crate) fn get_interrupt_flags(&self) -> u32 {
+                // SAFETY: each bit of IRI is at least R
+                unsafe { self.reg.iri().read() }.get_raw()
+            }
+
+            #[inline]
+            pub(crate) fn clear_interrupt_flags(&self, interrupts: u32) {
+                // SAFETY: bits 20, 21 and 31:30 are written with 0, writing 0 to the other bits has no effect
+                unsafe {
+                    self.reg
+                        .iri()
+                        .init(|r| r.set_raw(interrupts & INTERRUPT_MASK))
+                };
+            }
+
             pub(crate) fn set_interrupt_routing_group_1(&self, line: u32, group: u32) {
                 // SAFETY: TODO: line should be in range [0, 16) and group should be in range [0, 8)
                 unsafe {
@@ -465,6 +641,22 @@ macro_rules! impl_can_node_effect {
                 unsafe { self.reg.tx().txefsi().read() }.eff().get() == true
             }
 
+            pub(crate) fn get_tx_event_fifo_fill_level(&self) -> u8 {
+                // SAFETY: EFFL is RH
+                unsafe { self.reg.tx().txefsi().read() }.effl().get()
+            }
+
+            pub(crate) fn get_tx_event_fifo_get_index(&self) -> u8 {
+                // SAFETY: EFGI is RH
+                unsafe { self.reg.tx().txefsi().read() }.efgi().get()
+            }
+
+            #[inline]
+            pub(crate) fn set_tx_event_fifo_acknowledge_index(&self, index: u8) {
+                // SAFETY: bits 31:5 are written with 0, index is in range [0, 2^5)
+                unsafe { self.reg.tx().txefai().modify(|r| r.efai().set(index)) };
+            }
+
             pub(crate) fn is_tx_fifo_queue_full(&self) -> bool {
                 // SAFETY: TFQF is RH
                 unsafe { self.reg.tx().txfqsi().read() }.tfqf().get() == true
@@ -481,35 +673,31 @@ macro_rules! impl_can_node_effect {
             }
 
             pub(crate) fn set_standard_filter_list_start_address(&self, address: u16) {
-                // SAFETY: write is CCE and INIT protected: TODO: never used
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_filters after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 1:0 and 31:24 are written with 0, TODO: address should be in range [0, 2^14)
                 unsafe { self.reg.sidfci().modify(|r| r.flssa().set(address >> 2)) };
             }
 
             pub(crate) fn set_standard_filter_list_size(&self, size: u8) {
-                // SAFETY: write is CCE and INIT protected: TODO: never used
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_filters after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 1:0 and 31:24 are written with 0, size is in range [0, 2^8)
                 unsafe { self.reg.sidfci().modify(|r| r.lss().set(size.into())) };
             }
 
-            pub(crate) fn reject_remote_frames_with_standard_id(&self) {
-                 // SAFETY: write is CCE and INIT protected: TODO: never used
+            pub(crate) fn reject_remote_frames_with_standard_id(&self, reject: bool) {
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_global_filter after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 31:6 are written with 0, RRFS is a RW bit
-                unsafe {
-                    self.reg
-                        .gfci()
-                        .modify(|r| r.rrfs().set(true))
-                };
+                unsafe { self.reg.gfci().modify(|r| r.rrfs().set(reject)) };
             }
 
             pub(crate) fn set_extended_filter_list_start_address(&self, address: u16) {
-                // SAFETY: write is CCE and INIT protected: TODO: never used
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_filters after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 1:0 and 31:24 are written with 0, TODO: address should be in range [0, 2^14)
                 unsafe { self.reg.xidfci().modify(|r| r.flesa().set(address >> 2)) };
             }
 
             pub(crate) fn set_extended_filter_list_size(&self, size: u8) {
-                // SAFETY: write is CCE and INIT protected: TODO: never used
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_filters after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 1:0 and 31:24 are written with 0, size is in range [0, 2^8)
                 unsafe {
                     self.reg
@@ -518,10 +706,22 @@ macro_rules! impl_can_node_effect {
                 };
             }
 
-            pub(crate) fn reject_remote_frames_with_extended_id(&self) {
-                 // SAFETY: write is CCE and INIT protected: TODO: never used
+            pub(crate) fn reject_remote_frames_with_extended_id(&self, reject: bool) {
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_global_filter after node.effects.enable_configuration_change has been called in Node::new.
                 // bits 31:6 are written with 0, RRFE is a RW bit
-                unsafe { self.reg.gfci().modify(|r| r.rrfe().set(true)) };
+                unsafe { self.reg.gfci().modify(|r| r.rrfe().set(reject)) };
+            }
+
+            pub(crate) fn set_non_matching_frames_standard(&self, anfs: u8) {
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_global_filter after node.effects.enable_configuration_change has been called in Node::new.
+                // bits 31:6 are written with 0, anfs is in range [0, 2]
+                unsafe { self.reg.gfci().modify(|r| r.anfs().set(anfs)) };
+            }
+
+            pub(crate) fn set_non_matching_frames_extended(&self, anfe: u8) {
+                // SAFETY: write is CCE and INIT protected: called in Node<Configurable>.setup_global_filter after node.effects.enable_configuration_change has been called in Node::new.
+                // bits 31:6 are written with 0, anfe is in range [0, 2]
+                unsafe { self.reg.gfci().modify(|r| r.anfe().set(anfe)) };
             }
 
             pub(crate) fn get_tx_fifo_queue_put_index(&self) -> u8 {
@@ -543,6 +743,14 @@ macro_rules! impl_can_node_effect {
                 unsafe { RxBufferId::new_unchecked(idx) }
             }
 
+            pub(crate) fn get_rx_buffers_new_data(&self) -> (u32, u32) {
+                // SAFETY: each bit of NDAT1i is RWH
+                let ndat1 = unsafe { self.reg.ndat1i().read() }.get_raw();
+                // SAFETY: each bit of NDAT2i is RWH
+                let ndat2 = unsafe { self.reg.ndat2i().read() }.get_raw();
+                (ndat1, ndat2)
+            }
+
             pub(crate) fn is_rx_buffer_new_data_updated(&self, rx_buffer_id: u8) -> bool {
                 let (data, mask) = if rx_buffer_id < 32 {
                     // last number value in the reg name is the node id
@@ -590,6 +798,25 @@ macro_rules! impl_can_node_effect {
                 (data & mask) != 0
             }
 
+            #[inline]
+            pub(crate) fn is_tx_buffer_cancellation_finished(&self, tx_buffer_id: u8) -> bool {
+                // SAFETY: each bit of TXBCFI is RH
+                let data = unsafe { self.reg.tx().txbcfi().read() }.get_raw();
+                let mask = 1u32 << u32::from(tx_buffer_id);
+                (data & mask) != 0
+            }
+
+            #[inline]
+            pub(crate) fn set_tx_buffer_cancellation_request(&self, tx_buffer_id: u8) {
+                // SAFETY: each bit of TXBCRI is RWH, writing 0 has no effect, tx_buffer_id is in range [0, 31]
+                unsafe {
+                    self.reg
+                        .tx()
+                        .txbcri()
+                        .init(|r| r.set_raw(1u32 << u32::from(tx_buffer_id)))
+                };
+            }
+
             #[inline]
             pub(crate) fn set_tx_buffer_add_request(&self, id: u8) {
                 let txbari = self.reg.tx().txbari();
@@ -684,7 +911,7 @@ macro_rules! impl_can_node_effect {
             pub(crate) fn get_tx_buffer_data_field_size(&self) -> u8 {
                 let size_code: u8 =
                 // SAFETY: each bit of TXESCI is at least R
-                    (unsafe { self.reg.tx().txesci().read() }.get_raw() & 0x2) as u8;
+                    (unsafe { self.reg.tx().txesci().read() }.get_raw() & 0x7) as u8;
                 if size_code < (DataFieldSize::_32 as u8) {
                     (size_code + 2) * 4
                 } else {
@@ -733,6 +960,18 @@ macro_rules! impl_can_node_effect {
                 }
             }
         }
+
+        impl AcknowledgeRx for NodeEffects<$NodeReg> {
+            fn acknowledge_rx_element(&self, from: ReadFrom, buffer_id: RxBufferId) {
+                // the new data flags only belong to the dedicated Rx buffers, not to the
+                // FIFO elements with the same index
+                match from {
+                    ReadFrom::RxFifo0 => self.set_rx_fifo0_acknowledge_index(buffer_id),
+                    ReadFrom::RxFifo1 => self.set_rx_fifo1_acknowledge_index(buffer_id),
+                    ReadFrom::Buffer(_) => self.clear_rx_buffer_new_data_flag(buffer_id),
+                }
+            }
+        }
     };
 }
 
@@ -3,26 +3,39 @@
 
 pub mod config;
 mod effects;
+mod status;
 
 use super::baud_rate::*;
-use super::frame::{DataLenghtCode, Frame};
-use super::internals::Tx;
-use super::msg::TxBufferId;
+use super::frame::{DataLenghtCode, Frame, OwnedFrame};
+use super::internals::{
+    write_filter_list, ExtendedFilter, Rx, StandardFilter, Tx, TxEventElement,
+};
+use super::msg::{
+    Filter, GlobalFilterConfig, MessageIdLength, RxBuffersNewData, Timestamp, TxBufferId,
+    TxEvent,
+};
+use super::pin_map::RxdIn;
+use super::rx_queue::RxProducer;
 use super::{can_module, Module, ModuleId};
 use crate::can::can_module::ClockSelect;
-use crate::can::can_node::effects::NodeEffects;
-use crate::can::config::NodeInterruptConfig;
+use crate::can::can_node::effects::{AcknowledgeRx, NodeEffects};
+use crate::can::config::{
+    NodeInterruptConfig, NodeInterruptsConfig, OperatingMode, TimestampConfig, TimestampSource,
+    TransceiverDelayCompensation,
+};
 use crate::can::msg::FrameMode;
 use crate::can::msg::MessageId;
 use crate::can::msg::ReadFrom;
 use crate::can::msg::RxMessage;
 use crate::cpu::Priority;
+use crate::gpio::alt::CanCommon;
+use crate::gpio::{PinPull, PinSpeed, Pull, Speed};
 use crate::log::info;
 use crate::pac::common::RegisterValue;
-use crate::scu::wdt_call;
+use crate::scu::ccu::wait_cond;
 pub use config::NodeConfig;
 use core::marker::PhantomData;
-use core::mem::transmute;
+pub use status::*;
 
 #[derive(PartialEq, Debug, Default)]
 pub enum FrameType {
@@ -55,8 +68,37 @@ pub enum RxMode {
     SharedAll,
 }
 
-// TODO Suspicious
-const TX_BUFFER_START_ADDRESS: u32 = 0x0440u32;
+impl RxMode {
+    const fn uses_fifo0(self) -> bool {
+        matches!(
+            self,
+            RxMode::Fifo0 | RxMode::SharedFifo0 | RxMode::SharedAll
+        )
+    }
+
+    const fn uses_fifo1(self) -> bool {
+        matches!(
+            self,
+            RxMode::Fifo1 | RxMode::SharedFifo1 | RxMode::SharedAll
+        )
+    }
+
+    const fn uses_buffers(self) -> bool {
+        matches!(
+            self,
+            RxMode::DedicatedBuffers
+                | RxMode::SharedFifo0
+                | RxMode::SharedFifo1
+                | RxMode::SharedAll
+        )
+    }
+}
+
+const STANDARD_FILTER_LIST_MAX_SIZE: u8 = 128;
+const EXTENDED_FILTER_LIST_MAX_SIZE: u8 = 64;
+const STANDARD_FILTER_ELEMENT_SIZE: u32 = 4;
+const EXTENDED_FILTER_ELEMENT_SIZE: u32 = 8;
+const TX_EVENT_FIFO_ELEMENT_SIZE: u32 = 8;
 
 pub trait NodeId {
     const INDEX: usize;
@@ -88,29 +130,138 @@ impl NodeId for Node3 {
 pub struct Configured;
 pub struct Configurable;
 
-pub struct Node<N, M, I: NodeId, State> {
+pub struct Node<N, M: CanCommon<I>, I: NodeId, State> {
     effects: NodeEffects<N>,
     frame_mode: FrameMode,
     ram_base_address: u32,
     _phantom: PhantomData<(M, I, State)>,
 
     rx_config: Option<RxConfig>,
+    tx_config: Option<TxConfig>,
+    bus_off: BusOffState,
+    /// Module clock cycles per timestamp counter increment and module clock frequency
+    timestamp_clock: Option<(u32, u32)>,
+    padding_byte: u8,
+    /// Pins connected by setup_pins
+    pins: Option<Pins<M, I>>,
+    /// The clock stop has been requested by sleep
+    sleeping: bool,
+    /// Dedicated Tx buffers sent when a remote frame with their ID is read, one bit per buffer
+    remote_responder_buffers: u32,
 }
 
 pub enum ConfigError {
     CannotSetClockSource,
+    InvalidBitTiming(BitTimingError),
+}
+
+/// Error returned by [`Node::sleep`], the node keeps running
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SleepError {
+    /// Transmission requests are still pending after waiting for them, they must complete
+    /// or be cancelled first
+    PendingTransmissions,
+    /// The node has not acknowledged the clock stop request
+    Timeout,
+}
+
+/// Register polls waiting for the clock stop acknowledge, long enough for the node to
+/// finish the frame in progress at low bit rates
+const CLOCK_STOP_TIMEOUT_COUNT: usize = 0x40_0000;
+
+/// Register polls waiting for a cancelled Tx buffer, long enough for the node to finish
+/// the frame in progress at low bit rates
+const CANCEL_TIMEOUT_COUNT: usize = 0x40_0000;
+
+/// Register polls per microsecond of listen time after which a baud rate candidate is
+/// rejected, in case the timestamp counter does not advance. A poll reads several
+/// registers and takes well over 10 ns.
+const LISTEN_POLLS_PER_MICROSECOND: usize = 100;
+
+/// Error returned by [`Node::cancel`], the cancellation request stays active
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CancelError {
+    /// The node has neither finished the cancellation nor the transmission
+    Timeout,
+}
+
+/// Status of a Tx buffer, see [`Node::tx_buffer_status`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TxBufferStatus {
+    /// No request has been added to the buffer
+    Idle,
+    /// A transmission request is pending
+    Pending,
+    /// The last request has been transmitted
+    Transmitted,
+    /// The last request has been cancelled
+    Cancelled,
 }
 
 pub enum TransmitError {
     Busy,
+    /// The payload has no valid data length code, or does not fit in the Tx element
+    /// data field size
+    InvalidDataLength,
+    InvalidAccess,
+    /// The buffer is not a dedicated Tx buffer, or Tx is not configured
+    InvalidBufferId,
+    /// The frame format is not enabled on the node, e.g. a CAN FD frame on a classic CAN node
+    UnsupportedFrameMode,
+}
+
+/// Error returned by the [`embedded_can::nb::Can`] implementation of a configured node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CanError {
+    /// The frame data length cannot be encoded in a data length code
     InvalidDataLength,
+    /// The Tx element cannot be accessed
     InvalidAccess,
+    /// The received message ID is not a valid standard or extended ID
+    InvalidId,
+    /// The node has no Tx FIFO/queue, or Rx is not configured
+    NotConfigured,
+}
+
+impl embedded_can::Error for CanError {
+    fn kind(&self) -> embedded_can::ErrorKind {
+        embedded_can::ErrorKind::Other
+    }
+}
+
+/// Error returned when reading a received message, see [`Node::receive`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReceiveError {
+    /// `setup_rx` must be called before receiving
+    RxNotConfigured,
+    /// The Rx FIFO is empty, or the dedicated Rx buffer holds no new message
+    Empty,
+    /// The destination is shorter than the `length` bytes of received data
+    DataTooShort { length: usize },
+    /// An Rx FIFO in overwrite mode cannot be borrowed with `receive_view`, because the
+    /// node overwrites the element at the get index when the FIFO is full
+    OverwriteMode,
+}
+
+#[derive(Debug)]
+pub enum FilterError {
+    /// `setup_rx` must be called before `setup_filters`
+    RxNotConfigured,
+    /// Filter number exceeds the list size (128 standard, 64 extended elements)
+    InvalidNumber,
+    /// ID does not fit the filter ID length
+    InvalidId,
+    /// Filter ID length must be either standard or extended
+    InvalidIdLength,
 }
 
 macro_rules! impl_can_node {
     ($ModuleReg:ty, $NodeReg:path, $ModuleId: ty) => {
         // Methods only valid on a configurable node
-        impl<I: NodeId> Node<$NodeReg, $ModuleReg, I, Configurable> {
+        impl<I: NodeId> Node<$NodeReg, $ModuleReg, I, Configurable>
+        where
+            $ModuleReg: CanCommon<I>,
+        {
             /// Only a module can create a self. This function is only accessible from within this crate.
             pub(super) fn new(
                 module: &mut Module<$ModuleId, $ModuleReg, can_module::Enabled>,
@@ -127,27 +278,41 @@ macro_rules! impl_can_node {
                     .set_clock_source(clock_select, config.clock_source)
                     .map_err(|_| ConfigError::CannotSetClockSource)?;
 
-                let node = Self {
+                let mut node = Self {
                     effects,
                     _phantom: PhantomData,
                     frame_mode: config.frame_mode,
                     ram_base_address: module.ram_base_address(),
                     rx_config: None,
+                    tx_config: None,
+                    bus_off: BusOffState::new(config.bus_off_recovery),
+                    timestamp_clock: None,
+                    remote_responder_buffers: 0,
+                    padding_byte: config.padding_byte,
+                    pins: None,
+                    sleeping: false,
                 };
 
                 node.effects.enable_configuration_change();
 
-                node.configure_baud_rate(&config.baud_rate);
+                let bit_timing = node
+                    .configure_baud_rate(&config.baud_rate)
+                    .map_err(ConfigError::InvalidBitTiming)?;
 
-                // for CAN FD frames, set fast baud rate
-                if config.frame_mode != FrameMode::Standard {
-                    node.configure_fast_baud_rate(&config.fast_baud_rate);
+                node.configure_operating_mode(config.operating_mode);
+
+                if config.timestamp.source != TimestampSource::Disabled {
+                    node.configure_timestamp(&config.timestamp, &bit_timing);
                 }
 
-                // TODO Check if transceiver_delay_offset is needed only for CAN FD
-                if config.transceiver_delay_offset != 0 {
-                    node.effects
-                        .set_transceiver_delay_compensation_offset(config.transceiver_delay_offset);
+                // for CAN FD frames, set fast baud rate and transmitter delay compensation,
+                // which is only used in the data phase
+                if config.frame_mode != FrameMode::Standard {
+                    node.configure_fast_baud_rate(
+                        &config.fast_baud_rate,
+                        config.transceiver_delay_compensation,
+                    )
+                    .map_err(ConfigError::InvalidBitTiming)?;
                 }
 
                 Ok(node)
@@ -163,10 +328,75 @@ macro_rules! impl_can_node {
                     frame_mode: self.frame_mode,
                     ram_base_address: self.ram_base_address,
                     rx_config: self.rx_config,
+                    tx_config: self.tx_config,
+                    bus_off: self.bus_off,
+                    timestamp_clock: self.timestamp_clock,
+                    remote_responder_buffers: self.remote_responder_buffers,
+                    padding_byte: self.padding_byte,
+                    pins: self.pins,
+                    sleeping: self.sleeping,
                 }
             }
 
-            pub fn setup_tx(&self, tx_config: &TxConfig) {
+            /// Change the nominal bit timing, e.g. on a node made configurable again with
+            /// [`Node::reconfigure`]. The internal timestamp counter follows the new bit time.
+            pub fn set_baud_rate(
+                &mut self,
+                baud_rate: &BitTimingConfig,
+            ) -> Result<(), ConfigError> {
+                let bit_timing = self
+                    .configure_baud_rate(baud_rate)
+                    .map_err(ConfigError::InvalidBitTiming)?;
+
+                if let Some((_, module_freq)) = self.timestamp_clock {
+                    let prescaler = u32::from(self.effects.get_timestamp_prescaler()) + 1;
+                    self.timestamp_clock =
+                        Some((prescaler * bit_timing.clocks_per_bit(), module_freq));
+                }
+
+                Ok(())
+            }
+
+            /// Change the data phase bit timing and the transmitter delay compensation of a
+            /// CAN FD node. An automatic bit timing keeps the prescaler of the configured
+            /// nominal bit timing when possible, so the nominal baud rate is set first.
+            pub fn set_fast_baud_rate(
+                &mut self,
+                baud_rate: &FastBitTimingConfig,
+                delay_compensation: TransceiverDelayCompensation,
+            ) -> Result<(), ConfigError> {
+                self.configure_fast_baud_rate(baud_rate, delay_compensation)
+                    .map_err(ConfigError::InvalidBitTiming)
+            }
+
+            /// Find the baud rate of an unknown bus without disturbing the traffic. The node
+            /// listens in bus monitoring mode with each candidate bit timing in turn, until
+            /// enough frames are received without any error. The data phase candidates are
+            /// then tried the same way with frames using bit rate switching.
+            ///
+            /// Frames are counted in the Rx FIFOs and dedicated Rx buffers, so Rx and the
+            /// filters must be set up to store the bus traffic, for CAN FD frames too. The
+            /// received frames are left there.
+            ///
+            /// The matching bit timings are left configured and returned, `None` is returned
+            /// if no nominal candidate matched. The original bit timings are restored for a
+            /// phase without any matching candidate, and on error.
+            pub fn detect_baud_rate(
+                &mut self,
+                detection: &BaudRateDetection,
+            ) -> Result<Option<DetectedBaudRate>, ConfigError> {
+                let bus_monitoring = self.effects.is_bus_monitoring();
+                self.effects.set_bus_monitoring(true);
+
+                let detected = self.detect_bit_timings(detection);
+
+                self.effects.set_bus_monitoring(bus_monitoring);
+                detected
+            }
+
+            pub fn setup_tx(&mut self, tx_config: &TxConfig) {
+                self.tx_config = Some(*tx_config);
+
                 self.set_tx_buffer_data_field_size(tx_config.buffer_data_field_size);
                 self.effects
                     .set_tx_buffer_start_address(tx_config.tx_buffers_start_address);
@@ -191,8 +421,7 @@ macro_rules! impl_can_node {
                             0..tx_config.dedicated_tx_buffers_number + tx_config.fifo_queue_size
                         {
                             if let Ok(tx_buffer_id) = TxBufferId::try_from(id) {
-                                self.effects
-                                    .enable_tx_buffer_transmission_interrupt(tx_buffer_id);
+                                self.enable_tx_buffer_interrupts(tx_buffer_id);
                             }
                         }
                     }
@@ -202,8 +431,7 @@ macro_rules! impl_can_node {
                             .set_transmit_fifo_queue_size(tx_config.fifo_queue_size);
                         for id in 0..tx_config.fifo_queue_size {
                             if let Ok(tx_buffer_id) = TxBufferId::try_from(id) {
-                                self.effects
-                                    .enable_tx_buffer_transmission_interrupt(tx_buffer_id);
+                                self.enable_tx_buffer_interrupts(tx_buffer_id);
                             }
                         }
                     }
@@ -277,25 +505,95 @@ macro_rules! impl_can_node {
                 self.set_frame_mode(self.frame_mode);
             }
 
-            // TODO I think this should accept pins as provided by gpio module
-            pub fn setup_pins(&self, pins: Option<&Pins<$ModuleId, I>>) {
-                match pins {
-                    Some(pins) => {
-                        self.connect_pin_rx(
-                            &pins.rx,
-                            InputMode::PULL_UP,
-                            PadDriver::CmosAutomotiveSpeed3,
-                        );
-                        self.connect_pin_tx(
-                            &pins.tx,
-                            OutputMode::PUSH_PULL,
-                            PadDriver::CmosAutomotiveSpeed3,
-                        );
+            /// Connect the node to its pins, taken from the [`crate::gpio`] module. Only the
+            /// pins of this node are accepted, see [`CanCommon`]. The RXD pin is pulled up and
+            /// the TXD pin is driven by the node.
+            ///
+            /// Without a call the node is left unconnected, which is only useful with
+            /// [`OperatingMode::InternalLoopback`] or [`OperatingMode::ModuleLoopback`].
+            pub fn setup_pins(
+                &mut self,
+                tx: impl Into<<$ModuleReg as CanCommon<I>>::Tx>,
+                rx: impl Into<<$ModuleReg as CanCommon<I>>::Rx>,
+            ) where
+                <$ModuleReg as CanCommon<I>>::Tx: PinSpeed,
+                <$ModuleReg as CanCommon<I>>::Rx: RxdIn,
+            {
+                let mut rx = rx.into();
+                rx.set_internal_resistor(Pull::Up);
+                if let Some(select) = rx.select() {
+                    self.effects.connect_pin_rx(select);
+                }
+
+                let tx = tx.into().speed(Speed::High);
+
+                self.pins = Some(Pins { tx, rx });
+            }
+
+            /// Write the acceptance filter elements into the message RAM and configure the
+            /// standard and extended filter lists. The lists are placed at the addresses
+            /// given in [`RxConfig`] and sized to hold the highest filter number of each kind,
+            /// the elements below it without a filter are written disabled.
+            pub fn setup_filters(&self, filters: &[Filter]) -> Result<(), FilterError> {
+                let Some(rx_config) = self.rx_config else {
+                    return Err(FilterError::RxNotConfigured);
+                };
+
+                for filter in filters {
+                    let (max_number, max_id) = match filter.id_length {
+                        MessageIdLength::Standard => (STANDARD_FILTER_LIST_MAX_SIZE, 0x7FF),
+                        MessageIdLength::Extended => (EXTENDED_FILTER_LIST_MAX_SIZE, 0x1FFF_FFFF),
+                        MessageIdLength::Both => return Err(FilterError::InvalidIdLength),
+                    };
+                    if filter.number >= max_number {
+                        return Err(FilterError::InvalidNumber);
                     }
-                    None => {
-                        self.effects.enable_loopback();
+                    if filter.id1 > max_id || filter.id2 > max_id {
+                        return Err(FilterError::InvalidId);
                     }
-                };
+                }
+
+                let standard_list = self.ram_base_address
+                    + u32::from(rx_config.standard_filter_list_start_address);
+                let standard_list_size =
+                    write_filter_list(filters, MessageIdLength::Standard, |number| {
+                        let address =
+                            standard_list + u32::from(number) * STANDARD_FILTER_ELEMENT_SIZE;
+                        StandardFilter::new(address as *mut u8)
+                    });
+                let extended_list = self.ram_base_address
+                    + u32::from(rx_config.extended_filter_list_start_address);
+                let extended_list_size =
+                    write_filter_list(filters, MessageIdLength::Extended, |number| {
+                        let address =
+                            extended_list + u32::from(number) * EXTENDED_FILTER_ELEMENT_SIZE;
+                        ExtendedFilter::new(address as *mut u8)
+                    });
+
+                self.effects.set_standard_filter_list_start_address(
+                    rx_config.standard_filter_list_start_address,
+                );
+                self.effects
+                    .set_standard_filter_list_size(standard_list_size);
+                self.effects.set_extended_filter_list_start_address(
+                    rx_config.extended_filter_list_start_address,
+                );
+                self.effects
+                    .set_extended_filter_list_size(extended_list_size);
+
+                Ok(())
+            }
+
+            /// Configure how frames not matching any filter element and remote frames are handled
+            pub fn setup_global_filter(&self, config: &GlobalFilterConfig) {
+                self.effects
+                    .set_non_matching_frames_standard(config.non_matching_standard.into());
+                self.effects
+                    .set_non_matching_frames_extended(config.non_matching_extended.into());
+                self.effects
+                    .reject_remote_frames_with_standard_id(config.reject_remote_standard);
+                self.effects
+                    .reject_remote_frames_with_extended_id(config.reject_remote_extended);
             }
 
             pub fn setup_interrupt(&self, interrupt: &NodeInterruptConfig) {
@@ -308,6 +606,16 @@ macro_rules! impl_can_node {
                 );
             }
 
+            /// Route each interrupt group to its line and enable all the given interrupts
+            pub fn setup_interrupts(&self, config: &NodeInterruptsConfig) {
+                for route in config.routes {
+                    self.set_group_interrupt_line(route.group, route.line);
+                    <$ModuleId>::service_request(route.line).enable(route.priority, route.tos);
+                }
+
+                self.effects.enable_interrupts(config.interrupts.0);
+            }
+
             fn set_rx_fifo0(&self, data: FifoData) {
                 self.effects.set_rx_fifo0_data_field_size(data.field_size);
                 self.effects.set_rx_fifo0_start_address(data.start_address);
@@ -342,12 +650,18 @@ macro_rules! impl_can_node {
             fn set_inner_tx_int(&self, size: u8) {
                 for id in 0..size {
                     if let Ok(tx_buffer_id) = TxBufferId::try_from(id) {
-                        self.effects
-                            .enable_tx_buffer_transmission_interrupt(tx_buffer_id);
+                        self.enable_tx_buffer_interrupts(tx_buffer_id);
                     }
                 }
             }
 
+            fn enable_tx_buffer_interrupts(&self, tx_buffer_id: TxBufferId) {
+                self.effects
+                    .enable_tx_buffer_transmission_interrupt(tx_buffer_id);
+                self.effects
+                    .enable_tx_buffer_cancellation_interrupt(tx_buffer_id);
+            }
+
             fn set_transmit_fifo_queue_mode(&self, mode: TxMode) {
                 if let TxMode::Fifo | TxMode::Queue = mode {
                     self.effects.set_transmit_fifo_queue_mode(mode);
@@ -357,38 +671,229 @@ macro_rules! impl_can_node {
                 }
             }
 
-            fn configure_baud_rate(&self, baud_rate: &BitTimingConfig) {
+            fn configure_baud_rate(
+                &self,
+                baud_rate: &BitTimingConfig,
+            ) -> Result<NominalBitTiming, BitTimingError> {
                 let bit_timing: NominalBitTiming = match baud_rate {
                     BitTimingConfig::Auto(baud_rate) => {
-                        let module_freq = crate::scu::ccu::get_mcan_frequency() as f32;
-                        calculate_bit_timing(
-                            module_freq,
-                            baud_rate.baud_rate,
-                            baud_rate.sample_point,
-                            baud_rate.sync_jump_width,
-                        )
+                        let module_freq = crate::scu::ccu::get_mcan_frequency();
+                        calculate_bit_timing(module_freq, baud_rate)?.timing
                     }
                     BitTimingConfig::Manual(baud_rate) => *baud_rate,
                 };
 
                 self.effects.set_nominal_bit_timing(&bit_timing);
+                Ok(bit_timing)
+            }
+
+            fn detect_bit_timings(
+                &mut self,
+                detection: &BaudRateDetection,
+            ) -> Result<Option<DetectedBaudRate>, ConfigError> {
+                let original = self.effects.get_nominal_bit_timing();
+                let detected = self.find_bit_timing(detection);
+
+                // keep the matching bit timing, restore the original one otherwise
+                let bit_timing = match &detected {
+                    Ok(Some((_, bit_timing))) => *bit_timing,
+                    Ok(None) | Err(_) => original,
+                };
+                self.set_baud_rate(&BitTimingConfig::Manual(bit_timing))?;
+                let Some((baud_rate, bit_timing)) = detected? else {
+                    return Ok(None);
+                };
+
+                let fast_baud_rate = if self.frame_mode == FrameMode::Standard {
+                    None
+                } else {
+                    let original = self.effects.get_data_bit_timing_registers();
+                    let detected = self.find_fast_bit_timing(detection, bit_timing);
+                    if !matches!(detected, Ok(Some(_))) {
+                        self.effects.set_data_bit_timing_registers(original);
+                    }
+                    detected?
+                };
+
+                Ok(Some(DetectedBaudRate {
+                    baud_rate,
+                    fast_baud_rate,
+                }))
+            }
+
+            /// First nominal candidate receiving enough frames without error
+            fn find_bit_timing(
+                &self,
+                detection: &BaudRateDetection,
+            ) -> Result<Option<(BitTimingConfig, NominalBitTiming)>, ConfigError> {
+                for candidate in detection.candidates {
+                    let bit_timing = self
+                        .configure_baud_rate(candidate)
+                        .map_err(ConfigError::InvalidBitTiming)?;
+                    if self.listen_for_frames(detection, bit_timing, |status| {
+                        status.last_error_code
+                    }) {
+                        return Ok(Some((candidate.clone(), bit_timing)));
+                    }
+                }
+                Ok(None)
+            }
+
+            /// First data phase candidate receiving enough frames without error, with the
+            /// detected nominal bit timing
+            fn find_fast_bit_timing(
+                &self,
+                detection: &BaudRateDetection,
+                bit_timing: NominalBitTiming,
+            ) -> Result<Option<FastBitTimingConfig>, ConfigError> {
+                for candidate in detection.fast_candidates {
+                    self.configure_fast_baud_rate(candidate, detection.delay_compensation)
+                        .map_err(ConfigError::InvalidBitTiming)?;
+                    if self.listen_for_frames(detection, bit_timing, |status| {
+                        status.data_last_error_code
+                    }) {
+                        return Ok(Some(candidate.clone()));
+                    }
+                }
+                Ok(None)
+            }
+
+            /// Leave the initialization mode for the listen time of the detection and judge
+            /// the configured bit timing with the frames received and the last error code
+            /// selected from the protocol status
+            fn listen_for_frames(
+                &self,
+                detection: &BaudRateDetection,
+                bit_timing: NominalBitTiming,
+                last_error_code: fn(&ProtocolStatus) -> LastErrorCode,
+            ) -> bool {
+                // the internal timestamp counter measures the listen time in nominal bits
+                let prescaler = self.effects.get_timestamp_prescaler();
+                let select = self.effects.get_timestamp_select();
+                self.effects.set_timestamp_counter(0, 1);
+                let module_freq = u64::from(crate::scu::ccu::get_mcan_frequency());
+                let bits = u64::from(detection.listen_time) * module_freq
+                    / (u64::from(bit_timing.clocks_per_bit()) * 1_000_000);
+
+                let stored = self.stored_messages();
+                self.effects.disable_configuration_change();
+
+                // reading the protocol status resets the last error codes
+                let _ = self.effects.get_protocol_status();
+
+                let polls = usize::try_from(detection.listen_time)
+                    .unwrap_or(usize::MAX)
+                    .saturating_mul(LISTEN_POLLS_PER_MICROSECOND);
+
+                let mut score = CandidateScore::default();
+                let mut counter = self.effects.get_timestamp_counter();
+                let mut elapsed = 0;
+                let mut timed_out = true;
+                for _ in 0..=polls {
+                    let status = self.effects.get_protocol_status();
+                    let received = self.stored_messages().saturating_sub(stored);
+                    score.record(last_error_code(&status), received);
+                    let decided = score.is_rejected() || score.is_match(detection.frames);
+                    if decided || elapsed >= bits {
+                        timed_out = false;
+                        break;
+                    }
+
+                    let now = self.effects.get_timestamp_counter();
+                    elapsed += u64::from(now.wrapping_sub(counter));
+                    counter = now;
+                }
+
+                self.effects.enable_configuration_change();
+                self.effects.set_timestamp_counter(prescaler, select);
+                // running out of polls rejects the candidate
+                !timed_out && score.is_match(detection.frames)
+            }
+
+            /// Messages stored in the Rx FIFOs and the dedicated Rx buffers and not read yet
+            fn stored_messages(&self) -> u32 {
+                let (new_data1, new_data2) = self.effects.get_rx_buffers_new_data();
+                u32::from(self.effects.get_rx_fifo0_fill_level())
+                    + u32::from(self.effects.get_rx_fifo1_fill_level())
+                    + new_data1.count_ones()
+                    + new_data2.count_ones()
+            }
+
+            fn configure_operating_mode(&self, mode: OperatingMode) {
+                match mode {
+                    OperatingMode::Normal => {}
+                    OperatingMode::ListenOnly => self.effects.set_bus_monitoring(true),
+                    OperatingMode::Restricted => self.effects.set_restricted_operation(true),
+                    OperatingMode::InternalLoopback => {
+                        self.effects.enable_test_loopback();
+                        self.effects.set_bus_monitoring(true);
+                    }
+                    OperatingMode::ExternalLoopback => self.effects.enable_test_loopback(),
+                    OperatingMode::ModuleLoopback => self.effects.enable_loopback(),
+                }
             }
 
-            fn configure_fast_baud_rate(&self, baud_rate: &FastBitTimingConfig) {
+            fn configure_timestamp(
+                &mut self,
+                config: &TimestampConfig,
+                bit_timing: &NominalBitTiming,
+            ) {
+                let prescaler = config.prescaler.clamp(1, 16);
+                let select = match config.source {
+                    TimestampSource::Disabled => 0,
+                    TimestampSource::Internal => 1,
+                    TimestampSource::External => 2,
+                };
+
+                self.effects.set_timestamp_counter(prescaler - 1, select);
+
+                if config.source == TimestampSource::Internal {
+                    let clocks_per_tick = u32::from(prescaler) * bit_timing.clocks_per_bit();
+                    self.timestamp_clock =
+                        Some((clocks_per_tick, crate::scu::ccu::get_mcan_frequency()));
+                }
+            }
+
+            fn configure_fast_baud_rate(
+                &self,
+                baud_rate: &FastBitTimingConfig,
+                delay_compensation: TransceiverDelayCompensation,
+            ) -> Result<(), BitTimingError> {
+                let module_freq = crate::scu::ccu::get_mcan_frequency();
                 let bit_timing: DataBitTiming = match baud_rate {
                     FastBitTimingConfig::Auto(baud_rate) => {
-                        let module_freq = crate::scu::ccu::get_mcan_frequency() as f32;
+                        let nominal = self.effects.get_nominal_bit_timing();
                         calculate_fast_bit_timing(
                             module_freq,
-                            baud_rate.baud_rate,
-                            baud_rate.sample_point,
-                            baud_rate.sync_jump_width,
-                        )
+                            baud_rate,
+                            nominal,
+                            delay_compensation,
+                        )?
+                        .timing
                     }
                     FastBitTimingConfig::Manual(baud_rate) => *baud_rate,
                 };
 
+                let delay_compensation = match delay_compensation {
+                    TransceiverDelayCompensation::Disabled => None,
+                    TransceiverDelayCompensation::Auto => {
+                        Some((bit_timing.sample_point_clocks(), 0))
+                    }
+                    TransceiverDelayCompensation::Manual {
+                        offset,
+                        filter_window,
+                    } => Some((u32::from(offset), u32::from(filter_window))),
+                };
+                let delay_compensation =
+                    bit_timing.delay_compensation(module_freq, delay_compensation)?;
+
                 self.effects.set_data_bit_timing(&bit_timing);
+
+                if let Some((offset, filter_window)) = delay_compensation {
+                    self.effects
+                        .set_transceiver_delay_compensation(offset, filter_window);
+                }
+                Ok(())
             }
 
             #[inline]
@@ -440,55 +945,303 @@ macro_rules! impl_can_node {
                     self.effects.set_interrupt_routing_group_2(line, group);
                 }
             }
+        }
 
-            fn connect_pin_rx(
-                &self,
-                rxd: &RxdIn<$ModuleId, I>,
-                mode: InputMode,
-                pad_driver: PadDriver,
-            ) {
-                let port = Port::new(rxd.port);
-                port.set_pin_mode_input(rxd.pin_index, mode);
-                port.set_pin_pad_driver(rxd.pin_index, pad_driver);
-                self.effects.connect_pin_rx(rxd.select);
+        // Methods valid in any state
+        impl<I: NodeId, State> Node<$NodeReg, $ModuleReg, I, State>
+        where
+            $ModuleReg: CanCommon<I>,
+        {
+            /// Stop the node, leaving it in initialization mode, and give back its pins.
+            /// Called by the module when the node is released.
+            pub(crate) fn stop(mut self) -> Option<Pins<$ModuleReg, I>> {
+                self.cancel_clock_stop();
+                self.effects.enable_configuration_change();
+                self.pins
             }
 
-            fn connect_pin_tx(
-                &self,
-                txd: &TxdOut<$ModuleId, I>,
-                mode: OutputMode,
-                pad_driver: PadDriver,
-            ) {
-                let port = Port::new(txd.port);
-                port.set_pin_mode_output(txd.pin_index, mode, txd.select);
-                port.set_pin_pad_driver(txd.pin_index, pad_driver);
-                port.set_pin_low(txd.pin_index);
+            /// Withdraw the clock stop request of a sleeping node, returns true if it was sleeping
+            fn cancel_clock_stop(&mut self) -> bool {
+                if !core::mem::take(&mut self.sleeping) {
+                    return false;
+                }
+
+                self.effects.set_clock_stop_request(false);
+                // the request is withdrawn even if the acknowledge is still set on timeout
+                let _ = wait_cond(CLOCK_STOP_TIMEOUT_COUNT, || {
+                    self.effects.is_clock_stop_acknowledged()
+                });
+                true
             }
         }
 
         // Methods only valid on a configured node
-        impl<I: NodeId> Node<$NodeReg, $ModuleReg, I, Configured> {
+        impl<I: NodeId> Node<$NodeReg, $ModuleReg, I, Configured>
+        where
+            $ModuleReg: CanCommon<I>,
+        {
             // TODO This does not feel to be the right place for this function
             pub fn clear_interrupt_flag(&self, interrupt: Interrupt) {
                 self.effects.clear_interrupt_flag(interrupt);
             }
 
-            pub fn transmit(&self, frame: &Frame) -> Result<(), TransmitError> {
+            /// Read and acknowledge the pending enabled interrupts.
+            ///
+            /// Only the flags which have been read are cleared, so interrupts raised
+            /// in the meantime are reported by the next call.
+            pub fn pending_events(&self) -> InterruptSet {
+                let flags =
+                    self.effects.get_interrupt_flags() & self.effects.get_enabled_interrupts();
+                self.effects.clear_interrupt_flags(flags);
+                InterruptSet(flags)
+            }
+
+            /// Transmit a frame through the Tx FIFO/queue, returning the buffer it was written to
+            pub fn transmit(&self, frame: &Frame) -> Result<TxBufferId, TransmitError> {
+                let Some(tx_config) = self.tx_config else {
+                    return Err(TransmitError::InvalidBufferId);
+                };
+
+                if let TxMode::DedicatedBuffers = tx_config.mode {
+                    return Err(TransmitError::InvalidBufferId);
+                }
+
+                if self.effects.is_tx_fifo_queue_full() {
+                    return Err(TransmitError::Busy);
+                }
+
                 let buffer_id = self.get_tx_fifo_queue_put_index();
-                self.transmit_inner(buffer_id, frame.id, false, false, false, frame.data)
+                self.transmit_inner(buffer_id, frame)?;
+                Ok(buffer_id)
+            }
+
+            /// Transmit a frame through a dedicated Tx buffer
+            pub fn transmit_to(
+                &self,
+                buffer_id: TxBufferId,
+                frame: &Frame,
+            ) -> Result<TxBufferId, TransmitError> {
+                self.check_dedicated_buffer(buffer_id)?;
+                self.transmit_inner(buffer_id, frame)?;
+                Ok(buffer_id)
+            }
+
+            /// Load a frame in a dedicated Tx buffer, transmitted each time a remote frame
+            /// with the same ID is read.
+            ///
+            /// The M_CAN does not answer remote frames by itself: this is a software
+            /// responder, which requests the transmission of the buffer when the remote frame
+            /// is read from the message RAM by any receive path ([`Self::receive`],
+            /// [`Self::receive_view`], [`Self::drain_rx`] or `embedded_can::nb::Can::receive`).
+            /// The remote frames must therefore be accepted by the filters and the global
+            /// filter configuration, and the answer is delayed until they are read, e.g. by
+            /// draining Rx from the new message interrupts.
+            pub fn set_remote_responder(
+                &mut self,
+                buffer_id: TxBufferId,
+                frame: &Frame,
+            ) -> Result<(), TransmitError> {
+                self.check_dedicated_buffer(buffer_id)?;
+
+                if frame.remote {
+                    return Err(TransmitError::InvalidAccess);
+                }
+
+                if self.effects.is_tx_buffer_request_pending(buffer_id) {
+                    return Err(TransmitError::Busy);
+                }
+
+                self.write_tx_element(buffer_id, frame)?;
+                self.remote_responder_buffers |= 1 << u8::from(buffer_id);
+                Ok(())
+            }
+
+            /// Stop answering remote frames with the given dedicated Tx buffer, see
+            /// [`Self::set_remote_responder`]
+            pub fn clear_remote_responder(&mut self, buffer_id: TxBufferId) {
+                self.remote_responder_buffers &= !(1 << u8::from(buffer_id));
+            }
+
+            fn check_dedicated_buffer(&self, buffer_id: TxBufferId) -> Result<(), TransmitError> {
+                let Some(tx_config) = self.tx_config else {
+                    return Err(TransmitError::InvalidBufferId);
+                };
+
+                let dedicated_buffers = match tx_config.mode {
+                    TxMode::DedicatedBuffers | TxMode::SharedFifo | TxMode::SharedQueue => {
+                        tx_config.dedicated_tx_buffers_number
+                    }
+                    TxMode::Fifo | TxMode::Queue => 0,
+                };
+
+                if u8::from(buffer_id) >= dedicated_buffers {
+                    return Err(TransmitError::InvalidBufferId);
+                }
+                Ok(())
+            }
+
+            /// Request the responder buffers loaded with the ID of a remote frame read from the
+            /// message RAM
+            fn respond_to_remote_frame(&self, id: MessageId) {
+                for index in 0..32u8 {
+                    if self.remote_responder_buffers & (1 << index) == 0 {
+                        continue;
+                    }
+
+                    let Ok(buffer_id) = TxBufferId::try_from(index) else {
+                        continue;
+                    };
+                    let Ok(tx_buf_el) =
+                        self.get_tx_element_address(self.ram_base_address, buffer_id)
+                    else {
+                        continue;
+                    };
+
+                    if tx_buf_el.get_msg_id() == id
+                        && !self.effects.is_tx_buffer_request_pending(buffer_id)
+                    {
+                        self.effects.set_tx_buffer_add_request(buffer_id.into());
+                    }
+                }
             }
 
-            pub fn receive(&self, from: ReadFrom, data: &mut [u8]) -> Option<RxMessage> {
+            /// Dedicated Rx buffers holding a message not read yet
+            pub fn rx_buffers_with_new_data(&self) -> RxBuffersNewData {
+                let (ndat1, ndat2) = self.effects.get_rx_buffers_new_data();
+                RxBuffersNewData::new(ndat1, ndat2)
+            }
+
+            /// Read the message of the dedicated Rx buffer with new data having the lowest
+            /// index, `None` if all buffers have been read. Reading clears the new data flag,
+            /// so that repeated calls drain the buffers in index order.
+            ///
+            /// To be called from [`Interrupt::MessageStoredToDedicatedRxBuffer`], routed with
+            /// [`InterruptGroup::Reint`], after clearing the interrupt flag.
+            pub fn receive_any(
+                &self,
+                data: &mut [u8],
+            ) -> Option<Result<RxMessage, ReceiveError>> {
+                let id = self.rx_buffers_with_new_data().next()?;
+                Some(self.receive(ReadFrom::Buffer(id), data))
+            }
+
+            /// Move the received frames from the Rx FIFOs and dedicated Rx buffers to a queue.
+            ///
+            /// To be called from the new message, watermark or message stored to dedicated
+            /// Rx buffer interrupts. Frames not fitting in the queue are dropped and counted
+            /// as overflows of the queue. Message lost flags of the Rx FIFOs, which are
+            /// cleared, and elements that cannot be read as frames are counted as lost frames
+            /// of the queue. Returns the number of frames queued.
+            pub fn drain_rx<const N: usize>(&self, queue: &mut RxProducer<'_, N>) -> usize {
                 let Some(rx_config) = self.rx_config else {
-                    return None;
+                    return 0;
+                };
+
+                let flags = self.effects.get_interrupt_flags();
+                for lost in [Interrupt::RxFifo0messageLost, Interrupt::RxFifo1messageLost] {
+                    if flags & lost.bit() != 0 {
+                        self.effects.clear_interrupt_flag(lost);
+                        queue.record_lost();
+                    }
+                }
+
+                let mut queued = 0;
+                let mut push = |from: ReadFrom| {
+                    let mut data = [0u8; 64];
+                    let frame = match self.receive_with_length(from, &mut data) {
+                        Ok((msg, length)) => to_owned_frame(&msg, &data, length).ok(),
+                        Err(ReceiveError::Empty) => return,
+                        Err(_) => None,
+                    };
+                    match frame {
+                        Some(frame) => {
+                            if queue.push(frame).is_ok() {
+                                queued += 1;
+                            }
+                        }
+                        None => queue.record_lost(),
+                    }
                 };
 
-                let buffer_id = match from {
-                    ReadFrom::RxFifo0 => self.effects.get_rx_fifo0_get_index(),
-                    ReadFrom::RxFifo1 => self.effects.get_rx_fifo1_get_index(),
-                    ReadFrom::Buffer(id) => id,
+                if rx_config.mode.uses_fifo0() {
+                    for _ in 0..self.effects.get_rx_fifo0_fill_level() {
+                        push(ReadFrom::RxFifo0);
+                    }
+                }
+
+                if rx_config.mode.uses_fifo1() {
+                    for _ in 0..self.effects.get_rx_fifo1_fill_level() {
+                        push(ReadFrom::RxFifo1);
+                    }
+                }
+
+                if rx_config.mode.uses_buffers() {
+                    for id in self.rx_buffers_with_new_data() {
+                        push(ReadFrom::Buffer(id));
+                    }
+                }
+
+                queued
+            }
+
+            /// Read and acknowledge the oldest element of the Tx Event FIFO
+            pub fn read_tx_event(&self) -> Option<TxEvent> {
+                let tx_config = self.tx_config?;
+
+                if self.effects.get_tx_event_fifo_fill_level() == 0 {
+                    return None;
+                }
+
+                let index = self.effects.get_tx_event_fifo_get_index();
+                let address = self.ram_base_address
+                    + u32::from(tx_config.tx_event_fifo_start_address)
+                    + u32::from(index) * TX_EVENT_FIFO_ELEMENT_SIZE;
+                let mut event = TxEventElement::new(address as *mut u8).read();
+                event.timestamp = self.to_timestamp(event.timestamp.raw);
+
+                self.effects.set_tx_event_fifo_acknowledge_index(index);
+
+                Some(event)
+            }
+
+            /// Whether the Tx Event FIFO is full
+            pub fn is_tx_event_fifo_full(&self) -> bool {
+                self.effects.is_tx_event_fifo_full()
+            }
+
+            /// Whether a Tx event has been discarded because the Tx Event FIFO was full.
+            /// The flag is reset by clearing [`Interrupt::TxEventFifoEventLost`].
+            pub fn is_tx_event_fifo_element_lost(&self) -> bool {
+                self.effects.is_tx_event_fifo_element_lost()
+            }
+
+            /// Read the header of the next message of `from` and the length of its data. Every
+            /// receive path goes through here, so a remote frame is answered by the software
+            /// responder, see [`Self::set_remote_responder`].
+            fn read_rx_element(
+                &self,
+                from: ReadFrom,
+            ) -> Result<(Rx, RxMessage, usize), ReceiveError> {
+                let rx_config = self.rx_config.ok_or(ReceiveError::RxNotConfigured)?;
+
+                let (buffer_id, is_empty) = match from {
+                    ReadFrom::RxFifo0 => (
+                        self.effects.get_rx_fifo0_get_index(),
+                        self.effects.get_rx_fifo0_fill_level() == 0,
+                    ),
+                    ReadFrom::RxFifo1 => (
+                        self.effects.get_rx_fifo1_get_index(),
+                        self.effects.get_rx_fifo1_fill_level() == 0,
+                    ),
+                    ReadFrom::Buffer(id) => {
+                        (id, !self.effects.is_rx_buffer_new_data_updated(id.into()))
+                    }
                 };
 
+                if is_empty {
+                    return Err(ReceiveError::Empty);
+                }
+
                 let rx_buf_elem = self.effects.get_rx_element_address(
                     self.ram_base_address,
                     match from {
@@ -500,33 +1253,96 @@ macro_rules! impl_can_node {
                     buffer_id,
                 );
 
-                // info!("read message on buffer_id: {}", buffer_id.0);
-                // info!("rx_buf_elem at: {:x}", rx_buf_elem.get_ptr());
-
                 let id = MessageId {
                     data: rx_buf_elem.get_message_id(),
                     length: rx_buf_elem.get_message_id_length(),
                 };
 
                 let data_length_code = rx_buf_elem.get_data_length();
-                let frame_mode = rx_buf_elem.get_frame_mode();
-
-                rx_buf_elem.read_data(data_length_code, data.as_mut_ptr());
-
-                match from {
-                    ReadFrom::RxFifo0 => self.effects.set_rx_fifo0_acknowledge_index(buffer_id),
-                    ReadFrom::RxFifo1 => self.effects.set_rx_fifo1_acknowledge_index(buffer_id),
-                    ReadFrom::Buffer(_) => (),
-                }
+                let remote_transmit_request = rx_buf_elem.is_remote_frame();
 
-                self.effects.clear_rx_buffer_new_data_flag(buffer_id);
+                // the bytes exceeding the data field size of the element are not stored
+                let length = if remote_transmit_request {
+                    0
+                } else {
+                    data_length_code
+                        .to_length()
+                        .min(usize::from(self.effects.get_data_field_size(from)))
+                };
 
-                Some(RxMessage {
+                let msg = RxMessage {
                     id,
                     data_length_code,
-                    frame_mode,
+                    frame_mode: rx_buf_elem.get_frame_mode(),
                     buffer_id,
                     from,
+                    remote_transmit_request,
+                    error_state_indicator: rx_buf_elem.get_error_state_indicator(),
+                    timestamp: self.to_timestamp(rx_buf_elem.get_timestamp()),
+                };
+
+                if remote_transmit_request {
+                    self.respond_to_remote_frame(id);
+                }
+
+                Ok((rx_buf_elem, msg, length))
+            }
+
+            /// Copy the next message of `from` to `data` and release its Rx element.
+            ///
+            /// When `data` is shorter than the received data, the element is not released
+            /// and [`ReceiveError::DataTooShort`] is returned, so that the message can be
+            /// read again with a larger buffer.
+            pub fn receive(
+                &self,
+                from: ReadFrom,
+                data: &mut [u8],
+            ) -> Result<RxMessage, ReceiveError> {
+                self.receive_with_length(from, data).map(|(msg, _)| msg)
+            }
+
+            /// [`Self::receive`], also returning the number of bytes copied to `data`
+            fn receive_with_length(
+                &self,
+                from: ReadFrom,
+                data: &mut [u8],
+            ) -> Result<(RxMessage, usize), ReceiveError> {
+                let (rx_buf_elem, msg, length) = self.read_rx_element(from)?;
+
+                let data = data
+                    .get_mut(..length)
+                    .ok_or(ReceiveError::DataTooShort { length })?;
+                // SAFETY: length does not exceed the data field size of the element
+                unsafe { rx_buf_elem.copy_data(data) };
+
+                self.effects.acknowledge_rx_element(from, msg.buffer_id);
+
+                Ok((msg, length))
+            }
+
+            /// Borrow the next message of `from` in the message RAM, without copying its
+            /// data. The Rx element is released when the view is dropped.
+            ///
+            /// Only dedicated Rx buffers and Rx FIFOs in blocking mode can be borrowed,
+            /// [`ReceiveError::OverwriteMode`] is returned for a FIFO in overwrite mode.
+            pub fn receive_view(&mut self, from: ReadFrom) -> Result<RxView<'_>, ReceiveError> {
+                let rx_config = self.rx_config.ok_or(ReceiveError::RxNotConfigured)?;
+                let operating_mode = match from {
+                    ReadFrom::RxFifo0 => rx_config.fifo0_operating_mode,
+                    ReadFrom::RxFifo1 => rx_config.fifo1_operating_mode,
+                    ReadFrom::Buffer(_) => RxFifoMode::Blocking,
+                };
+                if operating_mode == RxFifoMode::Overwrite {
+                    return Err(ReceiveError::OverwriteMode);
+                }
+
+                let (element, message, length) = self.read_rx_element(from)?;
+
+                Ok(RxView {
+                    element,
+                    message,
+                    length,
+                    effects: &self.effects,
                 })
             }
 
@@ -536,46 +1352,90 @@ macro_rules! impl_can_node {
                 unsafe { TxBufferId::try_from(id).unwrap_unchecked() }
             }
 
-            #[allow(unused_variables)]
             fn transmit_inner(
                 &self,
                 buffer_id: TxBufferId,
-                id: MessageId,
-                tx_event_fifo_control: bool,
-                remote_transmit_request: bool,
-                error_state_indicator: bool,
-                data: &[u8],
+                frame: &Frame,
             ) -> Result<(), TransmitError> {
                 let req_pending = self.effects.is_tx_buffer_request_pending(buffer_id);
                 if req_pending {
                     return Err(TransmitError::Busy);
                 }
 
-                let tx_buf_el = self.get_tx_element_address(self.ram_base_address, buffer_id);
+                self.write_tx_element(buffer_id, frame)?;
+                self.effects.set_tx_buffer_add_request(buffer_id.into());
 
-                tx_buf_el.set_msg_id(id);
+                info!(
+                    "transmit {}#{}",
+                    frame.id.data,
+                    crate::log::HexSlice::from(frame.data)
+                );
 
-                if tx_event_fifo_control {
-                    tx_buf_el.set_tx_event_fifo_ctrl(tx_event_fifo_control);
-                    tx_buf_el.set_message_marker(buffer_id);
-                }
+                Ok(())
+            }
 
-                tx_buf_el.set_remote_transmit_req(remote_transmit_request);
+            fn write_tx_element(
+                &self,
+                buffer_id: TxBufferId,
+                frame: &Frame,
+            ) -> Result<(), TransmitError> {
+                let frame_mode = if frame.remote {
+                    FrameMode::Standard
+                } else {
+                    frame.frame_mode.unwrap_or(self.frame_mode)
+                };
 
-                if let FrameMode::FdLong | FrameMode::FdLongAndFast = self.frame_mode {
-                    tx_buf_el.set_err_state_indicator(error_state_indicator)
+                if frame_mode > self.frame_mode {
+                    return Err(TransmitError::UnsupportedFrameMode);
                 }
 
-                let dlc = DataLenghtCode::from_length(data.len())
+                // remote and classic CAN frames carry at most 8 bytes
+                if frame_mode == FrameMode::Standard && frame.data.len() > 8 {
+                    return Err(TransmitError::InvalidDataLength);
+                }
+
+                let dlc = DataLenghtCode::from_length_padded(frame.data.len())
                     .ok_or(TransmitError::InvalidDataLength)?;
 
+                let Some(tx_config) = self.tx_config else {
+                    return Err(TransmitError::InvalidBufferId);
+                };
+
+                if dlc.to_length() > tx_config.buffer_data_field_size.to_length() as usize {
+                    return Err(TransmitError::InvalidDataLength);
+                }
+
+                let tx_buf_el = self.get_tx_element_address(self.ram_base_address, buffer_id)?;
+
+                tx_buf_el.set_msg_id(frame.id);
+
+                tx_buf_el.set_tx_event_fifo_ctrl(frame.event_marker.is_some());
+                if let Some(marker) = frame.event_marker {
+                    tx_buf_el.set_message_marker(marker);
+                }
+
+                tx_buf_el.set_remote_transmit_req(frame.remote);
+
+                tx_buf_el.set_err_state_indicator(
+                    frame.error_state_indicator && frame_mode != FrameMode::Standard,
+                );
+
                 tx_buf_el.set_data_length(dlc);
-                tx_buf_el.write_tx_buf_data(dlc, data.as_ptr());
-                tx_buf_el.set_frame_mode_req(self.frame_mode);
-                let buffer_id = 0; //TODO
-                self.effects.set_tx_buffer_add_request(buffer_id);
+                // no data is transmitted for remote frames
+                if !frame.remote {
+                    if dlc.to_length() == frame.data.len() {
+                        tx_buf_el.write_tx_buf_data(dlc, frame.data.as_ptr());
+                    } else {
+                        let mut padded = [self.padding_byte; 64];
+                        padded
+                            .get_mut(..frame.data.len())
+                            .ok_or(TransmitError::InvalidDataLength)?
+                            .copy_from_slice(frame.data);
+                        tx_buf_el.write_tx_buf_data(dlc, padded.as_ptr());
+                    }
+                }
 
-                info!("transmit {}#{}", id.data, crate::log::HexSlice::from(data));
+                tx_buf_el.set_frame_mode_req(frame_mode);
 
                 Ok(())
             }
@@ -584,34 +1444,288 @@ macro_rules! impl_can_node {
                 &self,
                 ram_base_address: u32,
                 buffer_number: TxBufferId,
-            ) -> Tx {
+            ) -> Result<Tx, TransmitError> {
+                let Some(tx_config) = self.tx_config else {
+                    return Err(TransmitError::InvalidBufferId);
+                };
+
                 let num_of_config_bytes = 8u32;
-                let num_of_data_bytes = self.effects.get_tx_buffer_data_field_size() as u32;
+                let num_of_data_bytes = tx_config.buffer_data_field_size.to_length();
                 let tx_buffer_size = num_of_config_bytes + num_of_data_bytes;
                 let tx_buffer_index = tx_buffer_size * u32::from(u8::from(buffer_number));
 
-                let tx_buffer_element_address =
-                    ram_base_address + TX_BUFFER_START_ADDRESS + tx_buffer_index;
+                let tx_buffer_element_address = ram_base_address
+                    + u32::from(tx_config.tx_buffers_start_address)
+                    + tx_buffer_index;
 
-                Tx::new(tx_buffer_element_address as *mut u8)
+                Ok(Tx::new(tx_buffer_element_address as *mut u8))
             }
 
-            // TODO Untested, example missing
-            #[inline]
-            fn is_tx_buffer_cancellation_finished(&self, tx_buffer_id: TxBufferId) -> bool {
-                self.is_tx_buffer_transmission_occured(tx_buffer_id)
+            /// Read the current value of the timestamp counter
+            pub fn timestamp(&self) -> Timestamp {
+                self.to_timestamp(self.effects.get_timestamp_counter())
             }
 
-            // TODO Untested, example missing
-            #[inline]
-            fn is_tx_buffer_transmission_occured(&self, tx_buffer_id: TxBufferId) -> bool {
+            fn to_timestamp(&self, raw: u16) -> Timestamp {
+                let nanos = self
+                    .timestamp_clock
+                    .and_then(|(clocks_per_tick, frequency)| {
+                        let clocks = u128::from(raw) * u128::from(clocks_per_tick);
+                        u64::try_from((clocks * 1_000_000_000).checked_div(u128::from(frequency))?)
+                            .ok()
+                    });
+                Timestamp { raw, nanos }
+            }
+
+            /// Read the protocol status, resetting the last error codes
+            pub fn protocol_status(&self) -> ProtocolStatus {
+                self.effects.get_protocol_status()
+            }
+
+            /// Read the error counters, resetting the CAN error logging counter
+            pub fn error_counters(&self) -> ErrorCounters {
+                self.effects.get_error_counters()
+            }
+
+            pub fn error_state(&self) -> ErrorState {
+                let status = self.protocol_status();
+                if status.bus_off {
+                    ErrorState::BusOff
+                } else if status.error_passive {
+                    ErrorState::Passive
+                } else {
+                    ErrorState::Active
+                }
+            }
+
+            /// Apply the bus-off recovery policy given in [`NodeConfig`].
+            ///
+            /// To be called periodically or on [`Interrupt::BusOffStatus`].
+            pub fn handle_bus_off(&mut self) -> ErrorState {
+                let state = self.error_state();
+                match state {
+                    ErrorState::Active => self.bus_off.reset(),
+                    ErrorState::Passive => {}
+                    ErrorState::BusOff => {
+                        if self.effects.is_initialization_enabled() && self.bus_off.should_restart()
+                        {
+                            self.recover_from_bus_off();
+                        }
+                    }
+                }
+                state
+            }
+
+            /// Restart a bus-off node, it rejoins the bus after 128 occurrences of 11
+            /// consecutive recessive bits
+            pub fn recover_from_bus_off(&self) {
+                if self.effects.is_initialization_enabled() {
+                    self.effects.disable_configuration_change();
+                }
+            }
+
+            /// Stop the clock of the node, once the pending transmission requests have been
+            /// sent. Requests that cannot be sent, e.g. without acknowledge on the bus, must be
+            /// cancelled first, see [`Self::cancel`].
+            ///
+            /// The node does not transmit nor receive until [`Self::wake`] is called. The
+            /// M_CAN does not wake up on bus activity by itself, and no wake-up on a falling
+            /// edge of the RXD pin is set up by this driver: the application can call
+            /// [`Self::wake`] from an external request interrupt it configures on that pin.
+            pub fn sleep(&mut self) -> Result<(), SleepError> {
+                if wait_cond(CLOCK_STOP_TIMEOUT_COUNT, || {
+                    self.effects.has_pending_tx_requests()
+                })
+                .is_err()
+                {
+                    return Err(SleepError::PendingTransmissions);
+                }
+
+                // the node finishes the current frame, sets INIT and acknowledges
+                self.effects.set_clock_stop_request(true);
+                if wait_cond(CLOCK_STOP_TIMEOUT_COUNT, || {
+                    !self.effects.is_clock_stop_acknowledged()
+                })
+                .is_err()
+                {
+                    self.effects.set_clock_stop_request(false);
+                    return Err(SleepError::Timeout);
+                }
+
+                self.sleeping = true;
+                Ok(())
+            }
+
+            /// Stop the node and make it configurable again through the CCE sequence, e.g. to
+            /// change the baud rate. A transmission in progress is completed, pending
+            /// requests are not sent. The configuration is kept until changed.
+            #[must_use]
+            pub fn reconfigure(mut self) -> Node<$NodeReg, $ModuleReg, I, Configurable> {
+                self.cancel_clock_stop();
+                self.effects.enable_configuration_change();
+
+                Node {
+                    effects: self.effects,
+                    _phantom: PhantomData,
+                    frame_mode: self.frame_mode,
+                    ram_base_address: self.ram_base_address,
+                    rx_config: self.rx_config,
+                    tx_config: self.tx_config,
+                    bus_off: self.bus_off,
+                    timestamp_clock: self.timestamp_clock,
+                    remote_responder_buffers: self.remote_responder_buffers,
+                    padding_byte: self.padding_byte,
+                    pins: self.pins,
+                    sleeping: false,
+                }
+            }
+
+            /// Restart the clock of the node and resume normal operation
+            pub fn wake(&mut self) {
+                if self.cancel_clock_stop() {
+                    self.effects.disable_configuration_change();
+                }
+            }
+
+            /// Whether the node is sleeping
+            pub fn is_sleeping(&self) -> bool {
+                self.sleeping
+            }
+
+            /// Status of the last request added to a Tx buffer
+            pub fn tx_buffer_status(&self, tx_buffer_id: TxBufferId) -> TxBufferStatus {
+                let id = u8::from(tx_buffer_id);
+                if self.effects.is_tx_buffer_request_pending(tx_buffer_id) {
+                    TxBufferStatus::Pending
+                } else if self.effects.is_tx_buffer_transmission_occured(id) {
+                    TxBufferStatus::Transmitted
+                } else if self.effects.is_tx_buffer_cancellation_finished(id) {
+                    TxBufferStatus::Cancelled
+                } else {
+                    TxBufferStatus::Idle
+                }
+            }
+
+            /// Request the cancellation of a pending Tx buffer and wait until it is finished.
+            ///
+            /// A transmission already in progress is not aborted, in which case the returned
+            /// status is [`TxBufferStatus::Transmitted`]. See [`Self::request_cancel`] to
+            /// cancel without waiting.
+            pub fn cancel(&self, tx_buffer_id: TxBufferId) -> Result<TxBufferStatus, CancelError> {
+                if !self.request_cancel(tx_buffer_id) {
+                    return Ok(self.tx_buffer_status(tx_buffer_id));
+                }
+
+                let id = u8::from(tx_buffer_id);
+                wait_cond(CANCEL_TIMEOUT_COUNT, || {
+                    !self.effects.is_tx_buffer_cancellation_finished(id)
+                        && !self.effects.is_tx_buffer_transmission_occured(id)
+                })
+                .map_err(|()| CancelError::Timeout)?;
+
+                Ok(self.tx_buffer_status(tx_buffer_id))
+            }
+
+            /// Request the cancellation of a pending Tx buffer without waiting for it, returns
+            /// false if no request was pending.
+            ///
+            /// The status stays [`TxBufferStatus::Pending`] until the cancellation has
+            /// finished, which is signalled by [`Interrupt::TransmissionCancellationFinished`]
+            /// and then read with [`Self::tx_buffer_status`].
+            pub fn request_cancel(&self, tx_buffer_id: TxBufferId) -> bool {
+                if !self.effects.is_tx_buffer_request_pending(tx_buffer_id) {
+                    return false;
+                }
+
                 self.effects
-                    .is_tx_buffer_transmission_occured(tx_buffer_id.into())
+                    .set_tx_buffer_cancellation_request(u8::from(tx_buffer_id));
+                true
+            }
+        }
+
+        impl<I: NodeId> embedded_can::nb::Can for Node<$NodeReg, $ModuleReg, I, Configured>
+        where
+            $ModuleReg: CanCommon<I>,
+        {
+            type Frame = OwnedFrame;
+            type Error = CanError;
+
+            fn transmit(
+                &mut self,
+                frame: &Self::Frame,
+            ) -> nb::Result<Option<Self::Frame>, Self::Error> {
+                Node::transmit(self, &frame.as_frame()).map_err(|e| match e {
+                    TransmitError::Busy => nb::Error::WouldBlock,
+                    TransmitError::InvalidDataLength => {
+                        nb::Error::Other(CanError::InvalidDataLength)
+                    }
+                    // no Tx FIFO/queue, e.g. a node with dedicated Tx buffers only
+                    TransmitError::InvalidBufferId => nb::Error::Other(CanError::NotConfigured),
+                    TransmitError::InvalidAccess | TransmitError::UnsupportedFrameMode => {
+                        nb::Error::Other(CanError::InvalidAccess)
+                    }
+                })?;
+
+                Ok(None)
+            }
+
+            /// Read the oldest message of the Rx FIFOs or, when they are empty, the dedicated
+            /// Rx buffer with new data having the lowest index
+            fn receive(&mut self) -> nb::Result<Self::Frame, Self::Error> {
+                let Some(rx_config) = self.rx_config else {
+                    return Err(nb::Error::Other(CanError::NotConfigured));
+                };
+
+                let from = if rx_config.mode.uses_fifo0()
+                    && self.effects.get_rx_fifo0_fill_level() > 0
+                {
+                    ReadFrom::RxFifo0
+                } else if rx_config.mode.uses_fifo1() && self.effects.get_rx_fifo1_fill_level() > 0
+                {
+                    ReadFrom::RxFifo1
+                } else if rx_config.mode.uses_buffers() {
+                    let id = self
+                        .rx_buffers_with_new_data()
+                        .next()
+                        .ok_or(nb::Error::WouldBlock)?;
+                    ReadFrom::Buffer(id)
+                } else {
+                    return Err(nb::Error::WouldBlock);
+                };
+
+                let mut data = [0u8; 64];
+                let received = self.receive_with_length(from, &mut data);
+                let (msg, length) = received.map_err(|e| match e {
+                    ReceiveError::Empty => nb::Error::WouldBlock,
+                    ReceiveError::RxNotConfigured => nb::Error::Other(CanError::NotConfigured),
+                    ReceiveError::DataTooShort { .. } => {
+                        nb::Error::Other(CanError::InvalidDataLength)
+                    }
+                    ReceiveError::OverwriteMode => nb::Error::Other(CanError::InvalidAccess),
+                })?;
+                to_owned_frame(&msg, &data, length).map_err(nb::Error::Other)
             }
         }
     };
 }
 
+/// Build a frame from a received message and the `length` bytes copied from its Rx element
+fn to_owned_frame(msg: &RxMessage, data: &[u8], length: usize) -> Result<OwnedFrame, CanError> {
+    use embedded_can::Frame as _;
+
+    let id = embedded_can::Id::try_from(msg.id).map_err(|()| CanError::InvalidId)?;
+    let frame = if msg.remote_transmit_request {
+        // a data length code above 8 requests 8 bytes
+        OwnedFrame::new_remote(id, msg.data_length_code.to_length().min(8))
+    } else if msg.frame_mode == FrameMode::Standard {
+        // a classic frame with a data length code above 8 carries 8 bytes
+        data.get(..length.min(8)).and_then(|data| OwnedFrame::new(id, data))
+    } else {
+        data.get(..length).and_then(|data| OwnedFrame::new_fd(id, data))
+    };
+    frame.ok_or(CanError::InvalidDataLength)
+}
+
 impl_can_node!(
     crate::pac::can0::Can0,
     crate::pac::can0::N,
@@ -639,7 +1753,7 @@ pub enum RxFifoMode {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum DataFieldSize {
     #[default]
     _8,
@@ -665,6 +1779,20 @@ impl DataFieldSize {
             DataFieldSize::_64 => 7,
         }
     }
+
+    /// Size of the data field in bytes
+    pub(crate) const fn to_length(self) -> u32 {
+        match self {
+            DataFieldSize::_8 => 8,
+            DataFieldSize::_12 => 12,
+            DataFieldSize::_16 => 16,
+            DataFieldSize::_20 => 20,
+            DataFieldSize::_24 => 24,
+            DataFieldSize::_32 => 32,
+            DataFieldSize::_48 => 48,
+            DataFieldSize::_64 => 64,
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -684,6 +1812,7 @@ pub enum InterruptGroup {
     Safe,
     Boff,
     Loi,
+    /// Message stored to a dedicated Rx buffer
     Reint,
     Rxf1f,
     Rxf0f,
@@ -700,7 +1829,7 @@ impl From<InterruptGroup> for u8 {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Interrupt {
     RxFifo0newMessage,
     RxFifo0watermarkReached,
@@ -722,11 +1851,8 @@ pub enum Interrupt {
     MessageRamaccessFailure,
     TimeoutOccurred,
     MessageStoredToDedicatedRxBuffer,
-    // TODO: reserved
-    BitErrorCorrected,
-    // TODO: reserved
-    BitErrorUncorrected,
-    ErrorLoggingOverflow,
+    // bits 20 and 21 are reserved
+    ErrorLoggingOverflow = 22,
     ErrorPassive,
     WarningStatus,
     BusOffStatus,
@@ -736,6 +1862,109 @@ pub enum Interrupt {
     AccessToReservedAddress,
 }
 
+impl Interrupt {
+    const ALL: [Interrupt; 28] = [
+        Interrupt::RxFifo0newMessage,
+        Interrupt::RxFifo0watermarkReached,
+        Interrupt::RxFifo0full,
+        Interrupt::RxFifo0messageLost,
+        Interrupt::RxFifo1newMessage,
+        Interrupt::RxFifo1watermarkReached,
+        Interrupt::RxFifo1full,
+        Interrupt::RxFifo1messageLost,
+        Interrupt::HighPriorityMessage,
+        Interrupt::TransmissionCompleted,
+        Interrupt::TransmissionCancellationFinished,
+        Interrupt::TxFifoEmpty,
+        Interrupt::TxEventFifoNewEntry,
+        Interrupt::TxEventFifoWatermarkReached,
+        Interrupt::TxEventFifoFull,
+        Interrupt::TxEventFifoEventLost,
+        Interrupt::TimestampWraparound,
+        Interrupt::MessageRamaccessFailure,
+        Interrupt::TimeoutOccurred,
+        Interrupt::MessageStoredToDedicatedRxBuffer,
+        Interrupt::ErrorLoggingOverflow,
+        Interrupt::ErrorPassive,
+        Interrupt::WarningStatus,
+        Interrupt::BusOffStatus,
+        Interrupt::Watchdog,
+        Interrupt::ProtocolErrorArbitration,
+        Interrupt::ProtocolErrorData,
+        Interrupt::AccessToReservedAddress,
+    ];
+
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// A set of node interrupts, stored with the layout of the IR and IE registers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterruptSet(u32);
+
+impl InterruptSet {
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    #[must_use]
+    pub const fn new(interrupts: &[Interrupt]) -> Self {
+        let mut set = Self::empty();
+        let mut remaining = interrupts;
+        while let [interrupt, rest @ ..] = remaining {
+            set = set.with(*interrupt);
+            remaining = rest;
+        }
+        set
+    }
+
+    #[must_use]
+    pub const fn with(self, interrupt: Interrupt) -> Self {
+        Self(self.0 | interrupt.bit())
+    }
+
+    #[must_use]
+    pub const fn contains(self, interrupt: Interrupt) -> bool {
+        self.0 & interrupt.bit() != 0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the interrupts of the set, in IR bit order
+    pub fn iter(self) -> impl Iterator<Item = Interrupt> {
+        Interrupt::ALL
+            .into_iter()
+            .filter(move |interrupt| self.contains(*interrupt))
+    }
+}
+
+impl From<Interrupt> for InterruptSet {
+    fn from(interrupt: Interrupt) -> Self {
+        Self::empty().with(interrupt)
+    }
+}
+
+impl core::ops::BitOr for InterruptSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOr<Interrupt> for InterruptSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Interrupt) -> Self {
+        self.with(rhs)
+    }
+}
+
 #[repr(u8)]
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug, Default)]
 pub enum InterruptLine {
@@ -784,275 +2013,6 @@ impl From<Tos> for u8 {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct InputMode(u32);
-impl InputMode {
-    pub const NO_PULL_DEVICE: Self = Self(0 << 3);
-    pub const PULL_DOWN: Self = Self(1 << 3);
-    pub const PULL_UP: Self = Self(2 << 3);
-}
-
-#[derive(Clone, Copy)]
-pub struct OutputMode(u32);
-impl OutputMode {
-    pub const PUSH_PULL: OutputMode = Self(0x10 << 3);
-    pub const OPEN_DRAIN: OutputMode = Self(0x18 << 3);
-    pub const NONE: OutputMode = Self(0);
-}
-
-#[derive(Clone, Copy)]
-pub enum PadDriver {
-    CmosAutomotiveSpeed1 = 0,
-    CmosAutomotiveSpeed2 = 1,
-    CmosAutomotiveSpeed3 = 2,
-    CmosAutomotiveSpeed4 = 3,
-    TtlSpeed1 = 8,
-    TtlSpeed2 = 9,
-    TtlSpeed3 = 10,
-    TtlSpeed4 = 11,
-    Ttl3v3speed1 = 12,
-    Ttl3v3speed2 = 13,
-    Ttl3v3speed3 = 14,
-    Ttl3v3speed4 = 15,
-}
-
-struct Port {
-    inner: crate::pac::p00::P00,
-}
-
-#[derive(Clone, Copy)]
-pub enum PortNumber {
-    _00,
-    _01,
-    _02,
-    _10,
-    _11,
-    _12,
-    _13,
-    _14,
-    _15,
-    _20,
-    _21,
-    _22,
-    _23,
-    _32,
-    _33,
-    _34,
-    _40,
-}
-
-#[derive(Debug, PartialEq)]
-enum State {
-    NotChanged = 0,
-    High = 1,
-    Low = 1 << 16,
-    Toggled = (1 << 16) | 1,
-}
-
-// TODO Is this needed? Can we get rid of it? Seems to be a duplicate of gpio
-impl Port {
-    fn new(port: PortNumber) -> Self {
-        use crate::pac::p00::P00;
-        use crate::pac::*;
-
-        let inner: P00 = match port {
-            PortNumber::_00 => P00,
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_01 => unsafe { transmute(P01) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_02 => unsafe { transmute(P02) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_10 => unsafe { transmute(P10) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_11 => unsafe { transmute(P11) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_12 => unsafe { transmute(P12) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_13 => unsafe { transmute(P13) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_14 => unsafe { transmute(P14) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_15 => unsafe { transmute(P15) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_20 => unsafe { transmute(P20) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_21 => unsafe { transmute(P21) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_22 => unsafe { transmute(P22) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_23 => unsafe { transmute(P23) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_32 => unsafe { transmute(P32) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_33 => unsafe { transmute(P33) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_34 => unsafe { transmute(P34) },
-            // SAFETY: The following transmutes are safe because the underlying registers have the same layout
-            PortNumber::_40 => unsafe { transmute(P40) },
-        };
-        Self { inner }
-    }
-
-    fn set_pin_state(&self, index: u8, action: State) {
-        // SAFETY: Each bit of OMR is W0, TODO: index should be in range [0, 32)?
-        unsafe {
-            self.inner.omr().init(|r| {
-                let v = (action as u32) << index;
-                r.set_raw(v)
-            })
-        };
-    }
-
-    fn toogle_pin(&self, index: u8) {
-        self.set_pin_state(index, State::Toggled)
-    }
-
-    fn set_pin_low(&self, index: u8) {
-        self.set_pin_state(index, State::Low)
-    }
-
-    fn set_pin_mode_output(&self, pin_index: u8, mode: OutputMode, index: OutputIdx) {
-        self.set_pin_mode(pin_index, (mode, index).into());
-    }
-
-    fn set_pin_mode_input(&self, pin_index: u8, mode: InputMode) {
-        self.set_pin_mode(pin_index, mode.into())
-    }
-
-    fn set_pin_mode(&self, index: u8, mode: Mode) {
-        // TODO: index should be in range [0, 16)
-        let ioc_index = index / 4;
-        let shift = (index & 0x3) * 8;
-
-        // TODO This unsafe code could be made safe by comparing the address (usize) of the port if only self.inner.0 was public
-        let is_supervisor =
-            // SAFETY: The following transmute is safe because the underlying registers have the same layout
-            unsafe { transmute::<_, usize>(self.inner) } == unsafe { transmute(crate::pac::P40) };
-
-        if is_supervisor {
-            // SAFETY: Bits 0:16 of PDISC are RW, TODO: index should be in range [0, 16)?
-            wdt_call::call_without_cpu_endinit(|| unsafe {
-                self.inner.pdisc().modify(|r| {
-                    let mut v = r.get_raw();
-                    v &= !(1 << index);
-                    r.set_raw(v)
-                })
-            });
-        }
-
-        // TODO Can we do this without transmute?
-        // TODO Use change_pin_mode_port_pin from gpio module instead?
-        let iocr: crate::pac::Reg<crate::pac::p00::Iocr0_SPEC, crate::pac::RW> = {
-            let iocr0 = self.inner.iocr0();
-            // SAFETY: The following transmute is safe, IOCR0 is a 32 bit register
-            let addr: *mut u32 = unsafe { transmute(iocr0) };
-            // SAFETY: The following operation is safe since ioc_index is in range [0, 4) TODO: see line 918
-            let addr = unsafe { addr.add(ioc_index as usize) };
-            // SAFETY: The following transmute is safe because IOCR0, IOCR4, IOCR8 and IOCR12 have the same layout
-            unsafe { transmute(addr) }
-        };
-
-        let v: u32 = (mode.0) << shift;
-        let m: u32 = 0xFFu32 << shift;
-
-        // SAFETY: Writing PCx (RW) for ioc_index
-        unsafe {
-            crate::intrinsics::load_modify_store(iocr.ptr(), v, m);
-        }
-    }
-
-    fn set_pin_pad_driver(&self, index: u8, driver: PadDriver) {
-        // TODO: index should be in range [0, 16)
-        let pdr_index = index / 8;
-        let shift = (index & 0x7) * 4;
-        let pdr: crate::pac::Reg<crate::pac::p00::Pdr0_SPEC, crate::pac::RW> = {
-            let pdr0 = self.inner.pdr0();
-            // SAFETY: The following transmute is safe, PDR0 is a 32 bit register
-            let addr: *mut u32 = unsafe { transmute(pdr0) };
-            // SAFETY: The following operation is safe since pdr_index is in range [0, 1] TODO: see line 957
-            let addr = unsafe { addr.add(pdr_index as usize) };
-            // SAFETY: The following transmute is safe because PDR0 and PDR1 have the same layout
-            unsafe { transmute(addr) }
-        };
-
-        wdt_call::call_without_cpu_endinit(|| {
-            let v: u32 = (driver as u32) << shift;
-            let m: u32 = 0xF << shift;
-            // SAFETY: Writing PDx and PLx (RW) for pdr_index
-            unsafe {
-                crate::intrinsics::load_modify_store(pdr.ptr(), v, m);
-            }
-        });
-    }
-}
-
-impl From<InputMode> for Mode {
-    fn from(value: InputMode) -> Self {
-        Mode(value.0)
-    }
-}
-
-impl From<(OutputMode, OutputIdx)> for Mode {
-    fn from(value: (OutputMode, OutputIdx)) -> Self {
-        Mode(value.0 .0 | value.1 .0)
-    }
-}
-
-struct Mode(u32);
-impl Mode {
-    const INPUT_NO_PULL_DEVICE: Mode = Self(0);
-    const INPUT_PULL_DOWN: Mode = Self(8);
-    const INPUT_PULL_UP: Mode = Self(0x10);
-    const OUTPUT_PUSH_PULL_GENERAL: Mode = Self(0x80);
-    const OUTPUT_PUSH_PULL_ALT1: Mode = Self(0x88);
-    const OUTPUT_PUSH_PULL_ALT2: Mode = Self(0x90);
-    const OUTPUT_PUSH_PULL_ALT3: Mode = Self(0x98);
-    const OUTPUT_PUSH_PULL_ALT4: Mode = Self(0xA0);
-    const OUTPUT_PUSH_PULL_ALT5: Mode = Self(0xA8);
-    const OUTPUT_PUSH_PULL_ALT6: Mode = Self(0xB0);
-    const OUTPUT_PUSH_PULL_ALT7: Mode = Self(0xB8);
-    const OUTPUT_OPEN_DRAIN_GENERAL: Mode = Self(0xC0);
-    const OUTPUT_OPEN_DRAIN_ALT1: Mode = Self(0xC8);
-    const OUTPUT_OPEN_DRAIN_ALT2: Mode = Self(0xD0);
-    const OUTPUT_OPEN_DRAIN_ALT3: Mode = Self(0xD8);
-    const OUTPUT_OPEN_DRAIN_ALT4: Mode = Self(0xE0);
-    const OUTPUT_OPEN_DRAIN_ALT5: Mode = Self(0xE8);
-    const OUTPUT_OPEN_DRAIN_ALT6: Mode = Self(0xF0);
-    const OUTPUT_OPEN_DRAIN_ALT7: Mode = Self(0xF8);
-}
-
-#[derive(Clone, Copy)]
-pub struct OutputIdx(u32);
-impl OutputIdx {
-    pub(crate) const GENERAL: Self = Self(0x10 << 3);
-    pub(crate) const ALT1: Self = Self(0x11 << 3);
-    pub(crate) const ALT2: Self = Self(0x12 << 3);
-    pub(crate) const ALT3: Self = Self(0x13 << 3);
-    pub(crate) const ALT4: Self = Self(0x14 << 3);
-    pub(crate) const ALT5: Self = Self(0x15 << 3);
-    pub(crate) const ALT6: Self = Self(0x16 << 3);
-    pub(crate) const ALT7: Self = Self(0x17 << 3);
-}
-
-#[derive(Clone, Copy)]
-pub struct RxdIn<M, N> {
-    port: PortNumber,
-    pin_index: u8,
-    select: RxSel,
-    _phantom: PhantomData<(M, N)>,
-}
-
-impl<M, N> RxdIn<M, N> {
-    pub(crate) const fn new(port: PortNumber, pin_index: u8, select: RxSel) -> Self {
-        Self {
-            port,
-            pin_index,
-            select,
-            _phantom: PhantomData,
-        }
-    }
-}
-
 #[derive(Clone, Copy)]
 pub enum RxSel {
     _A,
@@ -1080,25 +2040,6 @@ impl From<RxSel> for u8 {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct TxdOut<M: ModuleId, N: NodeId> {
-    port: PortNumber,
-    pin_index: u8,
-    select: OutputIdx,
-    _phantom: PhantomData<(M, N)>,
-}
-
-impl<M: ModuleId, N: NodeId> TxdOut<M, N> {
-    pub(crate) const fn new(port: PortNumber, pin_index: u8, select: OutputIdx) -> Self {
-        Self {
-            port,
-            pin_index,
-            select,
-            _phantom: PhantomData,
-        }
-    }
-}
-
 #[derive(Clone, Copy)]
 pub struct TxConfig {
     pub mode: TxMode,
@@ -1110,9 +2051,6 @@ pub struct TxConfig {
     pub tx_buffers_start_address: u16,
 }
 
-//     pub standard_filter_list_start_address: u16,
-//     pub extended_filter_list_start_address: u16,
-
 #[derive(Clone, Copy)]
 pub struct RxConfig {
     pub mode: RxMode,
@@ -1128,10 +2066,132 @@ pub struct RxConfig {
     pub rx_fifo0_start_address: u16,
     pub rx_fifo1_start_address: u16,
     pub rx_buffers_start_address: u16,
+    pub standard_filter_list_start_address: u16,
+    pub extended_filter_list_start_address: u16,
 }
 
-#[derive(Clone, Copy)]
-pub struct Pins<M: ModuleId, N: NodeId> {
-    pub tx: TxdOut<M, N>,
-    pub rx: RxdIn<M, N>,
+/// Pins connected to a node, see [`Node::setup_pins`]
+pub struct Pins<M: CanCommon<N>, N> {
+    pub tx: M::Tx,
+    pub rx: M::Rx,
+}
+
+/// Received message borrowed from the message RAM, see [`Node::receive_view`].
+///
+/// Dropping the view releases the Rx element: the Rx FIFO get index is acknowledged, or the
+/// new data flag of the dedicated Rx buffer is cleared.
+pub struct RxView<'a> {
+    element: Rx,
+    message: RxMessage,
+    length: usize,
+    effects: &'a dyn AcknowledgeRx,
+}
+
+impl RxView<'_> {
+    /// Header of the message
+    #[must_use]
+    pub fn message(&self) -> &RxMessage {
+        &self.message
+    }
+
+    #[must_use]
+    pub fn id(&self) -> MessageId {
+        self.message.id
+    }
+
+    #[must_use]
+    pub fn data_length_code(&self) -> DataLenghtCode {
+        self.message.data_length_code
+    }
+
+    #[must_use]
+    pub fn frame_mode(&self) -> FrameMode {
+        self.message.frame_mode
+    }
+
+    #[must_use]
+    pub fn timestamp(&self) -> Timestamp {
+        self.message.timestamp
+    }
+
+    /// The message is a remote frame, its data is empty
+    #[must_use]
+    pub fn is_remote_frame(&self) -> bool {
+        self.message.remote_transmit_request
+    }
+
+    /// The transmitter was error passive, only set for CAN FD frames
+    #[must_use]
+    pub fn error_state_indicator(&self) -> bool {
+        self.message.error_state_indicator
+    }
+
+    /// Data of the message, truncated to the data field size of the Rx element
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        // SAFETY: length has been limited to the data field size of the element. The element
+        // is a dedicated Rx buffer or belongs to an Rx FIFO in blocking mode, see
+        // `Node::receive_view`, so the node does not write it before it is released on drop.
+        unsafe { self.element.data(self.length) }
+    }
+}
+
+impl Drop for RxView<'_> {
+    fn drop(&mut self) {
+        self.effects
+            .acknowledge_rx_element(self.message.from, self.message.buffer_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::effects::INTERRUPT_MASK;
+    use super::*;
+    use crate::can::msg::RxBufferId;
+
+    #[test]
+    fn test_interrupt_bits() {
+        let mut bits = 0;
+        for interrupt in Interrupt::ALL {
+            assert_eq!(interrupt.bit() & INTERRUPT_MASK, interrupt.bit());
+            assert_eq!(bits & interrupt.bit(), 0);
+            bits |= interrupt.bit();
+        }
+        assert_eq!(bits, INTERRUPT_MASK);
+    }
+
+    #[test]
+    fn test_to_owned_frame() {
+        use embedded_can::Frame as _;
+
+        let message = |data_length_code, frame_mode| RxMessage {
+            buffer_id: RxBufferId::new(0).unwrap(),
+            id: MessageId {
+                data: 0x123,
+                length: MessageIdLength::Standard,
+            },
+            data_length_code,
+            from: ReadFrom::RxFifo0,
+            frame_mode,
+            remote_transmit_request: false,
+            error_state_indicator: false,
+            timestamp: Timestamp {
+                raw: 0,
+                nanos: None,
+            },
+        };
+        let data: [u8; 64] = core::array::from_fn(|i| u8::try_from(i + 1).unwrap());
+
+        // classic frame with a data length code above 8
+        let msg = message(DataLenghtCode::_12, FrameMode::Standard);
+        let frame = to_owned_frame(&msg, &data, 12).unwrap();
+        assert_eq!(Some(frame.data()), data.get(..8));
+        assert_eq!(frame.as_frame().frame_mode, Some(FrameMode::Standard));
+
+        // CAN FD frame truncated to the data field size of the Rx element
+        let msg = message(DataLenghtCode::_64, FrameMode::FdLong);
+        let frame = to_owned_frame(&msg, &data, 16).unwrap();
+        assert_eq!(Some(frame.data()), data.get(..16));
+        assert_eq!(frame.as_frame().frame_mode, None);
+    }
 }
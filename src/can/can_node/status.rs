@@ -0,0 +1,269 @@
+#![allow(clippy::module_name_repetitions)]
+
+/// Type of the last error that occurred on the bus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LastErrorCode {
+    NoError,
+    StuffError,
+    FormError,
+    AckError,
+    Bit1Error,
+    Bit0Error,
+    CrcError,
+    /// No bus event since the last read of the protocol status
+    NoChange,
+}
+
+impl From<u8> for LastErrorCode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::NoError,
+            1 => Self::StuffError,
+            2 => Self::FormError,
+            3 => Self::AckError,
+            4 => Self::Bit1Error,
+            5 => Self::Bit0Error,
+            6 => Self::CrcError,
+            _ => Self::NoChange,
+        }
+    }
+}
+
+/// Current activity of the node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activity {
+    Synchronizing,
+    Idle,
+    Receiver,
+    Transmitter,
+}
+
+impl From<u8> for Activity {
+    fn from(value: u8) -> Self {
+        match value & 0x3 {
+            0 => Self::Synchronizing,
+            1 => Self::Idle,
+            2 => Self::Receiver,
+            _ => Self::Transmitter,
+        }
+    }
+}
+
+/// Content of the protocol status register (PSR).
+///
+/// Reading the protocol status resets the last error codes to [`LastErrorCode::NoChange`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtocolStatus {
+    pub last_error_code: LastErrorCode,
+    /// Last error code of the data phase of CAN FD frames with bit rate switching
+    pub data_last_error_code: LastErrorCode,
+    pub activity: Activity,
+    pub error_passive: bool,
+    /// At least one of the error counters has reached the warning limit of 96
+    pub warning: bool,
+    pub bus_off: bool,
+    /// Transmitter delay measured by the compensation, in module clock cycles
+    pub transmitter_delay_compensation_value: u8,
+}
+
+/// Content of the error counter register (ECR)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorCounters {
+    pub transmit: u8,
+    pub receive: u8,
+    /// The receive error counter has reached the error passive level of 128
+    pub receive_passive: bool,
+    /// Number of errors logged since the last read of the error counters
+    pub logging: u8,
+}
+
+/// Fault confinement state of a node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorState {
+    Active,
+    Passive,
+    BusOff,
+}
+
+/// How a node leaves the bus-off state, see [`crate::can::Node::handle_bus_off`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BusOffRecovery {
+    /// Restart as soon as bus-off is detected, the node rejoins the bus after the recovery
+    /// sequence of 128 occurrences of 11 consecutive recessive bits, whose length is fixed
+    /// by the hardware. Once `sequences` recovery sequences have been started without the
+    /// node becoming error active in between, it stays bus-off as with
+    /// [`BusOffRecovery::Manual`]. 0 for no limit.
+    Automatic { sequences: u8 },
+    /// Stay bus-off until [`crate::can::Node::recover_from_bus_off`] is called
+    Manual,
+    /// Restart after `delay` calls to `handle_bus_off`, the delay doubles after each
+    /// consecutive bus-off up to `max_delay`
+    BackOff { delay: u32, max_delay: u32 },
+}
+
+impl Default for BusOffRecovery {
+    /// Automatic recovery without limit
+    fn default() -> Self {
+        BusOffRecovery::Automatic { sequences: 0 }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub(super) struct BusOffState {
+    pub(super) recovery: BusOffRecovery,
+    pub(super) consecutive: u8,
+    pub(super) remaining: Option<u32>,
+}
+
+impl BusOffState {
+    pub(super) fn new(recovery: BusOffRecovery) -> Self {
+        Self {
+            recovery,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the node shall be restarted now, called while the node is bus-off
+    pub(super) fn should_restart(&mut self) -> bool {
+        match self.recovery {
+            BusOffRecovery::Automatic { sequences } => {
+                if sequences != 0 && self.consecutive >= sequences {
+                    return false;
+                }
+                self.consecutive = self.consecutive.saturating_add(1);
+                true
+            }
+            BusOffRecovery::Manual => false,
+            BusOffRecovery::BackOff { delay, max_delay } => {
+                let factor = 1u32
+                    .checked_shl(u32::from(self.consecutive))
+                    .unwrap_or(u32::MAX);
+                let remaining = self
+                    .remaining
+                    .get_or_insert(delay.saturating_mul(factor).min(max_delay));
+
+                if *remaining == 0 {
+                    self.remaining = None;
+                    self.consecutive = self.consecutive.saturating_add(1);
+                    true
+                } else {
+                    *remaining -= 1;
+                    false
+                }
+            }
+        }
+    }
+
+    /// Reset the back-off and the recovery sequence count once the node is error active
+    /// again
+    pub(super) fn reset(&mut self) {
+        self.consecutive = 0;
+        self.remaining = None;
+    }
+}
+
+/// Frames received and last error codes seen while listening to the bus with a candidate
+/// bit timing
+#[derive(Default)]
+pub(super) struct CandidateScore {
+    /// Frames stored in the message RAM since listening started
+    frames: u32,
+    /// Whether a frame has been transferred without error
+    confirmed: bool,
+    errors: u32,
+}
+
+impl CandidateScore {
+    /// Account for a read of the last error code, which is reset by each read so that
+    /// [`LastErrorCode::NoError`] is only seen after a frame has been transferred, and for
+    /// the frames received so far
+    pub(super) fn record(&mut self, code: LastErrorCode, frames: u32) {
+        self.frames = frames;
+        match code {
+            LastErrorCode::NoChange => {}
+            LastErrorCode::NoError => self.confirmed = true,
+            _ => self.errors = self.errors.saturating_add(1),
+        }
+    }
+
+    /// The bit timing is wrong as soon as an error is seen
+    pub(super) fn is_rejected(&self) -> bool {
+        self.errors > 0
+    }
+
+    /// Enough frames have been received, and at least one has been seen without error
+    /// in the judged phase
+    pub(super) fn is_match(&self, frames: u32) -> bool {
+        !self.is_rejected() && self.confirmed && self.frames >= frames
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidate_score() {
+        let mut score = CandidateScore::default();
+        score.record(LastErrorCode::NoChange, 0);
+        score.record(LastErrorCode::NoError, 1);
+        assert!(!score.is_match(2));
+        score.record(LastErrorCode::NoChange, 2);
+        assert!(score.is_match(2));
+
+        // frames received without any transfer seen by the judged last error code
+        let mut unconfirmed = CandidateScore::default();
+        unconfirmed.record(LastErrorCode::NoChange, 5);
+        assert!(!unconfirmed.is_match(2));
+
+        score.record(LastErrorCode::StuffError, 2);
+        assert!(score.is_rejected());
+        assert!(!score.is_match(2));
+    }
+
+    #[test]
+    fn test_back_off() {
+        let mut state = BusOffState::new(BusOffRecovery::BackOff {
+            delay: 2,
+            max_delay: 5,
+        });
+
+        let polls_until_restart = |state: &mut BusOffState| {
+            let mut polls = 1;
+            while !state.should_restart() {
+                polls += 1;
+            }
+            polls
+        };
+
+        assert_eq!(polls_until_restart(&mut state), 3);
+        assert_eq!(polls_until_restart(&mut state), 5);
+        assert_eq!(polls_until_restart(&mut state), 6);
+        assert_eq!(polls_until_restart(&mut state), 6);
+
+        state.reset();
+        assert_eq!(polls_until_restart(&mut state), 3);
+    }
+
+    #[test]
+    fn test_automatic() {
+        let mut state = BusOffState::new(BusOffRecovery::Automatic { sequences: 2 });
+        assert!(state.should_restart());
+        assert!(state.should_restart());
+        assert!(!state.should_restart());
+        assert!(!state.should_restart());
+        state.reset();
+        assert!(state.should_restart());
+
+        let mut state = BusOffState::new(BusOffRecovery::default());
+        for _ in 0..=u8::MAX {
+            assert!(state.should_restart());
+        }
+        assert!(state.should_restart());
+    }
+
+    #[test]
+    fn test_manual() {
+        assert!(!BusOffState::new(BusOffRecovery::Manual).should_restart());
+    }
+}
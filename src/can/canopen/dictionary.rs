@@ -0,0 +1,208 @@
+//! Object dictionary: a static table of entries over a storage of their values.
+
+/// Access to an entry through the SDO server and the PDOs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+impl Access {
+    fn is_readable(self) -> bool {
+        matches!(self, Access::ReadOnly | Access::ReadWrite)
+    }
+
+    fn is_writable(self) -> bool {
+        matches!(self, Access::WriteOnly | Access::ReadWrite)
+    }
+}
+
+/// Entry of the object dictionary. Its value is stored little endian, as transferred on
+/// the bus, in the `length` bytes at `offset` of the dictionary storage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub index: u16,
+    pub subindex: u8,
+    pub access: Access,
+    /// Whether the entry can be mapped into PDOs
+    pub mappable: bool,
+    pub offset: usize,
+    pub length: usize,
+}
+
+impl Entry {
+    #[must_use]
+    pub const fn new(
+        index: u16,
+        subindex: u8,
+        access: Access,
+        offset: usize,
+        length: usize,
+    ) -> Self {
+        Self {
+            index,
+            subindex,
+            access,
+            mappable: false,
+            offset,
+            length,
+        }
+    }
+
+    /// Allow mapping the entry into PDOs
+    #[must_use]
+    pub const fn mappable(self) -> Self {
+        Self {
+            mappable: true,
+            ..self
+        }
+    }
+
+    pub(super) fn is_readable(&self) -> bool {
+        self.access.is_readable()
+    }
+
+    pub(super) fn is_writable(&self) -> bool {
+        self.access.is_writable()
+    }
+}
+
+/// Reason of an aborted SDO transfer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbortCode {
+    ToggleBitNotAlternated,
+    Timeout,
+    InvalidCommand,
+    WriteOnly,
+    ReadOnly,
+    ObjectDoesNotExist,
+    SubindexDoesNotExist,
+    LengthMismatch,
+    LengthTooHigh,
+    LengthTooLow,
+    General,
+}
+
+impl From<AbortCode> for u32 {
+    fn from(value: AbortCode) -> Self {
+        match value {
+            AbortCode::ToggleBitNotAlternated => 0x0503_0000,
+            AbortCode::Timeout => 0x0504_0000,
+            AbortCode::InvalidCommand => 0x0504_0001,
+            AbortCode::WriteOnly => 0x0601_0001,
+            AbortCode::ReadOnly => 0x0601_0002,
+            AbortCode::ObjectDoesNotExist => 0x0602_0000,
+            AbortCode::SubindexDoesNotExist => 0x0609_0011,
+            AbortCode::LengthMismatch => 0x0607_0010,
+            AbortCode::LengthTooHigh => 0x0607_0012,
+            AbortCode::LengthTooLow => 0x0607_0013,
+            AbortCode::General => 0x0800_0000,
+        }
+    }
+}
+
+/// Object dictionary of the node
+pub struct ObjectDictionary<'a> {
+    entries: &'static [Entry],
+    storage: &'a mut [u8],
+}
+
+impl<'a> ObjectDictionary<'a> {
+    /// Create a dictionary, `None` if an entry is empty or exceeds the storage
+    #[must_use]
+    pub fn new(entries: &'static [Entry], storage: &'a mut [u8]) -> Option<Self> {
+        let fits = |entry: &Entry| {
+            entry
+                .offset
+                .checked_add(entry.length)
+                .is_some_and(|end| entry.length > 0 && end <= storage.len())
+        };
+        if !entries.iter().all(fits) {
+            return None;
+        }
+        Some(Self { entries, storage })
+    }
+
+    /// Value of an entry, regardless of its access
+    #[must_use]
+    pub fn get(&self, index: u16, subindex: u8) -> Option<&[u8]> {
+        let entry = self.find(index, subindex).ok()?;
+        Some(self.value(&entry))
+    }
+
+    /// Mutable value of an entry, regardless of its access. Event-driven TPDOs mapping
+    /// the entry are not triggered, see [`Slave::trigger_tpdo`](super::Slave::trigger_tpdo).
+    #[must_use]
+    pub fn get_mut(&mut self, index: u16, subindex: u8) -> Option<&mut [u8]> {
+        let entry = self.find(index, subindex).ok()?;
+        Some(self.value_mut(&entry))
+    }
+
+    pub(super) fn find(&self, index: u16, subindex: u8) -> Result<Entry, AbortCode> {
+        let mut index_found = false;
+        for entry in self.entries.iter().filter(|entry| entry.index == index) {
+            if entry.subindex == subindex {
+                return Ok(*entry);
+            }
+            index_found = true;
+        }
+
+        if index_found {
+            Err(AbortCode::SubindexDoesNotExist)
+        } else {
+            Err(AbortCode::ObjectDoesNotExist)
+        }
+    }
+
+    pub(super) fn value(&self, entry: &Entry) -> &[u8] {
+        self.storage
+            .get(entry.offset..)
+            .and_then(|value| value.get(..entry.length))
+            .unwrap_or_default()
+    }
+
+    pub(super) fn value_mut(&mut self, entry: &Entry) -> &mut [u8] {
+        self.storage
+            .get_mut(entry.offset..)
+            .and_then(|value| value.get_mut(..entry.length))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ENTRIES: &[Entry] = &[
+        Entry::new(0x1000, 0, Access::ReadOnly, 0, 4),
+        Entry::new(0x2000, 1, Access::ReadWrite, 4, 2).mappable(),
+        Entry::new(0x2000, 2, Access::WriteOnly, 6, 1),
+    ];
+
+    #[test]
+    fn test_dictionary() {
+        let mut storage = [0; 7];
+        assert!(ObjectDictionary::new(ENTRIES, &mut storage[..6]).is_none());
+
+        let mut dictionary = ObjectDictionary::new(ENTRIES, &mut storage).unwrap();
+        dictionary
+            .get_mut(0x2000, 1)
+            .unwrap()
+            .copy_from_slice(&0x1234u16.to_le_bytes());
+        assert_eq!(dictionary.get(0x2000, 1), Some([0x34, 0x12].as_slice()));
+        assert_eq!(dictionary.get(0x1000, 0), Some([0; 4].as_slice()));
+
+        assert_eq!(
+            dictionary.find(0x2000, 3),
+            Err(AbortCode::SubindexDoesNotExist)
+        );
+        assert_eq!(
+            dictionary.find(0x3000, 0),
+            Err(AbortCode::ObjectDoesNotExist)
+        );
+        let entry = dictionary.find(0x2000, 2).unwrap();
+        assert!(entry.is_writable());
+        assert!(!entry.is_readable());
+    }
+}
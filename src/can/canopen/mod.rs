@@ -0,0 +1,117 @@
+//! CANopen slave (CiA 301) on 11 bit identifiers.
+//!
+//! [`Slave`] implements the NMT state machine, the heartbeat producer and consumer, an
+//! expedited and segmented SDO server over an [`ObjectDictionary`], and the transmit and
+//! receive PDOs triggered by events and SYNC. [`Slave::filters`] returns the acceptance
+//! filters of the COB-IDs consumed by the node, to be set with
+//! [`Node::setup_filters`](crate::can::Node::setup_filters).
+//!
+//! The dictionary entries, the PDO mappings and the communication parameters are static
+//! tables: they are not exposed as objects of the dictionary and cannot be changed by the
+//! master.
+//!
+//! The slave is a [protocol layer](super#protocol-layers) driven by [`Slave::receive`],
+//! [`Slave::next_frame`] and [`Slave::poll`].
+
+#![allow(clippy::module_name_repetitions)]
+
+mod dictionary;
+mod nmt;
+mod pdo;
+mod sdo;
+mod slave;
+
+pub use dictionary::{AbortCode, Access, Entry, ObjectDictionary};
+pub use nmt::{HeartbeatConsumer, NmtState, MAX_HEARTBEAT_CONSUMERS};
+pub use pdo::{MappedObject, PdoCobId, PdoConfig, Transmission, MAX_PDOS};
+pub use slave::{Config, Event, Filters, Slave, MAX_FILTERS};
+
+use super::frame::OwnedFrame;
+
+/// COB-ID of the NMT commands
+pub const NMT_COB_ID: u16 = 0x000;
+/// COB-ID of the SYNC messages
+pub const SYNC_COB_ID: u16 = 0x080;
+
+/// Function codes of the predefined connection set, the node-ID is added to them
+const TPDO_FUNCTION_CODES: [u16; MAX_PDOS] = [0x180, 0x280, 0x380, 0x480];
+const RPDO_FUNCTION_CODES: [u16; MAX_PDOS] = [0x200, 0x300, 0x400, 0x500];
+const SDO_TX_FUNCTION_CODE: u16 = 0x580;
+const SDO_RX_FUNCTION_CODE: u16 = 0x600;
+const HEARTBEAT_FUNCTION_CODE: u16 = 0x700;
+
+/// Highest 11 bit identifier
+const MAX_COB_ID: u16 = 0x7FF;
+
+/// Node-ID, from 1 to 127
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeId(u8);
+
+impl NodeId {
+    /// Create a node-ID, `None` if it is not between 1 and 127
+    #[must_use]
+    pub const fn new(id: u8) -> Option<Self> {
+        match id {
+            1..=127 => Some(Self(id)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn raw(self) -> u8 {
+        self.0
+    }
+
+    /// COB-ID of a function code of the predefined connection set
+    fn cob_id(self, function_code: u16) -> u16 {
+        function_code | u16::from(self.0)
+    }
+}
+
+/// Classic frame with an 11 bit COB-ID, `None` if the COB-ID exceeds 11 bits or the data
+/// is longer than 8 bytes
+fn new_frame(cob_id: u16, data: &[u8]) -> Option<OwnedFrame> {
+    use embedded_can::Frame as _;
+
+    OwnedFrame::new(embedded_can::StandardId::new(cob_id)?, data)
+}
+
+/// COB-ID of a received data frame, `None` for extended identifiers and remote frames
+fn frame_cob_id(frame: &impl embedded_can::Frame) -> Option<u16> {
+    match frame.id() {
+        embedded_can::Id::Standard(id) if !frame.is_remote_frame() => Some(id.as_raw()),
+        embedded_can::Id::Standard(_) | embedded_can::Id::Extended(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::can::msg::FrameMode;
+    use embedded_can::{ExtendedId, Frame as _, StandardId};
+
+    #[test]
+    fn test_node_id() {
+        assert_eq!(NodeId::new(0), None);
+        assert_eq!(NodeId::new(128), None);
+        let node_id = NodeId::new(0x12).unwrap();
+        assert_eq!(node_id.raw(), 0x12);
+        assert_eq!(node_id.cob_id(SDO_RX_FUNCTION_CODE), 0x612);
+        assert_eq!(node_id.cob_id(HEARTBEAT_FUNCTION_CODE), 0x712);
+    }
+
+    #[test]
+    fn test_frame() {
+        let frame = new_frame(0x181, &[1, 2, 3]).unwrap();
+        assert_eq!(frame.data(), &[1, 2, 3]);
+        assert_eq!(frame.as_frame().frame_mode, Some(FrameMode::Standard));
+        assert_eq!(frame_cob_id(&frame), Some(0x181));
+        assert!(new_frame(0x800, &[]).is_none());
+        assert!(new_frame(0x181, &[0; 9]).is_none());
+
+        let remote = OwnedFrame::new_remote(StandardId::new(0x181).unwrap(), 1).unwrap();
+        assert_eq!(frame_cob_id(&remote), None);
+        let extended = OwnedFrame::new(ExtendedId::new(0x181).unwrap(), &[]).unwrap();
+        assert_eq!(frame_cob_id(&extended), None);
+    }
+}
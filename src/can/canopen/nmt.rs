@@ -0,0 +1,121 @@
+//! Network management: NMT states and commands, heartbeat.
+
+use super::NodeId;
+
+/// Maximum number of nodes whose heartbeat is monitored
+pub const MAX_HEARTBEAT_CONSUMERS: usize = 8;
+
+/// NMT state of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtState {
+    /// Not started yet, or being reset
+    Initialising,
+    Stopped,
+    Operational,
+    PreOperational,
+}
+
+impl NmtState {
+    /// State sent in a heartbeat, the boot-up message sending [`NmtState::Initialising`]
+    pub(super) fn from_heartbeat(data: &[u8]) -> Option<Self> {
+        let [state] = <[u8; 1]>::try_from(data).ok()?;
+        match state & 0x7F {
+            0x00 => Some(NmtState::Initialising),
+            0x04 => Some(NmtState::Stopped),
+            0x05 => Some(NmtState::Operational),
+            0x7F => Some(NmtState::PreOperational),
+            _ => None,
+        }
+    }
+}
+
+impl From<NmtState> for u8 {
+    fn from(value: NmtState) -> Self {
+        match value {
+            NmtState::Initialising => 0x00,
+            NmtState::Stopped => 0x04,
+            NmtState::Operational => 0x05,
+            NmtState::PreOperational => 0x7F,
+        }
+    }
+}
+
+/// Command sent by the NMT master
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum NmtCommand {
+    Start,
+    Stop,
+    EnterPreOperational,
+    ResetNode,
+    ResetCommunication,
+}
+
+impl NmtCommand {
+    /// Command of an NMT frame sent to `node_id` or to all the nodes
+    pub(super) fn parse(data: &[u8], node_id: NodeId) -> Option<Self> {
+        let [command, target] = <[u8; 2]>::try_from(data).ok()?;
+        if target != 0 && target != node_id.raw() {
+            return None;
+        }
+
+        match command {
+            0x01 => Some(NmtCommand::Start),
+            0x02 => Some(NmtCommand::Stop),
+            0x80 => Some(NmtCommand::EnterPreOperational),
+            0x81 => Some(NmtCommand::ResetNode),
+            0x82 => Some(NmtCommand::ResetCommunication),
+            _ => None,
+        }
+    }
+
+    /// State entered with this command, `None` for the resets
+    pub(super) fn target_state(self) -> Option<NmtState> {
+        match self {
+            NmtCommand::Start => Some(NmtState::Operational),
+            NmtCommand::Stop => Some(NmtState::Stopped),
+            NmtCommand::EnterPreOperational => Some(NmtState::PreOperational),
+            NmtCommand::ResetNode | NmtCommand::ResetCommunication => None,
+        }
+    }
+}
+
+/// Node whose heartbeat is monitored. Monitoring starts with the first heartbeat
+/// received from the node.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConsumer {
+    pub node_id: NodeId,
+    /// Heartbeat consumer time in milliseconds
+    pub time: u16,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let node_id = NodeId::new(5).unwrap();
+        assert_eq!(
+            NmtCommand::parse(&[0x01, 5], node_id),
+            Some(NmtCommand::Start)
+        );
+        assert_eq!(
+            NmtCommand::parse(&[0x82, 0], node_id),
+            Some(NmtCommand::ResetCommunication)
+        );
+        assert_eq!(NmtCommand::parse(&[0x01, 6], node_id), None);
+        assert_eq!(NmtCommand::parse(&[0x03, 5], node_id), None);
+        assert_eq!(NmtCommand::parse(&[0x01], node_id), None);
+
+        assert_eq!(
+            NmtState::from_heartbeat(&[0x05]),
+            Some(NmtState::Operational)
+        );
+        assert_eq!(
+            NmtState::from_heartbeat(&[0x85]),
+            Some(NmtState::Operational)
+        );
+        assert_eq!(NmtState::from_heartbeat(&[0x01]), None);
+        assert_eq!(u8::from(NmtState::PreOperational), 0x7F);
+    }
+}
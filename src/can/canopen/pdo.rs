@@ -0,0 +1,307 @@
+//! Process data objects: mapping and transmission triggers.
+
+use super::dictionary::{Entry, ObjectDictionary};
+use super::MAX_COB_ID;
+
+/// Maximum number of transmit and receive PDOs
+pub const MAX_PDOS: usize = 4;
+
+/// Data bytes of a PDO
+const PDO_LENGTH: usize = 8;
+/// Highest transmission type counting SYNC messages
+const MAX_SYNC_PERIOD: u8 = 240;
+
+/// COB-ID of a PDO
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PdoCobId {
+    /// COB-ID of the predefined connection set for the position of the PDO, from TPDO1 at
+    /// 0x180 + node-ID and RPDO1 at 0x200 + node-ID
+    Default,
+    Fixed(u16),
+}
+
+/// When a PDO is transmitted, or a received PDO written to the dictionary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transmission {
+    /// With the first SYNC after an event (transmission type 0)
+    SyncAcyclic,
+    /// Every given number of SYNC, from 1 to 240 (transmission types 1 to 240)
+    SyncCyclic(u8),
+    /// On event, the event timer or a trigger of the application (transmission types 254
+    /// and 255)
+    Event,
+}
+
+/// Object of the dictionary mapped into a PDO. Whole entries are mapped.
+#[derive(Debug, Clone, Copy)]
+pub struct MappedObject {
+    pub index: u16,
+    pub subindex: u8,
+}
+
+/// Communication and mapping parameters of a PDO
+#[derive(Debug, Clone, Copy)]
+pub struct PdoConfig {
+    pub cob_id: PdoCobId,
+    pub transmission: Transmission,
+    /// Minimum time between two transmissions of a TPDO, in multiples of 100 µs. Unused
+    /// for RPDOs.
+    pub inhibit_time: u16,
+    /// Period of an event-driven TPDO in milliseconds, 0 to disable. Unused for RPDOs.
+    pub event_timer: u16,
+    /// Objects mapped in order into the up to 8 bytes of the PDO
+    pub mapping: &'static [MappedObject],
+}
+
+impl PdoConfig {
+    #[must_use]
+    pub const fn new(transmission: Transmission, mapping: &'static [MappedObject]) -> Self {
+        Self {
+            cob_id: PdoCobId::Default,
+            transmission,
+            inhibit_time: 0,
+            event_timer: 0,
+            mapping,
+        }
+    }
+
+    /// Whether the COB-ID and the transmission type are valid, and the mapped objects
+    /// exist, can be mapped with `access` and fit into a PDO
+    pub(super) fn is_valid(
+        &self,
+        dictionary: &ObjectDictionary,
+        access: fn(&Entry) -> bool,
+    ) -> bool {
+        let sync_period_valid = !matches!(
+            self.transmission,
+            Transmission::SyncCyclic(period) if period == 0 || period > MAX_SYNC_PERIOD
+        );
+        let cob_id_valid = !matches!(self.cob_id, PdoCobId::Fixed(cob_id) if cob_id > MAX_COB_ID);
+        if !(sync_period_valid && cob_id_valid) {
+            return false;
+        }
+
+        let mut length = 0;
+        for object in self.mapping {
+            match dictionary.find(object.index, object.subindex) {
+                Ok(entry) if entry.mappable && access(&entry) => length += entry.length,
+                _ => return false,
+            }
+        }
+        length <= PDO_LENGTH
+    }
+
+    /// Data of the PDO, from the values of the mapped objects
+    pub(super) fn map(&self, dictionary: &ObjectDictionary) -> ([u8; PDO_LENGTH], usize) {
+        let mut data = [0; PDO_LENGTH];
+        let mut length = 0;
+        for entry in self.entries(dictionary) {
+            let value = dictionary.value(&entry);
+            let end = length + value.len();
+            if let Some(data) = data.get_mut(length..end) {
+                data.copy_from_slice(value);
+            }
+            length = end;
+        }
+        (data, length.min(PDO_LENGTH))
+    }
+
+    /// Write the values of the mapped objects, `false` if the PDO is too short
+    pub(super) fn unmap(&self, dictionary: &mut ObjectDictionary, data: &[u8]) -> bool {
+        let length: usize = self.entries(dictionary).map(|entry| entry.length).sum();
+        if data.len() < length {
+            return false;
+        }
+
+        let mut offset = 0;
+        for object in self.mapping {
+            let Ok(entry) = dictionary.find(object.index, object.subindex) else {
+                continue;
+            };
+            let end = offset + entry.length;
+            if let Some(data) = data.get(offset..end) {
+                dictionary.value_mut(&entry).copy_from_slice(data);
+            }
+            offset = end;
+        }
+        true
+    }
+
+    fn entries<'d>(&'d self, dictionary: &'d ObjectDictionary) -> impl Iterator<Item = Entry> + 'd {
+        self.mapping
+            .iter()
+            .filter_map(|object| dictionary.find(object.index, object.subindex).ok())
+    }
+}
+
+/// Transmission state of a TPDO
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Tpdo {
+    /// Event of the application waiting for a SYNC
+    pub(super) event: bool,
+    /// Waiting to be transmitted
+    pub(super) pending: bool,
+    /// SYNC messages counted since the last transmission
+    pub(super) sync_count: u8,
+    /// Time of the last transmission, or of the start of the operational state
+    pub(super) since: u32,
+    /// Whether the inhibit time has started with a transmission
+    pub(super) sent: bool,
+}
+
+impl Tpdo {
+    /// Event of the application
+    pub(super) fn trigger(&mut self, config: &PdoConfig) {
+        match config.transmission {
+            Transmission::SyncAcyclic => self.event = true,
+            Transmission::SyncCyclic(_) => {}
+            Transmission::Event => self.pending = true,
+        }
+    }
+
+    pub(super) fn sync(&mut self, config: &PdoConfig) {
+        match config.transmission {
+            Transmission::SyncAcyclic => {
+                self.pending |= self.event;
+                self.event = false;
+            }
+            Transmission::SyncCyclic(period) => {
+                self.sync_count = self.sync_count.saturating_add(1);
+                if self.sync_count >= period {
+                    self.sync_count = 0;
+                    self.pending = true;
+                }
+            }
+            Transmission::Event => {}
+        }
+    }
+
+    /// Whether the PDO is to be transmitted now
+    pub(super) fn is_due(self, config: &PdoConfig, now: u32) -> bool {
+        let elapsed = now.wrapping_sub(self.since);
+        let event_timer = u32::from(config.event_timer) * 1000;
+        let timer_expired = config.transmission == Transmission::Event
+            && event_timer != 0
+            && elapsed >= event_timer;
+        let inhibited = self.sent && elapsed < u32::from(config.inhibit_time) * 100;
+
+        (self.pending || timer_expired) && !inhibited
+    }
+
+    pub(super) fn transmitted(&mut self, now: u32) {
+        self.pending = false;
+        self.since = now;
+        self.sent = true;
+    }
+}
+
+/// Reception state of an RPDO written to the dictionary with the next SYNC
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Rpdo {
+    pub(super) data: [u8; PDO_LENGTH],
+    pub(super) length: usize,
+    pub(super) pending: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::dictionary::Access;
+    use super::*;
+
+    const ENTRIES: &[Entry] = &[
+        Entry::new(0x6000, 1, Access::ReadOnly, 0, 1).mappable(),
+        Entry::new(0x6000, 2, Access::ReadWrite, 1, 2).mappable(),
+        Entry::new(0x6000, 3, Access::ReadWrite, 3, 4),
+        Entry::new(0x6001, 0, Access::ReadWrite, 7, 8).mappable(),
+    ];
+    const MAPPING: &[MappedObject] = &[
+        MappedObject {
+            index: 0x6000,
+            subindex: 2,
+        },
+        MappedObject {
+            index: 0x6000,
+            subindex: 1,
+        },
+    ];
+
+    #[test]
+    fn test_mapping() {
+        let mut storage = [0; 15];
+        let mut dictionary = ObjectDictionary::new(ENTRIES, &mut storage).unwrap();
+        let config = PdoConfig::new(Transmission::Event, MAPPING);
+        assert!(config.is_valid(&dictionary, Entry::is_readable));
+        // 0x6000:1 is read only
+        assert!(!config.is_valid(&dictionary, Entry::is_writable));
+
+        assert!(config.unmap(&mut dictionary, &[0x34, 0x12, 0x56, 0xFF]));
+        assert!(!config.unmap(&mut dictionary, &[0x34, 0x12]));
+        assert_eq!(dictionary.get(0x6000, 2), Some([0x34, 0x12].as_slice()));
+        assert_eq!(dictionary.get(0x6000, 1), Some([0x56].as_slice()));
+        let (data, length) = config.map(&dictionary);
+        assert_eq!(data.get(..length), Some([0x34, 0x12, 0x56].as_slice()));
+
+        // not mappable, too long, invalid transmission type and COB-ID
+        let not_mappable = &[MappedObject {
+            index: 0x6000,
+            subindex: 3,
+        }];
+        let too_long = &[
+            MappedObject {
+                index: 0x6001,
+                subindex: 0,
+            },
+            MappedObject {
+                index: 0x6000,
+                subindex: 1,
+            },
+        ];
+        for config in [
+            PdoConfig::new(Transmission::Event, not_mappable),
+            PdoConfig::new(Transmission::Event, too_long),
+            PdoConfig::new(Transmission::SyncCyclic(241), MAPPING),
+            PdoConfig {
+                cob_id: PdoCobId::Fixed(0x800),
+                ..PdoConfig::new(Transmission::Event, MAPPING)
+            },
+        ] {
+            assert!(!config.is_valid(&dictionary, Entry::is_readable));
+        }
+    }
+
+    #[test]
+    fn test_triggers() {
+        let mut config = PdoConfig::new(Transmission::Event, MAPPING);
+        config.inhibit_time = 10;
+        config.event_timer = 100;
+        let mut tpdo = Tpdo::default();
+        assert!(!tpdo.is_due(&config, 0));
+        tpdo.trigger(&config);
+        assert!(tpdo.is_due(&config, 0));
+        tpdo.transmitted(0);
+        // inhibited for 1 ms
+        tpdo.trigger(&config);
+        assert!(!tpdo.is_due(&config, 999));
+        assert!(tpdo.is_due(&config, 1000));
+        tpdo.transmitted(1000);
+        // event timer
+        assert!(!tpdo.is_due(&config, 100_999));
+        assert!(tpdo.is_due(&config, 101_000));
+
+        let config = PdoConfig::new(Transmission::SyncCyclic(2), MAPPING);
+        let mut tpdo = Tpdo::default();
+        tpdo.sync(&config);
+        assert!(!tpdo.is_due(&config, 0));
+        tpdo.sync(&config);
+        assert!(tpdo.is_due(&config, 0));
+
+        let config = PdoConfig::new(Transmission::SyncAcyclic, MAPPING);
+        let mut tpdo = Tpdo::default();
+        tpdo.sync(&config);
+        assert!(!tpdo.is_due(&config, 0));
+        tpdo.trigger(&config);
+        assert!(!tpdo.is_due(&config, 0));
+        tpdo.sync(&config);
+        assert!(tpdo.is_due(&config, 0));
+    }
+}
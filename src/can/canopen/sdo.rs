@@ -0,0 +1,331 @@
+//! SDO server with expedited and segmented transfers.
+
+use super::dictionary::{AbortCode, Entry, ObjectDictionary};
+
+/// Client command specifiers
+const INITIATE_DOWNLOAD: u8 = 1;
+const DOWNLOAD_SEGMENT: u8 = 0;
+const INITIATE_UPLOAD: u8 = 2;
+const UPLOAD_SEGMENT: u8 = 3;
+const ABORT: u8 = 4;
+
+/// Bits of the command byte
+const TOGGLE: u8 = 0x10;
+const EXPEDITED: u8 = 0x02;
+const SIZE_INDICATED: u8 = 0x01;
+const LAST_SEGMENT: u8 = 0x01;
+
+/// Data bytes of an expedited transfer and of a segment
+const EXPEDITED_LENGTH: usize = 4;
+const SEGMENT_LENGTH: usize = 7;
+
+#[derive(Debug, Clone, Copy)]
+enum Transfer {
+    Idle,
+    Download {
+        entry: Entry,
+        offset: usize,
+        toggle: bool,
+        since: u32,
+    },
+    Upload {
+        entry: Entry,
+        offset: usize,
+        toggle: bool,
+        since: u32,
+    },
+}
+
+/// Outcome of an SDO request
+pub(super) struct Response {
+    /// Data of the response frame, `None` for an abort from the client
+    pub(super) data: Option<[u8; 8]>,
+    /// Entry whose value has been written by a completed download
+    pub(super) written: Option<Entry>,
+}
+
+/// SDO server, one transfer at a time
+pub(super) struct SdoServer {
+    transfer: Transfer,
+}
+
+impl SdoServer {
+    pub(super) fn new() -> Self {
+        Self {
+            transfer: Transfer::Idle,
+        }
+    }
+
+    pub(super) fn reset(&mut self) {
+        self.transfer = Transfer::Idle;
+    }
+
+    /// Process a request of the client. The values of a segmented download are written to
+    /// the dictionary as the segments are received, an aborted download leaves the entry
+    /// partially written.
+    pub(super) fn receive(
+        &mut self,
+        dictionary: &mut ObjectDictionary,
+        data: &[u8],
+        now: u32,
+    ) -> Response {
+        let Ok(request) = <[u8; 8]>::try_from(data) else {
+            // requests are always 8 bytes
+            return Response {
+                data: None,
+                written: None,
+            };
+        };
+        let [command, ..] = request;
+
+        let result = match command >> 5 {
+            INITIATE_DOWNLOAD => self.initiate_download(dictionary, request, now),
+            DOWNLOAD_SEGMENT => self.download_segment(dictionary, request, now),
+            INITIATE_UPLOAD => self.initiate_upload(dictionary, request, now),
+            UPLOAD_SEGMENT => self.upload_segment(dictionary, request, now),
+            ABORT => {
+                self.transfer = Transfer::Idle;
+                return Response {
+                    data: None,
+                    written: None,
+                };
+            }
+            _ => Err(AbortCode::InvalidCommand),
+        };
+
+        match result {
+            Ok((data, written)) => Response {
+                data: Some(data),
+                written,
+            },
+            Err(code) => {
+                let (index, subindex) = self.multiplexer(request);
+                self.transfer = Transfer::Idle;
+                Response {
+                    data: Some(abort(index, subindex, code)),
+                    written: None,
+                }
+            }
+        }
+    }
+
+    /// Abort a segmented transfer without request for too long
+    pub(super) fn poll(&mut self, now: u32, timeout: u32) -> Option<[u8; 8]> {
+        let (Transfer::Download { entry, since, .. } | Transfer::Upload { entry, since, .. }) =
+            self.transfer
+        else {
+            return None;
+        };
+
+        if now.wrapping_sub(since) < timeout {
+            return None;
+        }
+        self.transfer = Transfer::Idle;
+        Some(abort(entry.index, entry.subindex, AbortCode::Timeout))
+    }
+
+    fn initiate_download(
+        &mut self,
+        dictionary: &mut ObjectDictionary,
+        request: [u8; 8],
+        now: u32,
+    ) -> Result<([u8; 8], Option<Entry>), AbortCode> {
+        self.transfer = Transfer::Idle;
+        let [command, index_low, index_high, subindex, size @ ..] = request;
+        let index = u16::from_le_bytes([index_low, index_high]);
+        let entry = dictionary.find(index, subindex)?;
+        if !entry.is_writable() {
+            return Err(AbortCode::ReadOnly);
+        }
+        let response = [0x60, index_low, index_high, subindex, 0, 0, 0, 0];
+
+        if command & EXPEDITED != 0 {
+            let length = if command & SIZE_INDICATED == 0 {
+                entry.length.min(EXPEDITED_LENGTH)
+            } else {
+                EXPEDITED_LENGTH - usize::from((command >> 2) & 0x3)
+            };
+            check_length(length, &entry)?;
+            let data = size.get(..length).ok_or(AbortCode::LengthTooHigh)?;
+            dictionary.value_mut(&entry).copy_from_slice(data);
+            return Ok((response, Some(entry)));
+        }
+
+        if command & SIZE_INDICATED != 0 {
+            let length = usize::try_from(u32::from_le_bytes(size)).unwrap_or(usize::MAX);
+            check_length(length, &entry)?;
+        }
+        self.transfer = Transfer::Download {
+            entry,
+            offset: 0,
+            toggle: false,
+            since: now,
+        };
+        Ok((response, None))
+    }
+
+    fn download_segment(
+        &mut self,
+        dictionary: &mut ObjectDictionary,
+        request: [u8; 8],
+        now: u32,
+    ) -> Result<([u8; 8], Option<Entry>), AbortCode> {
+        let Transfer::Download {
+            entry,
+            offset,
+            toggle,
+            ..
+        } = self.transfer
+        else {
+            return Err(AbortCode::InvalidCommand);
+        };
+        let [command, segment @ ..] = request;
+        if (command & TOGGLE != 0) != toggle {
+            return Err(AbortCode::ToggleBitNotAlternated);
+        }
+
+        let length = SEGMENT_LENGTH - usize::from((command >> 1) & 0x7);
+        let end = offset + length;
+        let data = segment.get(..length).ok_or(AbortCode::InvalidCommand)?;
+        dictionary
+            .value_mut(&entry)
+            .get_mut(offset..end)
+            .ok_or(AbortCode::LengthTooHigh)?
+            .copy_from_slice(data);
+
+        let response = [0x20 | (command & TOGGLE), 0, 0, 0, 0, 0, 0, 0];
+        if command & LAST_SEGMENT == 0 {
+            self.transfer = Transfer::Download {
+                entry,
+                offset: end,
+                toggle: !toggle,
+                since: now,
+            };
+            return Ok((response, None));
+        }
+
+        check_length(end, &entry)?;
+        self.transfer = Transfer::Idle;
+        Ok((response, Some(entry)))
+    }
+
+    fn initiate_upload(
+        &mut self,
+        dictionary: &ObjectDictionary,
+        request: [u8; 8],
+        now: u32,
+    ) -> Result<([u8; 8], Option<Entry>), AbortCode> {
+        self.transfer = Transfer::Idle;
+        let [_, index_low, index_high, subindex, ..] = request;
+        let entry = dictionary.find(u16::from_le_bytes([index_low, index_high]), subindex)?;
+        if !entry.is_readable() {
+            return Err(AbortCode::WriteOnly);
+        }
+        let value = dictionary.value(&entry);
+
+        let mut response = [0, index_low, index_high, subindex, 0, 0, 0, 0];
+        if value.len() <= EXPEDITED_LENGTH {
+            let unused = u8::try_from(EXPEDITED_LENGTH - value.len()).unwrap_or_default();
+            let [command, _, _, _, data @ ..] = &mut response;
+            *command = 0x40 | (unused << 2) | EXPEDITED | SIZE_INDICATED;
+            if let Some(data) = data.get_mut(..value.len()) {
+                data.copy_from_slice(value);
+            }
+            return Ok((response, None));
+        }
+
+        let size = u32::try_from(value.len()).map_err(|_| AbortCode::General)?;
+        let [command, _, _, _, size_bytes @ ..] = &mut response;
+        *command = 0x40 | SIZE_INDICATED;
+        *size_bytes = size.to_le_bytes();
+        self.transfer = Transfer::Upload {
+            entry,
+            offset: 0,
+            toggle: false,
+            since: now,
+        };
+        Ok((response, None))
+    }
+
+    fn upload_segment(
+        &mut self,
+        dictionary: &ObjectDictionary,
+        request: [u8; 8],
+        now: u32,
+    ) -> Result<([u8; 8], Option<Entry>), AbortCode> {
+        let Transfer::Upload {
+            entry,
+            offset,
+            toggle,
+            ..
+        } = self.transfer
+        else {
+            return Err(AbortCode::InvalidCommand);
+        };
+        let [command, ..] = request;
+        if (command & TOGGLE != 0) != toggle {
+            return Err(AbortCode::ToggleBitNotAlternated);
+        }
+
+        let remaining = dictionary.value(&entry).get(offset..).unwrap_or_default();
+        let length = remaining.len().min(SEGMENT_LENGTH);
+        let last = remaining.len() <= SEGMENT_LENGTH;
+        let unused = u8::try_from(SEGMENT_LENGTH - length).map_err(|_| AbortCode::General)?;
+
+        let mut response = [0; 8];
+        let [response_command, segment @ ..] = &mut response;
+        *response_command = (command & TOGGLE) | (unused << 1) | u8::from(last);
+        if let (Some(segment), Some(data)) = (segment.get_mut(..length), remaining.get(..length)) {
+            segment.copy_from_slice(data);
+        }
+
+        self.transfer = if last {
+            Transfer::Idle
+        } else {
+            Transfer::Upload {
+                entry,
+                offset: offset + length,
+                toggle: !toggle,
+                since: now,
+            }
+        };
+        Ok((response, None))
+    }
+
+    /// Index and subindex of the aborted transfer
+    fn multiplexer(&self, request: [u8; 8]) -> (u16, u8) {
+        match self.transfer {
+            Transfer::Download { entry, .. } | Transfer::Upload { entry, .. } => {
+                (entry.index, entry.subindex)
+            }
+            Transfer::Idle => {
+                let [_, index_low, index_high, subindex, ..] = request;
+                (u16::from_le_bytes([index_low, index_high]), subindex)
+            }
+        }
+    }
+}
+
+fn check_length(length: usize, entry: &Entry) -> Result<(), AbortCode> {
+    match length.cmp(&entry.length) {
+        core::cmp::Ordering::Less => Err(AbortCode::LengthTooLow),
+        core::cmp::Ordering::Equal => Ok(()),
+        core::cmp::Ordering::Greater => Err(AbortCode::LengthTooHigh),
+    }
+}
+
+/// Abort transfer frame data
+fn abort(index: u16, subindex: u8, code: AbortCode) -> [u8; 8] {
+    let [index_low, index_high] = index.to_le_bytes();
+    let [code0, code1, code2, code3] = u32::from(code).to_le_bytes();
+    [
+        ABORT << 5,
+        index_low,
+        index_high,
+        subindex,
+        code0,
+        code1,
+        code2,
+        code3,
+    ]
+}
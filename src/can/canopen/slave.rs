@@ -0,0 +1,766 @@
+//! CANopen slave tying the NMT state machine, the SDO server and the PDOs.
+
+use super::dictionary::{Entry, ObjectDictionary};
+use super::nmt::{HeartbeatConsumer, NmtCommand, NmtState, MAX_HEARTBEAT_CONSUMERS};
+use super::pdo::{PdoCobId, PdoConfig, Rpdo, Tpdo, Transmission, MAX_PDOS};
+use super::sdo::SdoServer;
+use super::{
+    frame_cob_id, new_frame, NodeId, HEARTBEAT_FUNCTION_CODE, NMT_COB_ID, RPDO_FUNCTION_CODES,
+    SDO_RX_FUNCTION_CODE, SDO_TX_FUNCTION_CODE, SYNC_COB_ID, TPDO_FUNCTION_CODES,
+};
+use crate::can::frame::OwnedFrame;
+use crate::can::msg::{
+    Filter, FilterElementConfiguration, FilterType, MessageIdLength, RxBufferId,
+};
+
+/// Maximum number of filter elements returned by [`Slave::filters`]: the NMT, SYNC, SDO,
+/// RPDO and heartbeat COB-IDs, two per element
+pub const MAX_FILTERS: usize = (3 + MAX_PDOS + MAX_HEARTBEAT_CONSUMERS).div_ceil(2);
+
+/// Default heartbeat producer time, in milliseconds
+const HEARTBEAT_TIME: u16 = 1000;
+/// Default time without request after which a segmented SDO transfer is aborted
+const SDO_TIMEOUT: u32 = 1_000_000;
+
+/// Configuration of a [`Slave`]
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub node_id: NodeId,
+    /// Heartbeat producer time in milliseconds, 0 to disable the heartbeat
+    pub heartbeat_time: u16,
+    /// Nodes whose heartbeat is monitored, up to [`MAX_HEARTBEAT_CONSUMERS`]
+    pub heartbeat_consumers: &'static [HeartbeatConsumer],
+    /// Transmit PDOs, up to [`MAX_PDOS`]
+    pub tpdos: &'static [PdoConfig],
+    /// Receive PDOs, up to [`MAX_PDOS`]
+    pub rpdos: &'static [PdoConfig],
+    /// Time without request after which a segmented SDO transfer is aborted, in
+    /// microseconds
+    pub sdo_timeout: u32,
+}
+
+impl Config {
+    /// Heartbeat every second, no heartbeat consumer and no PDO
+    #[must_use]
+    pub fn new(node_id: NodeId) -> Self {
+        Self {
+            node_id,
+            heartbeat_time: HEARTBEAT_TIME,
+            heartbeat_consumers: &[],
+            tpdos: &[],
+            rpdos: &[],
+            sdo_timeout: SDO_TIMEOUT,
+        }
+    }
+}
+
+/// Event reported to the application
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// The NMT state of the node changed on a command of the master
+    StateChanged(NmtState),
+    /// Reset node command. The communication has been restarted, the application is to
+    /// reset the values of the dictionary.
+    ResetNode,
+    /// Reset communication command. The communication has been restarted.
+    ResetCommunication,
+    /// SYNC received in the operational state. The synchronous RPDOs received since the
+    /// previous SYNC have been written to the dictionary.
+    Sync,
+    /// Event-driven RPDO, numbered from 0, written to the dictionary
+    RpdoReceived(usize),
+    /// Value written by an SDO download
+    Written { index: u16, subindex: u8 },
+    /// First heartbeat of a monitored node, or change of its state
+    RemoteState { node_id: NodeId, state: NmtState },
+    /// No heartbeat received from a monitored node within its consumer time. Monitoring
+    /// restarts with its next heartbeat.
+    HeartbeatLost(NodeId),
+}
+
+/// Acceptance filter elements of a [`Slave`]
+#[derive(Clone, Copy)]
+pub struct Filters {
+    filters: [Filter; MAX_FILTERS],
+    count: usize,
+}
+
+impl Filters {
+    #[must_use]
+    pub fn as_slice(&self) -> &[Filter] {
+        self.filters.get(..self.count).unwrap_or_default()
+    }
+}
+
+/// CANopen slave
+pub struct Slave<'a> {
+    config: Config,
+    dictionary: ObjectDictionary<'a>,
+    state: NmtState,
+    /// The boot-up message waits to be transmitted
+    boot_up: bool,
+    heartbeat_since: u32,
+    /// Time and state of the last heartbeat of the monitored nodes
+    heartbeats: [Option<(u32, NmtState)>; MAX_HEARTBEAT_CONSUMERS],
+    sdo: SdoServer,
+    sdo_response: Option<[u8; 8]>,
+    tpdos: [Tpdo; MAX_PDOS],
+    rpdos: [Rpdo; MAX_PDOS],
+}
+
+impl<'a> Slave<'a> {
+    /// Create a slave in the initialising state. `None` if there are too many PDOs or
+    /// heartbeat consumers, or a PDO is invalid: a mapped object does not exist, cannot be
+    /// mapped, cannot be read by a TPDO or written by an RPDO, the mapped objects exceed 8
+    /// bytes, or the COB-ID or the number of SYNC is out of range.
+    #[must_use]
+    pub fn new(config: Config, dictionary: ObjectDictionary<'a>) -> Option<Self> {
+        if config.tpdos.len() > MAX_PDOS
+            || config.rpdos.len() > MAX_PDOS
+            || config.heartbeat_consumers.len() > MAX_HEARTBEAT_CONSUMERS
+        {
+            return None;
+        }
+
+        let tpdos_valid = config
+            .tpdos
+            .iter()
+            .all(|pdo| pdo.is_valid(&dictionary, Entry::is_readable));
+        let rpdos_valid = config
+            .rpdos
+            .iter()
+            .all(|pdo| pdo.is_valid(&dictionary, Entry::is_writable));
+        if !(tpdos_valid && rpdos_valid) {
+            return None;
+        }
+
+        Some(Self {
+            config,
+            dictionary,
+            state: NmtState::Initialising,
+            boot_up: false,
+            heartbeat_since: 0,
+            heartbeats: [None; MAX_HEARTBEAT_CONSUMERS],
+            sdo: SdoServer::new(),
+            sdo_response: None,
+            tpdos: [Tpdo::default(); MAX_PDOS],
+            rpdos: [Rpdo::default(); MAX_PDOS],
+        })
+    }
+
+    #[must_use]
+    pub fn node_id(&self) -> NodeId {
+        self.config.node_id
+    }
+
+    #[must_use]
+    pub fn state(&self) -> NmtState {
+        self.state
+    }
+
+    #[must_use]
+    pub fn dictionary(&self) -> &ObjectDictionary<'a> {
+        &self.dictionary
+    }
+
+    #[must_use]
+    pub fn dictionary_mut(&mut self) -> &mut ObjectDictionary<'a> {
+        &mut self.dictionary
+    }
+
+    /// Start the communication: the boot-up message is sent and the pre-operational state
+    /// entered
+    pub fn start(&mut self, now: u32) {
+        self.state = NmtState::PreOperational;
+        self.boot_up = true;
+        self.heartbeat_since = now;
+        self.heartbeats = [None; MAX_HEARTBEAT_CONSUMERS];
+        self.sdo.reset();
+        self.sdo_response = None;
+        self.tpdos = [Tpdo::default(); MAX_PDOS];
+        self.rpdos = [Rpdo::default(); MAX_PDOS];
+    }
+
+    /// Event of the application for a TPDO, numbered from 0. An event-driven TPDO is
+    /// transmitted once its inhibit time elapsed, an acyclic synchronous TPDO with the next
+    /// SYNC. `false` if there is no such TPDO.
+    pub fn trigger_tpdo(&mut self, number: usize) -> bool {
+        let (Some(tpdo), Some(config)) =
+            (self.tpdos.get_mut(number), self.config.tpdos.get(number))
+        else {
+            return false;
+        };
+        tpdo.trigger(config);
+        true
+    }
+
+    /// Process a received frame. Frames are ignored until the node is started.
+    pub fn receive(&mut self, frame: &impl embedded_can::Frame, now: u32) -> Option<Event> {
+        if self.state == NmtState::Initialising {
+            return None;
+        }
+
+        let cob_id = frame_cob_id(frame)?;
+        let data = frame.data();
+        if cob_id == NMT_COB_ID {
+            let command = NmtCommand::parse(data, self.config.node_id)?;
+            return self.command(command, now);
+        }
+        if self.state == NmtState::Stopped {
+            // only NMT and heartbeats in the stopped state
+            return self.consume_heartbeat(cob_id, data, now);
+        }
+        if cob_id == SYNC_COB_ID {
+            return self.sync();
+        }
+        if cob_id == self.config.node_id.cob_id(SDO_RX_FUNCTION_CODE) {
+            let response = self.sdo.receive(&mut self.dictionary, data, now);
+            self.sdo_response = response.data;
+            return response.written.map(|entry| Event::Written {
+                index: entry.index,
+                subindex: entry.subindex,
+            });
+        }
+
+        self.consume_heartbeat(cob_id, data, now)
+            .or_else(|| self.receive_pdo(cob_id, data))
+    }
+
+    /// Check the heartbeats of the monitored nodes and the SDO transfer, to be called
+    /// periodically
+    pub fn poll(&mut self, now: u32) -> Option<Event> {
+        if let Some(abort) = self.sdo.poll(now, self.config.sdo_timeout) {
+            self.sdo_response = Some(abort);
+        }
+
+        for (heartbeat, consumer) in self
+            .heartbeats
+            .iter_mut()
+            .zip(self.config.heartbeat_consumers)
+        {
+            let timeout = u32::from(consumer.time) * 1000;
+            let lost = timeout != 0
+                && heartbeat.is_some_and(|(since, _)| now.wrapping_sub(since) >= timeout);
+            if lost {
+                *heartbeat = None;
+                return Some(Event::HeartbeatLost(consumer.node_id));
+            }
+        }
+        None
+    }
+
+    /// The next frame to transmit: the boot-up message, the SDO response, the TPDOs due in
+    /// the operational state and the heartbeat. The TPDOs are sampled from the dictionary
+    /// when returned.
+    pub fn next_frame(&mut self, now: u32) -> Option<OwnedFrame> {
+        if self.state == NmtState::Initialising {
+            return None;
+        }
+        let node_id = self.config.node_id;
+        let heartbeat_cob_id = node_id.cob_id(HEARTBEAT_FUNCTION_CODE);
+
+        if self.boot_up {
+            self.boot_up = false;
+            return new_frame(heartbeat_cob_id, &[u8::from(NmtState::Initialising)]);
+        }
+        if let Some(data) = self.sdo_response.take() {
+            return new_frame(node_id.cob_id(SDO_TX_FUNCTION_CODE), &data);
+        }
+
+        if self.state == NmtState::Operational {
+            let tpdos = self.tpdos.iter_mut().zip(self.config.tpdos).enumerate();
+            for (number, (tpdo, config)) in tpdos {
+                if tpdo.is_due(config, now) {
+                    tpdo.transmitted(now);
+                    let (data, length) = config.map(&self.dictionary);
+                    let cob_id = pdo_cob_id(config, TPDO_FUNCTION_CODES, number, node_id)?;
+                    return new_frame(cob_id, data.get(..length)?);
+                }
+            }
+        }
+
+        let period = u32::from(self.config.heartbeat_time) * 1000;
+        if period != 0 && now.wrapping_sub(self.heartbeat_since) >= period {
+            self.heartbeat_since = now;
+            return new_frame(heartbeat_cob_id, &[u8::from(self.state)]);
+        }
+        None
+    }
+
+    /// Acceptance filters of the COB-IDs consumed by the node: NMT, SYNC, SDO requests,
+    /// RPDOs and heartbeats of the monitored nodes, as dual ID filters numbered from
+    /// `first_number` in the standard filter list. `element_configuration` is to store into
+    /// an Rx FIFO, and the non-matching standard frames are to be rejected with the
+    /// [`GlobalFilterConfig`](crate::can::msg::GlobalFilterConfig).
+    #[must_use]
+    pub fn filters(
+        &self,
+        first_number: u8,
+        element_configuration: FilterElementConfiguration,
+    ) -> Filters {
+        let node_id = self.config.node_id;
+        let rpdos = self
+            .config
+            .rpdos
+            .iter()
+            .enumerate()
+            .filter_map(|(number, config)| {
+                pdo_cob_id(config, RPDO_FUNCTION_CODES, number, node_id)
+            });
+        let heartbeats = self
+            .config
+            .heartbeat_consumers
+            .iter()
+            .map(|consumer| consumer.node_id.cob_id(HEARTBEAT_FUNCTION_CODE));
+        let mut cob_ids = [
+            NMT_COB_ID,
+            SYNC_COB_ID,
+            node_id.cob_id(SDO_RX_FUNCTION_CODE),
+        ]
+        .into_iter()
+        .chain(rpdos)
+        .chain(heartbeats);
+
+        let mut filters = [Filter {
+            number: first_number,
+            element_configuration,
+            typ: FilterType::Dualid,
+            id_length: MessageIdLength::Standard,
+            id1: 0,
+            id2: 0,
+            // unused by dual ID filters
+            rx_buffer_offset: RxBufferId::default(),
+        }; MAX_FILTERS];
+
+        let mut count = 0;
+        for (filter, number) in filters.iter_mut().zip(first_number..=u8::MAX) {
+            let Some(id1) = cob_ids.next() else {
+                break;
+            };
+            let id2 = cob_ids.next().unwrap_or(id1);
+            filter.number = number;
+            filter.id1 = u32::from(id1);
+            filter.id2 = u32::from(id2);
+            count += 1;
+        }
+        Filters { filters, count }
+    }
+
+    fn command(&mut self, command: NmtCommand, now: u32) -> Option<Event> {
+        let Some(state) = command.target_state() else {
+            self.start(now);
+            return Some(if command == NmtCommand::ResetNode {
+                Event::ResetNode
+            } else {
+                Event::ResetCommunication
+            });
+        };
+
+        if state == self.state {
+            return None;
+        }
+        if state == NmtState::Operational {
+            self.tpdos = [Tpdo {
+                since: now,
+                ..Tpdo::default()
+            }; MAX_PDOS];
+            self.rpdos = [Rpdo::default(); MAX_PDOS];
+        }
+        if state == NmtState::Stopped {
+            self.sdo.reset();
+            self.sdo_response = None;
+        }
+        self.state = state;
+        Some(Event::StateChanged(state))
+    }
+
+    fn sync(&mut self) -> Option<Event> {
+        if self.state != NmtState::Operational {
+            return None;
+        }
+
+        for (tpdo, config) in self.tpdos.iter_mut().zip(self.config.tpdos) {
+            tpdo.sync(config);
+        }
+        for (rpdo, config) in self.rpdos.iter_mut().zip(self.config.rpdos) {
+            if rpdo.pending {
+                rpdo.pending = false;
+                let data = rpdo.data.get(..rpdo.length).unwrap_or_default();
+                config.unmap(&mut self.dictionary, data);
+            }
+        }
+        Some(Event::Sync)
+    }
+
+    fn consume_heartbeat(&mut self, cob_id: u16, data: &[u8], now: u32) -> Option<Event> {
+        let node_id = cob_id
+            .checked_sub(HEARTBEAT_FUNCTION_CODE)
+            .and_then(|id| u8::try_from(id).ok())
+            .and_then(NodeId::new)?;
+        let state = NmtState::from_heartbeat(data)?;
+        let position = self
+            .config
+            .heartbeat_consumers
+            .iter()
+            .position(|consumer| consumer.node_id == node_id)?;
+
+        let previous = self.heartbeats.get_mut(position)?.replace((now, state));
+        (previous.map(|(_, previous)| previous) != Some(state))
+            .then_some(Event::RemoteState { node_id, state })
+    }
+
+    fn receive_pdo(&mut self, cob_id: u16, data: &[u8]) -> Option<Event> {
+        if self.state != NmtState::Operational {
+            return None;
+        }
+
+        let node_id = self.config.node_id;
+        let number = self
+            .config
+            .rpdos
+            .iter()
+            .enumerate()
+            .position(|(number, config)| {
+                pdo_cob_id(config, RPDO_FUNCTION_CODES, number, node_id) == Some(cob_id)
+            })?;
+        let config = self.config.rpdos.get(number)?;
+
+        if config.transmission == Transmission::Event {
+            return config
+                .unmap(&mut self.dictionary, data)
+                .then_some(Event::RpdoReceived(number));
+        }
+        // written with the next SYNC
+        let mut rpdo = Rpdo {
+            length: data.len(),
+            pending: true,
+            ..Rpdo::default()
+        };
+        rpdo.data.get_mut(..data.len())?.copy_from_slice(data);
+        *self.rpdos.get_mut(number)? = rpdo;
+        None
+    }
+}
+
+/// COB-ID of the PDO numbered from 0
+fn pdo_cob_id(
+    config: &PdoConfig,
+    function_codes: [u16; MAX_PDOS],
+    number: usize,
+    node_id: NodeId,
+) -> Option<u16> {
+    match config.cob_id {
+        PdoCobId::Default => function_codes
+            .get(number)
+            .map(|&function_code| node_id.cob_id(function_code)),
+        PdoCobId::Fixed(cob_id) => Some(cob_id),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{Access, MappedObject};
+    use super::*;
+    use embedded_can::Frame as _;
+
+    const ENTRIES: &[Entry] = &[
+        Entry::new(0x1000, 0, Access::ReadOnly, 0, 4),
+        Entry::new(0x2000, 0, Access::ReadWrite, 4, 10),
+        Entry::new(0x6000, 1, Access::ReadOnly, 14, 2).mappable(),
+        Entry::new(0x6200, 1, Access::ReadWrite, 16, 1).mappable(),
+        Entry::new(0x6200, 2, Access::ReadWrite, 17, 1).mappable(),
+    ];
+    const INPUTS: &[MappedObject] = &[MappedObject {
+        index: 0x6000,
+        subindex: 1,
+    }];
+    const OUTPUT1: &[MappedObject] = &[MappedObject {
+        index: 0x6200,
+        subindex: 1,
+    }];
+    const OUTPUT2: &[MappedObject] = &[MappedObject {
+        index: 0x6200,
+        subindex: 2,
+    }];
+    const TPDOS: &[PdoConfig] = &[
+        PdoConfig::new(Transmission::Event, INPUTS),
+        PdoConfig::new(Transmission::SyncCyclic(2), INPUTS),
+    ];
+    const RPDOS: &[PdoConfig] = &[
+        PdoConfig::new(Transmission::Event, OUTPUT1),
+        PdoConfig {
+            cob_id: PdoCobId::Fixed(0x1F0),
+            ..PdoConfig::new(Transmission::SyncAcyclic, OUTPUT2)
+        },
+    ];
+    const HEARTBEAT_CONSUMERS: &[HeartbeatConsumer] = &[HeartbeatConsumer {
+        node_id: REMOTE,
+        time: 150,
+    }];
+
+    const NODE_ID: NodeId = NodeId(5);
+    const REMOTE: NodeId = NodeId(0x10);
+
+    fn slave(storage: &mut [u8]) -> Slave<'_> {
+        let config = Config {
+            tpdos: TPDOS,
+            rpdos: RPDOS,
+            heartbeat_consumers: HEARTBEAT_CONSUMERS,
+            ..Config::new(NODE_ID)
+        };
+        let dictionary = ObjectDictionary::new(ENTRIES, storage).unwrap();
+        let mut slave = Slave::new(config, dictionary).unwrap();
+        slave.start(0);
+        slave.next_frame(0);
+        slave
+    }
+
+    fn frame(cob_id: u16, data: &[u8]) -> OwnedFrame {
+        new_frame(cob_id, data).unwrap()
+    }
+
+    fn nmt(command: u8) -> OwnedFrame {
+        frame(NMT_COB_ID, &[command, NODE_ID.raw()])
+    }
+
+    /// SDO request and response
+    fn sdo(slave: &mut Slave, request: [u8; 8], now: u32) -> (Option<Event>, [u8; 8]) {
+        let event = slave.receive(&frame(0x605, &request), now);
+        let response = slave.next_frame(now).unwrap();
+        assert_eq!(frame_cob_id(&response), Some(0x585));
+        (event, response.data().try_into().unwrap())
+    }
+
+    #[test]
+    fn test_nmt() {
+        let mut storage = [0; 18];
+        let dictionary = ObjectDictionary::new(ENTRIES, &mut storage).unwrap();
+        let mut slave = Slave::new(Config::new(NODE_ID), dictionary).unwrap();
+        assert_eq!(slave.receive(&nmt(0x01), 0), None);
+        assert_eq!(slave.next_frame(0), None);
+
+        slave.start(0);
+        assert_eq!(slave.next_frame(0), Some(frame(0x705, &[0x00])));
+        assert_eq!(slave.state(), NmtState::PreOperational);
+        assert_eq!(slave.next_frame(999_999), None);
+        assert_eq!(slave.next_frame(1_000_000), Some(frame(0x705, &[0x7F])));
+
+        assert_eq!(
+            slave.receive(&nmt(0x01), 0),
+            Some(Event::StateChanged(NmtState::Operational))
+        );
+        assert_eq!(slave.receive(&nmt(0x01), 0), None);
+        // sent to another node, to all the nodes
+        assert_eq!(slave.receive(&frame(NMT_COB_ID, &[0x02, 6]), 0), None);
+        assert_eq!(
+            slave.receive(&frame(NMT_COB_ID, &[0x02, 0]), 0),
+            Some(Event::StateChanged(NmtState::Stopped))
+        );
+        assert_eq!(slave.next_frame(2_000_000), Some(frame(0x705, &[0x04])));
+
+        // no SDO in the stopped state
+        let request = frame(0x605, &[0x40, 0x00, 0x10, 0, 0, 0, 0, 0]);
+        assert_eq!(slave.receive(&request, 0), None);
+        assert_eq!(slave.next_frame(2_000_000), None);
+
+        assert_eq!(
+            slave.receive(&nmt(0x82), 0),
+            Some(Event::ResetCommunication)
+        );
+        assert_eq!(slave.state(), NmtState::PreOperational);
+        assert_eq!(slave.next_frame(0), Some(frame(0x705, &[0x00])));
+    }
+
+    #[test]
+    fn test_sdo_expedited() {
+        let mut storage = [0; 18];
+        let mut slave = slave(&mut storage);
+        slave
+            .dictionary_mut()
+            .get_mut(0x1000, 0)
+            .unwrap()
+            .copy_from_slice(&0x0001_0191u32.to_le_bytes());
+
+        // upload of 4 bytes
+        let (event, response) = sdo(&mut slave, [0x40, 0x00, 0x10, 0, 0, 0, 0, 0], 0);
+        assert_eq!(event, None);
+        assert_eq!(response, [0x43, 0x00, 0x10, 0, 0x91, 0x01, 0x01, 0x00]);
+
+        // download of 1 byte
+        let (event, response) = sdo(&mut slave, [0x2F, 0x00, 0x62, 1, 0xAA, 0, 0, 0], 0);
+        assert_eq!(
+            event,
+            Some(Event::Written {
+                index: 0x6200,
+                subindex: 1
+            })
+        );
+        assert_eq!(response, [0x60, 0x00, 0x62, 1, 0, 0, 0, 0]);
+        assert_eq!(slave.dictionary().get(0x6200, 1), Some([0xAA].as_slice()));
+
+        // read only, wrong length, object and subindex not existing
+        let (_, response) = sdo(&mut slave, [0x23, 0x00, 0x10, 0, 1, 2, 3, 4], 0);
+        assert_eq!(response, [0x80, 0x00, 0x10, 0, 0x02, 0x00, 0x01, 0x06]);
+        let (_, response) = sdo(&mut slave, [0x2B, 0x00, 0x62, 1, 1, 2, 0, 0], 0);
+        assert_eq!(response, [0x80, 0x00, 0x62, 1, 0x12, 0x00, 0x07, 0x06]);
+        let (_, response) = sdo(&mut slave, [0x40, 0x00, 0x30, 0, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x80, 0x00, 0x30, 0, 0x00, 0x00, 0x02, 0x06]);
+        let (_, response) = sdo(&mut slave, [0x40, 0x00, 0x62, 3, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x80, 0x00, 0x62, 3, 0x11, 0x00, 0x09, 0x06]);
+    }
+
+    #[test]
+    fn test_sdo_segmented() {
+        let mut storage = [0; 18];
+        let mut slave = slave(&mut storage);
+
+        // download of 10 bytes
+        let (event, response) = sdo(&mut slave, [0x21, 0x00, 0x20, 0, 10, 0, 0, 0], 0);
+        assert_eq!(event, None);
+        assert_eq!(response, [0x60, 0x00, 0x20, 0, 0, 0, 0, 0]);
+        let (event, response) = sdo(
+            &mut slave,
+            [0x00, b'c', b'a', b'n', b'o', b'p', b'e', b'n'],
+            0,
+        );
+        assert_eq!(event, None);
+        assert_eq!(response, [0x20, 0, 0, 0, 0, 0, 0, 0]);
+        // 4 bytes not containing data, last segment
+        let (event, response) = sdo(&mut slave, [0x19, b' ', b'4', b'2', 0, 0, 0, 0], 0);
+        assert_eq!(
+            event,
+            Some(Event::Written {
+                index: 0x2000,
+                subindex: 0
+            })
+        );
+        assert_eq!(response, [0x30, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            slave.dictionary().get(0x2000, 0),
+            Some(b"canopen 42".as_slice())
+        );
+
+        // upload of 10 bytes
+        let (_, response) = sdo(&mut slave, [0x40, 0x00, 0x20, 0, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x41, 0x00, 0x20, 0, 10, 0, 0, 0]);
+        let (_, response) = sdo(&mut slave, [0x60, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x00, b'c', b'a', b'n', b'o', b'p', b'e', b'n']);
+        let (_, response) = sdo(&mut slave, [0x70, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x19, b' ', b'4', b'2', 0, 0, 0, 0]);
+
+        // toggle bit not alternated
+        sdo(&mut slave, [0x40, 0x00, 0x20, 0, 0, 0, 0, 0], 0);
+        let (_, response) = sdo(&mut slave, [0x70, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x80, 0x00, 0x20, 0, 0x00, 0x00, 0x03, 0x05]);
+        let (_, response) = sdo(&mut slave, [0x60, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert_eq!(response, [0x80, 0x00, 0x00, 0, 0x01, 0x00, 0x04, 0x05]);
+
+        // timeout
+        sdo(&mut slave, [0x40, 0x00, 0x20, 0, 0, 0, 0, 0], 0);
+        assert_eq!(slave.poll(SDO_TIMEOUT - 1), None);
+        assert_eq!(slave.poll(SDO_TIMEOUT), None);
+        assert_eq!(
+            slave.next_frame(SDO_TIMEOUT),
+            Some(frame(0x585, &[0x80, 0x00, 0x20, 0, 0x00, 0x00, 0x04, 0x05]))
+        );
+    }
+
+    #[test]
+    fn test_pdo() {
+        let mut storage = [0; 18];
+        let mut slave = slave(&mut storage);
+        slave
+            .dictionary_mut()
+            .get_mut(0x6000, 1)
+            .unwrap()
+            .copy_from_slice(&[0x34, 0x12]);
+
+        // no PDO in the pre-operational state
+        assert!(slave.trigger_tpdo(0));
+        assert!(!slave.trigger_tpdo(2));
+        assert_eq!(slave.receive(&frame(0x205, &[0x01]), 0), None);
+        assert_eq!(slave.next_frame(0), None);
+        slave.receive(&nmt(0x01), 0);
+
+        // event-driven TPDO
+        assert!(slave.trigger_tpdo(0));
+        assert_eq!(slave.next_frame(0), Some(frame(0x185, &[0x34, 0x12])));
+        assert_eq!(slave.next_frame(0), None);
+
+        // TPDO every 2 SYNC
+        assert_eq!(
+            slave.receive(&frame(SYNC_COB_ID, &[]), 0),
+            Some(Event::Sync)
+        );
+        assert_eq!(slave.next_frame(0), None);
+        slave.receive(&frame(SYNC_COB_ID, &[]), 0);
+        assert_eq!(slave.next_frame(0), Some(frame(0x285, &[0x34, 0x12])));
+
+        // event-driven RPDO
+        assert_eq!(
+            slave.receive(&frame(0x205, &[0x01]), 0),
+            Some(Event::RpdoReceived(0))
+        );
+        assert_eq!(slave.dictionary().get(0x6200, 1), Some([0x01].as_slice()));
+        assert_eq!(slave.receive(&frame(0x205, &[]), 0), None);
+
+        // synchronous RPDO, written with the next SYNC
+        assert_eq!(slave.receive(&frame(0x1F0, &[0x02]), 0), None);
+        assert_eq!(slave.dictionary().get(0x6200, 2), Some([0x00].as_slice()));
+        slave.receive(&frame(SYNC_COB_ID, &[]), 0);
+        assert_eq!(slave.dictionary().get(0x6200, 2), Some([0x02].as_slice()));
+    }
+
+    #[test]
+    fn test_heartbeat_consumer() {
+        let mut storage = [0; 18];
+        let mut slave = slave(&mut storage);
+        let heartbeat = |state: NmtState| frame(0x710, &[u8::from(state)]);
+
+        // monitoring starts with the first heartbeat
+        assert_eq!(slave.poll(1_000_000), None);
+        assert_eq!(
+            slave.receive(&heartbeat(NmtState::PreOperational), 0),
+            Some(Event::RemoteState {
+                node_id: REMOTE,
+                state: NmtState::PreOperational
+            })
+        );
+        assert_eq!(
+            slave.receive(&heartbeat(NmtState::PreOperational), 100_000),
+            None
+        );
+        assert_eq!(slave.poll(249_999), None);
+        assert_eq!(slave.poll(250_000), Some(Event::HeartbeatLost(REMOTE)));
+        assert_eq!(slave.poll(500_000), None);
+
+        // heartbeats are consumed in the stopped state, of the monitored nodes only
+        slave.receive(&nmt(0x02), 0);
+        assert_eq!(
+            slave.receive(&heartbeat(NmtState::Operational), 0),
+            Some(Event::RemoteState {
+                node_id: REMOTE,
+                state: NmtState::Operational
+            })
+        );
+        assert_eq!(slave.receive(&frame(0x711, &[0x05]), 0), None);
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut storage = [0; 18];
+        let slave = slave(&mut storage);
+        let filters = slave.filters(2, FilterElementConfiguration::StoreInRxFifo0);
+        let filters = filters.as_slice();
+
+        let ids: [(u8, u32, u32); 3] = [(2, 0x000, 0x080), (3, 0x605, 0x205), (4, 0x1F0, 0x710)];
+        assert_eq!(filters.len(), ids.len());
+        for (filter, (number, id1, id2)) in filters.iter().zip(ids) {
+            assert_eq!(filter.number, number);
+            assert_eq!(filter.id1, id1);
+            assert_eq!(filter.id2, id2);
+            assert_eq!(filter.id_length, MessageIdLength::Standard);
+            assert!(matches!(filter.typ, FilterType::Dualid));
+        }
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(unused_variables)]
+#![allow(clippy::cast_possible_truncation)]
 
-use crate::can::msg::MessageId;
+use crate::can::msg::{FrameMode, MessageId};
 
 // TODO This should be DataLength(u8) and only from_length and to_length should be public
 /// Data length code
@@ -49,6 +50,22 @@ impl DataLenghtCode {
         }
     }
 
+    /// Create the smallest `DataLenghtCode` holding `length` bytes, the payload being
+    /// padded up to the CAN FD data length
+    pub const fn from_length_padded(length: usize) -> Option<Self> {
+        match length {
+            0..=8 => Self::from_length(length),
+            9..=12 => Some(Self::_12),
+            13..=16 => Some(Self::_16),
+            17..=20 => Some(Self::_20),
+            21..=24 => Some(Self::_24),
+            25..=32 => Some(Self::_32),
+            33..=48 => Some(Self::_48),
+            49..=64 => Some(Self::_64),
+            _ => None,
+        }
+    }
+
     /// Convert the `DataLenghtCode` to a data length
     pub const fn to_length(self) -> usize {
         match self {
@@ -110,22 +127,211 @@ pub struct Frame<'a> {
     pub id: MessageId,
     /// The data
     pub data: &'a [u8],
+    /// When set, an event with this message marker is stored in the Tx Event FIFO
+    /// once the frame has been transmitted
+    pub event_marker: Option<u8>,
+    /// Transmit a remote frame, requesting `data.len()` bytes. The data itself is not sent
+    pub remote: bool,
+    /// Format of the frame, `None` uses the frame mode of the node. Remote frames are
+    /// always sent as classic CAN frames
+    pub frame_mode: Option<FrameMode>,
+    /// Transmit the error state indicator recessive, only used for CAN FD frames
+    pub error_state_indicator: bool,
 }
 
+/// Payload of remote frames, only its length is used as data length code
+const REMOTE_PAYLOAD: [u8; 8] = [0; 8];
+
 impl<'a> Frame<'a> {
     #[must_use]
     pub fn new(id: MessageId, data: &'a [u8]) -> Option<Self> {
         if data.len() > 64 {
             None
         } else {
-            Some(Self { id, data })
+            Some(Self {
+                id,
+                data,
+                event_marker: None,
+                remote: false,
+                frame_mode: None,
+                error_state_indicator: false,
+            })
+        }
+    }
+
+    /// Create a remote frame requesting `dlc` bytes, in range [0, 8]
+    #[must_use]
+    pub fn new_remote(id: MessageId, dlc: usize) -> Option<Frame<'static>> {
+        Some(Frame {
+            id,
+            data: REMOTE_PAYLOAD.get(..dlc)?,
+            event_marker: None,
+            remote: true,
+            frame_mode: None,
+            error_state_indicator: false,
+        })
+    }
+
+    /// Store a Tx event with the given message marker once the frame has been transmitted
+    #[must_use]
+    pub fn with_event_marker(self, marker: u8) -> Self {
+        Self {
+            event_marker: Some(marker),
+            ..self
+        }
+    }
+
+    /// Transmit the frame in the given format instead of the frame mode of the node
+    #[must_use]
+    pub fn with_frame_mode(self, frame_mode: FrameMode) -> Self {
+        Self {
+            frame_mode: Some(frame_mode),
+            ..self
+        }
+    }
+
+    /// Transmit the error state indicator recessive, signaling an error passive node
+    #[must_use]
+    pub fn with_error_state_indicator(self) -> Self {
+        Self {
+            error_state_indicator: true,
+            ..self
+        }
+    }
+}
+
+/// A CAN frame owning its data, implementing [`embedded_can::Frame`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OwnedFrame {
+    id: embedded_can::Id,
+    remote: bool,
+    /// Created with [`OwnedFrame::new_fd`], otherwise the frame is transmitted as a
+    /// classic frame
+    fd: bool,
+    len: u8,
+    data: [u8; 64],
+}
+
+impl OwnedFrame {
+    pub(crate) const EMPTY: Self = Self {
+        id: embedded_can::Id::Standard(embedded_can::StandardId::ZERO),
+        remote: false,
+        fd: false,
+        len: 0,
+        data: [0; 64],
+    };
+
+    /// Borrow this frame as a [`Frame`] to be transmitted. CAN FD frames use the frame
+    /// mode of the node, the other frames are classic frames.
+    #[must_use]
+    pub fn as_frame(&self) -> Frame<'_> {
+        Frame {
+            id: self.id.into(),
+            data: self.payload(),
+            event_marker: None,
+            remote: self.remote,
+            frame_mode: if self.fd {
+                None
+            } else {
+                Some(FrameMode::Standard)
+            },
+            error_state_indicator: false,
+        }
+    }
+
+    /// Create a CAN FD data frame, `None` if the data length cannot be encoded in a data
+    /// length code. [`embedded_can::Frame::new`] only creates classic frames of up to 8
+    /// bytes.
+    #[must_use]
+    pub fn new_fd(id: impl Into<embedded_can::Id>, data: &[u8]) -> Option<Self> {
+        Self::with_data(id.into(), data, true)
+    }
+
+    /// Classic data frame of 8 bytes, as used by the protocol layers
+    pub(crate) fn from_bytes(id: impl Into<embedded_can::Id>, data: [u8; 8]) -> Self {
+        let mut frame = Self {
+            id: id.into(),
+            len: 8,
+            ..Self::EMPTY
+        };
+        if let Some(bytes) = frame.data.get_mut(..8) {
+            bytes.copy_from_slice(&data);
+        }
+        frame
+    }
+
+    fn with_data(id: embedded_can::Id, data: &[u8], fd: bool) -> Option<Self> {
+        DataLenghtCode::from_length(data.len())?;
+        let mut frame = Self {
+            id,
+            remote: false,
+            fd,
+            len: data.len() as u8,
+            data: [0; 64],
+        };
+        frame.data.get_mut(..data.len())?.copy_from_slice(data);
+        Some(frame)
+    }
+
+    /// Payload as written in the Tx element. For remote frames this is a zeroed
+    /// slice of `dlc` bytes, so that the requested data length code is transmitted.
+    pub(crate) fn payload(&self) -> &[u8] {
+        self.data.get(..usize::from(self.len)).unwrap_or_default()
+    }
+}
+
+impl embedded_can::Frame for OwnedFrame {
+    /// Create a classic data frame of up to 8 bytes, see [`OwnedFrame::new_fd`] for CAN FD
+    fn new(id: impl Into<embedded_can::Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        Self::with_data(id.into(), data, false)
+    }
+
+    fn new_remote(id: impl Into<embedded_can::Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+        Some(Self {
+            id: id.into(),
+            remote: true,
+            fd: false,
+            len: dlc as u8,
+            data: [0; 64],
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, embedded_can::Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.remote
+    }
+
+    fn id(&self) -> embedded_can::Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        usize::from(self.len)
+    }
+
+    fn data(&self) -> &[u8] {
+        if self.remote {
+            &[]
+        } else {
+            self.payload()
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::DataLenghtCode;
+    use super::{DataLenghtCode, OwnedFrame};
+    use crate::can::msg::FrameMode;
+    use embedded_can::{Frame as _, StandardId};
 
     #[test]
     fn test_data_length_code() {
@@ -156,4 +362,68 @@ mod test {
             test(DataLenghtCode::try_from(i).unwrap());
         }
     }
+    #[test]
+    fn test_data_length_code_padded() {
+        for length in 0..=64 {
+            let dlc = DataLenghtCode::from_length_padded(length).unwrap();
+            assert!(dlc.to_length() >= length);
+            if let Some(exact) = DataLenghtCode::from_length(length) {
+                assert_eq!(dlc, exact);
+            } else {
+                assert!(DataLenghtCode::try_from(dlc as u8 - 1).unwrap().to_length() < length);
+            }
+        }
+        assert_eq!(
+            DataLenghtCode::from_length_padded(10),
+            Some(DataLenghtCode::_12)
+        );
+        assert_eq!(
+            DataLenghtCode::from_length_padded(33),
+            Some(DataLenghtCode::_48)
+        );
+        assert_eq!(DataLenghtCode::from_length_padded(65), None);
+    }
+
+    #[test]
+    fn test_owned_frame() {
+        let id = StandardId::new(0x123).unwrap();
+
+        let frame = OwnedFrame::new(id, &[1, 2, 3]).unwrap();
+        assert_eq!(frame.dlc(), 3);
+        assert_eq!(frame.data(), &[1, 2, 3]);
+        assert!(!frame.is_remote_frame());
+        assert!(!frame.is_extended());
+
+        let remote = OwnedFrame::new_remote(id, 4).unwrap();
+        assert_eq!(remote.dlc(), 4);
+        assert!(remote.data().is_empty());
+        assert_eq!(remote.payload(), &[0; 4]);
+
+        assert!(OwnedFrame::new(id, &[0; 9]).is_none());
+        assert!(OwnedFrame::new(id, &[0; 12]).is_none());
+        assert!(OwnedFrame::new(id, &[0; 65]).is_none());
+
+        let fd = OwnedFrame::new_fd(id, &[0xAA; 12]).unwrap();
+        assert_eq!(fd.dlc(), 12);
+        assert_eq!(fd.data(), &[0xAA; 12]);
+        assert!(OwnedFrame::new_fd(id, &[0; 9]).is_none());
+        assert!(OwnedFrame::new_fd(id, &[0; 65]).is_none());
+        assert!(OwnedFrame::new_remote(id, 9).is_none());
+
+        let borrowed = remote.as_frame();
+        assert!(borrowed.remote);
+        assert_eq!(borrowed.data.len(), 4);
+
+        // only the CAN FD frames use the frame mode of the node
+        assert_eq!(frame.as_frame().frame_mode, Some(FrameMode::Standard));
+        assert_eq!(fd.as_frame().frame_mode, None);
+        let short_fd = OwnedFrame::new_fd(id, &[1, 2]).unwrap();
+        assert_eq!(short_fd.data(), &[1, 2]);
+        assert_eq!(short_fd.as_frame().frame_mode, None);
+        assert_ne!(short_fd, OwnedFrame::new(id, &[1, 2]).unwrap());
+
+        let bytes = OwnedFrame::from_bytes(id, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(bytes.data(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(bytes.as_frame().frame_mode, Some(FrameMode::Standard));
+    }
 }
@@ -0,0 +1,372 @@
+// TODO Remove this once the code is stable
+#![allow(clippy::cast_possible_truncation)]
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::can::msg::{Filter, FilterElementConfiguration, FilterType, MessageIdLength};
+use crate::can::reg;
+use core::mem::transmute;
+
+// Filter type encoding for the standard filter element (SFT)
+const SFT_RANGE: u8 = 0;
+const SFT_DUAL_ID: u8 = 1;
+const SFT_CLASSIC: u8 = 2;
+const SFT_DISABLED: u8 = 3;
+
+// Filter type encoding for the extended filter element (EFT)
+const EFT_RANGE: u8 = 0;
+const EFT_DUAL_ID: u8 = 1;
+const EFT_CLASSIC: u8 = 2;
+
+/// Element of the standard or extended filter list in the message RAM
+pub(crate) trait FilterElement {
+    fn write(&self, filter: &Filter);
+
+    /// Write a disabled element (SFEC/EFEC = 0), skipped by the acceptance filtering
+    fn disable(&self);
+}
+
+/// Write the filters with `id_length` into the list elements given by `element`, and
+/// every element below the list size without a filter as disabled, so that a gap in the
+/// numbering is not evaluated from stale message RAM. Returns the list size, the highest
+/// filter number + 1.
+pub(crate) fn write_filter_list<E: FilterElement>(
+    filters: &[Filter],
+    id_length: MessageIdLength,
+    element: impl Fn(u8) -> E,
+) -> u8 {
+    let list = || {
+        filters
+            .iter()
+            .filter(move |filter| filter.id_length == id_length)
+    };
+    let size = list().map(|filter| filter.number + 1).max().unwrap_or(0);
+
+    for number in 0..size {
+        if !list().any(|filter| filter.number == number) {
+            element(number).disable();
+        }
+    }
+    for filter in list() {
+        element(filter.number).write(filter);
+    }
+
+    size
+}
+
+pub(crate) struct StandardFilter {
+    inner: reg::msg_filter::StdFilterMsg,
+}
+
+impl StandardFilter {
+    pub(crate) fn new(ptr: *mut u8) -> Self {
+        Self {
+            inner: unsafe { transmute(ptr) },
+        }
+    }
+}
+
+impl FilterElement for StandardFilter {
+    fn write(&self, filter: &Filter) {
+        let sfec = u8::from(filter.element_configuration);
+
+        let (sft, sfid1, sfid2) = match (filter.element_configuration, filter.typ) {
+            (FilterElementConfiguration::Disable, _) | (_, FilterType::None) => {
+                (SFT_DISABLED, 0, 0)
+            }
+            // When storing into a dedicated Rx buffer, SFID1 is the exact ID to match and
+            // SFID2 holds the buffer offset (bits 10:9 set to 0 select "store into Rx buffer")
+            (FilterElementConfiguration::StoreInRxBuffer, _) => (
+                SFT_RANGE,
+                filter.id1 as u16,
+                u16::from(u8::from(filter.rx_buffer_offset)),
+            ),
+            (_, FilterType::Range) => (SFT_RANGE, filter.id1 as u16, filter.id2 as u16),
+            (_, FilterType::Dualid) => (SFT_DUAL_ID, filter.id1 as u16, filter.id2 as u16),
+            (_, FilterType::Classic) => (SFT_CLASSIC, filter.id1 as u16, filter.id2 as u16),
+        };
+
+        let sfec = if sft == SFT_DISABLED { 0 } else { sfec };
+
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe {
+            self.inner.s0().init(|r| {
+                r.sft()
+                    .set(sft)
+                    .sfec()
+                    .set(sfec)
+                    .sfid1()
+                    .set(sfid1)
+                    .sfid2()
+                    .set(sfid2)
+            })
+        };
+    }
+
+    fn disable(&self) {
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe { self.inner.s0().init(|r| r.sft().set(SFT_DISABLED).sfec().set(0)) };
+    }
+}
+
+pub(crate) struct ExtendedFilter {
+    inner: reg::msg_filter::ExtFilterMsg,
+}
+
+impl ExtendedFilter {
+    pub(crate) fn new(ptr: *mut u8) -> Self {
+        Self {
+            inner: unsafe { transmute(ptr) },
+        }
+    }
+}
+
+impl FilterElement for ExtendedFilter {
+    fn write(&self, filter: &Filter) {
+        let (efec, eft, efid1, efid2) = match (filter.element_configuration, filter.typ) {
+            (FilterElementConfiguration::Disable, _) | (_, FilterType::None) => (0, 0, 0, 0),
+            // When storing into a dedicated Rx buffer, EFID1 is the exact ID to match and
+            // EFID2 holds the buffer offset (bits 10:9 set to 0 select "store into Rx buffer")
+            (FilterElementConfiguration::StoreInRxBuffer, _) => (
+                u8::from(filter.element_configuration),
+                EFT_RANGE,
+                filter.id1,
+                u32::from(filter.rx_buffer_offset),
+            ),
+            (config, FilterType::Range) => (u8::from(config), EFT_RANGE, filter.id1, filter.id2),
+            (config, FilterType::Dualid) => (u8::from(config), EFT_DUAL_ID, filter.id1, filter.id2),
+            (config, FilterType::Classic) => {
+                (u8::from(config), EFT_CLASSIC, filter.id1, filter.id2)
+            }
+        };
+
+        // The element is disabled first, so that it is never active with a half written
+        // configuration, then the second word is written and finally the element is enabled.
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe { self.inner.f0().init(|r| r.efec().set(0)) };
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe {
+            self.inner
+                .f1()
+                .init(|r| r.eft().set(eft).efid2().set(efid2))
+        };
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe {
+            self.inner
+                .f0()
+                .init(|r| r.efec().set(efec).efid1().set(efid1))
+        };
+    }
+
+    fn disable(&self) {
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe { self.inner.f0().init(|r| r.efec().set(0)) };
+        // SAFETY: the element is written as a whole, all values are masked to their field width
+        unsafe { self.inner.f1().init(|r| r) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::can::msg::RxBufferId;
+
+    const CONFIGURATIONS: [FilterElementConfiguration; 7] = [
+        FilterElementConfiguration::StoreInRxFifo0,
+        FilterElementConfiguration::StoreInRxFifo1,
+        FilterElementConfiguration::RejectId,
+        FilterElementConfiguration::SetPriority,
+        FilterElementConfiguration::SetPriorityAndStoreInFifo0,
+        FilterElementConfiguration::SetPriorityAndStoreInFifo1,
+        FilterElementConfiguration::StoreInRxBuffer,
+    ];
+
+    fn filter(
+        element_configuration: FilterElementConfiguration,
+        typ: FilterType,
+        id_length: MessageIdLength,
+        id1: u32,
+        id2: u32,
+    ) -> Filter {
+        Filter {
+            number: 0,
+            element_configuration,
+            typ,
+            id_length,
+            id1,
+            id2,
+            rx_buffer_offset: RxBufferId::new(5).unwrap(),
+        }
+    }
+
+    /// Word of the standard filter element written for `filter`
+    fn standard_element(filter: &Filter) -> u32 {
+        let mut element = u32::MAX;
+        StandardFilter::new(core::ptr::addr_of_mut!(element).cast()).write(filter);
+        element
+    }
+
+    /// Words F0 and F1 of the extended filter element written for `filter`
+    fn extended_element(filter: &Filter) -> [u32; 2] {
+        let mut element = [u32::MAX; 2];
+        ExtendedFilter::new(element.as_mut_ptr().cast()).write(filter);
+        element
+    }
+
+    #[test]
+    fn test_standard_filter_type() {
+        let standard = |typ| {
+            let filter = filter(
+                FilterElementConfiguration::StoreInRxFifo0,
+                typ,
+                MessageIdLength::Standard,
+                0x123,
+                0x456,
+            );
+            standard_element(&filter)
+        };
+
+        // SFT in bits 31:30, SFEC in bits 29:27, SFID1 in bits 26:16, SFID2 in bits 10:0
+        assert_eq!(standard(FilterType::Range), 0x0923_0456);
+        assert_eq!(standard(FilterType::Dualid), 0x4923_0456);
+        assert_eq!(standard(FilterType::Classic), 0x8923_0456);
+        assert_eq!(standard(FilterType::None), 0xC000_0000);
+    }
+
+    #[test]
+    fn test_standard_filter_configuration() {
+        for configuration in CONFIGURATIONS {
+            let filter = filter(
+                configuration,
+                FilterType::Classic,
+                MessageIdLength::Standard,
+                0x7FF,
+                0x700,
+            );
+            let sfec = u32::from(u8::from(configuration)) << 27;
+            let expected = if configuration == FilterElementConfiguration::StoreInRxBuffer {
+                // range type bits, SFID2 holding the Rx buffer offset
+                sfec | 0x07FF_0005
+            } else {
+                0x8000_0000 | sfec | 0x07FF_0700
+            };
+            assert_eq!(standard_element(&filter), expected);
+        }
+
+        let disabled = filter(
+            FilterElementConfiguration::Disable,
+            FilterType::Classic,
+            MessageIdLength::Standard,
+            0x123,
+            0x456,
+        );
+        assert_eq!(standard_element(&disabled), 0xC000_0000);
+    }
+
+    #[test]
+    fn test_extended_filter_type() {
+        let extended = |typ| {
+            let filter = filter(
+                FilterElementConfiguration::StoreInRxFifo1,
+                typ,
+                MessageIdLength::Extended,
+                0x1234_5678,
+                0x1ABC_DEF0,
+            );
+            extended_element(&filter)
+        };
+
+        // EFEC in bits 31:29 and EFID1 in bits 28:0 of F0, EFT in bits 31:30 and EFID2 in
+        // bits 28:0 of F1
+        assert_eq!(extended(FilterType::Range), [0x5234_5678, 0x1ABC_DEF0]);
+        assert_eq!(extended(FilterType::Dualid), [0x5234_5678, 0x5ABC_DEF0]);
+        assert_eq!(extended(FilterType::Classic), [0x5234_5678, 0x9ABC_DEF0]);
+        assert_eq!(extended(FilterType::None), [0, 0]);
+    }
+
+    #[test]
+    fn test_extended_filter_configuration() {
+        for configuration in CONFIGURATIONS {
+            let filter = filter(
+                configuration,
+                FilterType::Dualid,
+                MessageIdLength::Extended,
+                0x1FFF_FFFF,
+                0x0000_0001,
+            );
+            let efec = u32::from(u8::from(configuration)) << 29;
+            let expected = if configuration == FilterElementConfiguration::StoreInRxBuffer {
+                // range type bits, EFID2 holding the Rx buffer offset
+                [efec | 0x1FFF_FFFF, 0x0000_0005]
+            } else {
+                [efec | 0x1FFF_FFFF, 0x4000_0001]
+            };
+            assert_eq!(extended_element(&filter), expected);
+        }
+
+        let disabled = filter(
+            FilterElementConfiguration::Disable,
+            FilterType::Dualid,
+            MessageIdLength::Extended,
+            0x1234_5678,
+            0x1ABC_DEF0,
+        );
+        assert_eq!(extended_element(&disabled), [0, 0]);
+    }
+
+    #[test]
+    fn test_filter_list_gap() {
+        let standard = filter(
+            FilterElementConfiguration::StoreInRxFifo0,
+            FilterType::Classic,
+            MessageIdLength::Standard,
+            0x123,
+            0x456,
+        );
+        let extended = filter(
+            FilterElementConfiguration::StoreInRxFifo0,
+            FilterType::Classic,
+            MessageIdLength::Extended,
+            0x123,
+            0x456,
+        );
+        let filters = [
+            standard,
+            Filter {
+                number: 5,
+                ..standard
+            },
+            Filter {
+                number: 2,
+                ..extended
+            },
+        ];
+
+        let mut elements = [u32::MAX; 7];
+        let list = elements.as_mut_ptr();
+        let size = write_filter_list(&filters, MessageIdLength::Standard, |number| {
+            StandardFilter::new(list.wrapping_add(usize::from(number)).cast())
+        });
+        // filters 1 to 4 are disabled, the element after the list is left untouched
+        assert_eq!(size, 6);
+        assert_eq!(
+            elements,
+            [
+                0x8923_0456,
+                0xC000_0000,
+                0xC000_0000,
+                0xC000_0000,
+                0xC000_0000,
+                0x8923_0456,
+                u32::MAX
+            ]
+        );
+
+        let mut elements = [[u32::MAX; 2]; 3];
+        let list = elements.as_mut_ptr();
+        let size = write_filter_list(&filters, MessageIdLength::Extended, |number| {
+            ExtendedFilter::new(list.wrapping_add(usize::from(number)).cast())
+        });
+        assert_eq!(size, 3);
+        assert_eq!(elements, [[0, 0], [0, 0], [0x2000_0123, 0x8000_0456]]);
+    }
+}
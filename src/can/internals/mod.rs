@@ -1,4 +1,6 @@
+mod filter;
 mod rx;
 mod tx;
+mod tx_event;
 
-pub(crate) use {rx::*, tx::*};
+pub(crate) use {filter::*, rx::*, tx::*, tx_event::*};
@@ -58,6 +58,24 @@ impl Rx {
         unsafe { DataLenghtCode::try_from(d).unwrap_unchecked() }
     }
 
+    #[inline]
+    pub(crate) fn is_remote_frame(&self) -> bool {
+        // SAFETY: each bit of R0 is RWH
+        unsafe { self.inner.r0().read() }.rtr().get()
+    }
+
+    #[inline]
+    pub(crate) fn get_error_state_indicator(&self) -> bool {
+        // SAFETY: each bit of R0 is RWH
+        unsafe { self.inner.r0().read() }.esi().get()
+    }
+
+    #[inline]
+    pub(crate) fn get_timestamp(&self) -> u16 {
+        // SAFETY: each bit of R1 is RWH
+        unsafe { self.inner.r1().read() }.rxts().get()
+    }
+
     pub(crate) fn get_frame_mode(&self) -> FrameMode {
         // SAFETY: each bit of R1 is RWH
         let r1 = unsafe { self.inner.r1().read() };
@@ -73,12 +91,39 @@ impl Rx {
         }
     }
 
-    pub(crate) fn read_data(&self, data_length_code: DataLenghtCode, data: *mut u8) {
+    /// Data field of the element, borrowed from the message RAM
+    ///
+    /// # Safety
+    ///
+    /// `length` must not exceed the data field size of the element, and the node must not
+    /// write the element while the data is borrowed: the element must not have been released,
+    /// and must not belong to an Rx FIFO in overwrite mode, where the node overwrites the
+    /// element at the get index when the FIFO is full.
+    pub(crate) unsafe fn data(&self, length: usize) -> &[u8] {
         let source_address = self.inner.db().ptr() as *const u8;
-        let length = data_length_code.to_length();
 
         debug!("reading {} bytes from {:x}", length, source_address);
 
-        unsafe { core::ptr::copy_nonoverlapping(source_address, data, length) };
+        // SAFETY: the element is in the message RAM, which is never deallocated, and the
+        // caller guarantees that the data field holds at least length bytes which are not
+        // written while they are borrowed
+        unsafe { core::slice::from_raw_parts(source_address, length) }
+    }
+
+    /// Copy the start of the data field into `data`. The bytes are read one at a time, as
+    /// the node may overwrite the element of an Rx FIFO in overwrite mode meanwhile.
+    ///
+    /// # Safety
+    ///
+    /// The length of `data` must not exceed the data field size of the element.
+    pub(crate) unsafe fn copy_data(&self, data: &mut [u8]) {
+        let source_address = self.inner.db().ptr() as *const u8;
+
+        debug!("copying {} bytes from {:x}", data.len(), source_address);
+
+        for (offset, byte) in data.iter_mut().enumerate() {
+            // SAFETY: the caller guarantees that offset is within the data field
+            *byte = unsafe { source_address.wrapping_add(offset).read_volatile() };
+        }
     }
 }
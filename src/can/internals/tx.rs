@@ -1,7 +1,7 @@
 // TODO Remove this once the code is stable
 #![allow(clippy::undocumented_unsafe_blocks)]
 
-use crate::can::msg::{MessageId, MessageIdLength, TxBufferId};
+use crate::can::msg::{MessageId, MessageIdLength};
 use crate::can::{frame::DataLenghtCode, reg, FrameMode};
 use core::mem::transmute;
 
@@ -47,15 +47,34 @@ impl Tx {
         };
     }
 
+    #[inline]
+    pub(crate) fn get_msg_id(&self) -> MessageId {
+        let t0 = unsafe { self.inner.t0().read() };
+        let length = if t0.xtd().get() {
+            MessageIdLength::Extended
+        } else {
+            MessageIdLength::Standard
+        };
+        let shift = if length == MessageIdLength::Standard {
+            18
+        } else {
+            0
+        };
+        MessageId {
+            data: t0.id().get() >> shift,
+            length,
+        }
+    }
+
     #[inline]
     pub(crate) fn set_tx_event_fifo_ctrl(&self, enable: bool) {
         // SAFETY: bits 15:0 and 22 are written with 0, enable is in range [0, 1]
         unsafe { self.inner.t1().modify(|r| r.efc().set(enable)) };
     }
 
-    pub(crate) fn set_message_marker(&self, buffer_id: TxBufferId) {
-        // SAFETY: bits 15:0 and 22 are written with 0, buffer_id is in range [0, 2^8)
-        unsafe { self.inner.t1().modify(|r| r.mm().set(buffer_id.into())) };
+    pub(crate) fn set_message_marker(&self, marker: u8) {
+        // SAFETY: bits 15:0 and 22 are written with 0, marker is in range [0, 2^8)
+        unsafe { self.inner.t1().modify(|r| r.mm().set(marker)) };
     }
 
     #[inline]
@@ -0,0 +1,66 @@
+// TODO Remove this once the code is stable
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+use crate::can::msg::{MessageId, MessageIdLength, Timestamp, TxEvent, TxEventType};
+use crate::can::{frame::DataLenghtCode, reg, FrameMode};
+use core::mem::transmute;
+
+pub(crate) struct TxEventElement {
+    inner: reg::msg_tx_event::TxEventMsg,
+}
+
+impl TxEventElement {
+    pub(crate) fn new(ptr: *mut u8) -> Self {
+        Self {
+            inner: unsafe { transmute(ptr) },
+        }
+    }
+}
+
+impl TxEventElement {
+    pub(crate) fn read(&self) -> TxEvent {
+        // SAFETY: each bit of E0 is R
+        let e0 = unsafe { self.inner.e0().read() };
+        // SAFETY: each bit of E1 is R
+        let e1 = unsafe { self.inner.e1().read() };
+
+        let (length, shift) = if e0.xtd().get() {
+            (MessageIdLength::Extended, 0)
+        } else {
+            (MessageIdLength::Standard, 18)
+        };
+
+        let frame_mode = match (e1.fdf().get(), e1.brs().get()) {
+            (false, _) => FrameMode::Standard,
+            (true, false) => FrameMode::FdLong,
+            (true, true) => FrameMode::FdLongAndFast,
+        };
+
+        let event_type = if e1.et().get() == 0b10 {
+            TxEventType::TransmissionInSpiteOfCancellation
+        } else {
+            TxEventType::Transmission
+        };
+
+        // SAFETY: the value is a valid DataLenghtCode, because it is a 4 bit field
+        let data_length_code =
+            unsafe { DataLenghtCode::try_from(e1.dlc().get()).unwrap_unchecked() };
+
+        TxEvent {
+            id: MessageId {
+                data: e0.id().get() >> shift,
+                length,
+            },
+            marker: e1.mm().get(),
+            data_length_code,
+            frame_mode,
+            remote_transmit_request: e0.rtr().get(),
+            error_state_indicator: e0.esi().get(),
+            event_type,
+            timestamp: Timestamp {
+                raw: e1.txts().get(),
+                nanos: None,
+            },
+        }
+    }
+}
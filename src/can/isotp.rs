@@ -0,0 +1,1110 @@
+//! ISO-TP (ISO 15765-2) transport layer, carrying messages larger than a CAN frame.
+//!
+//! An [`IsoTp`] channel segments a message in a first frame followed by consecutive frames,
+//! paced by the flow control frames of the receiver, and reassembles the messages sent by
+//! its peer. As the other [protocol layers](super#protocol-layers), the received frames are
+//! given to [`IsoTp::receive_frame`], while [`IsoTp::poll`] checks the separation time and
+//! the N_As, N_Bs and N_Cr timeouts, and transmits on any [`embedded_can::nb::Can`]
+//! implementation, such as a configured [`Node`](crate::can::Node).
+
+use super::frame::{DataLenghtCode, OwnedFrame};
+use embedded_can::Id;
+
+const PCI_SINGLE_FRAME: u8 = 0x00;
+const PCI_FIRST_FRAME: u8 = 0x10;
+const PCI_CONSECUTIVE_FRAME: u8 = 0x20;
+const PCI_FLOW_CONTROL: u8 = 0x30;
+
+/// Data length of a classic CAN frame, also used by the flow control frames
+const CLASSIC_LENGTH: usize = 8;
+/// Largest message length encoded in the 12 bits of a first frame
+const FIRST_FRAME_LENGTH_MAX: usize = 4095;
+/// Default padding of the CAN FD frames up to a valid data length
+const FD_PADDING: u8 = 0xCC;
+
+/// How the peers are addressed, besides the CAN identifiers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Addressing {
+    /// The CAN identifiers alone address the peers
+    Normal,
+    /// The first data byte holds the target address: `tx` in the transmitted frames, `rx`
+    /// in the received frames
+    Extended { tx: u8, rx: u8 },
+    /// The first data byte holds the same address extension in both directions
+    Mixed { address_extension: u8 },
+}
+
+impl Addressing {
+    fn tx_address(self) -> Option<u8> {
+        match self {
+            Addressing::Normal => None,
+            Addressing::Extended { tx, .. } => Some(tx),
+            Addressing::Mixed { address_extension } => Some(address_extension),
+        }
+    }
+
+    fn rx_address(self) -> Option<u8> {
+        match self {
+            Addressing::Normal => None,
+            Addressing::Extended { rx, .. } => Some(rx),
+            Addressing::Mixed { address_extension } => Some(address_extension),
+        }
+    }
+
+    /// Number of address bytes before the protocol control information
+    fn offset(self) -> usize {
+        usize::from(self != Addressing::Normal)
+    }
+}
+
+/// Format of the transmitted frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// Classic CAN frames of up to 8 bytes
+    Classic,
+    /// CAN FD frames of up to the given length, a CAN FD data length greater than 8.
+    /// The node must transmit CAN FD frames.
+    Fd(usize),
+}
+
+impl Framing {
+    fn max_length(self) -> usize {
+        match self {
+            Framing::Classic => CLASSIC_LENGTH,
+            Framing::Fd(length) => length,
+        }
+    }
+}
+
+/// Timeouts of a channel, in microseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    /// Transmission of a frame by the node
+    pub n_as: u32,
+    /// Reception of a flow control frame, once the sender waits for one
+    pub n_bs: u32,
+    /// Reception of the next consecutive frame
+    pub n_cr: u32,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            n_as: 1_000_000,
+            n_bs: 1_000_000,
+            n_cr: 1_000_000,
+        }
+    }
+}
+
+/// Configuration of an [`IsoTp`] channel
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Identifier of the transmitted frames
+    pub tx_id: Id,
+    /// Identifier of the received frames
+    pub rx_id: Id,
+    pub addressing: Addressing,
+    pub framing: Framing,
+    /// Pad the classic frames to 8 bytes with this value. CAN FD frames longer than 8 bytes
+    /// are always padded up to a valid data length, with 0xCC by default.
+    pub padding: Option<u8>,
+    /// Number of consecutive frames the peer sends before waiting for a flow control frame,
+    /// 0 for the whole message
+    pub block_size: u8,
+    /// Minimum separation time between the consecutive frames sent by the peer, encoded as
+    /// in the flow control frame: 0x00 to 0x7F milliseconds, or 0xF1 to 0xF9 for 100 to
+    /// 900 microseconds
+    pub st_min: u8,
+    /// Number of flow control wait frames accepted in a row before aborting a transmission
+    pub max_wait_frames: u8,
+    pub timeouts: Timeouts,
+}
+
+impl Config {
+    /// Normal addressing with unpadded classic frames, no block size and no separation time
+    #[must_use]
+    pub fn new(tx_id: impl Into<Id>, rx_id: impl Into<Id>) -> Self {
+        Self {
+            tx_id: tx_id.into(),
+            rx_id: rx_id.into(),
+            addressing: Addressing::Normal,
+            framing: Framing::Classic,
+            padding: None,
+            block_size: 0,
+            st_min: 0,
+            max_wait_frames: 10,
+            timeouts: Timeouts::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// A message is already being sent
+    Busy,
+    /// The message is empty or larger than the channel buffer
+    InvalidLength,
+    /// The node has not accepted a frame in time (N_As)
+    TransmitTimeout,
+    /// The node has refused a frame
+    TransmitFailed,
+    /// No flow control frame has been received in time (N_Bs)
+    FlowControlTimeout,
+    /// The receiver has no room for the message
+    Overflow,
+    /// The receiver has sent more wait flow control frames than accepted
+    WaitLimit,
+    /// The flow control frame has a reserved flow status
+    InvalidFlowStatus,
+    /// No consecutive frame has been received in time (N_Cr)
+    ConsecutiveFrameTimeout,
+    /// A consecutive frame has been lost
+    WrongSequenceNumber,
+    /// The received message is larger than the channel buffer
+    BufferOverflow,
+}
+
+/// Outcome of [`IsoTp::poll`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// The message given to [`IsoTp::send`] has been transmitted
+    Sent,
+    /// The message given to [`IsoTp::send`] has been aborted
+    SendFailed(Error),
+    /// The message being received has been aborted
+    ReceiveFailed(Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlowStatus {
+    ContinueToSend,
+    Overflow,
+}
+
+impl FlowStatus {
+    fn code(self) -> u8 {
+        match self {
+            FlowStatus::ContinueToSend => 0,
+            FlowStatus::Overflow => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SenderState {
+    Idle,
+    /// The next frame can be built
+    Ready,
+    /// The frame waits to be accepted by the node, then for a flow control frame or not
+    Transmit {
+        frame: OwnedFrame,
+        since: u32,
+        flow_control: bool,
+    },
+    WaitFlowControl {
+        since: u32,
+    },
+    WaitSeparationTime {
+        since: u32,
+    },
+    /// The transmission is aborted, reported by the next poll
+    Failed(Error),
+}
+
+struct Sender<const N: usize> {
+    data: [u8; N],
+    length: usize,
+    offset: usize,
+    sequence: u8,
+    state: SenderState,
+    /// Consecutive frames left in the current block, `None` without limit
+    block_remaining: Option<u8>,
+    /// Separation time between consecutive frames requested by the receiver, in microseconds
+    separation_time: u32,
+    waits: u8,
+}
+
+#[derive(Clone, Copy)]
+enum ReceiverState {
+    Idle,
+    ConsecutiveFrames { since: u32 },
+}
+
+struct Receiver<const N: usize> {
+    data: [u8; N],
+    length: usize,
+    offset: usize,
+    sequence: u8,
+    /// Consecutive frames received in the current block
+    block_count: u8,
+    state: ReceiverState,
+    /// Flow control frame waiting to be transmitted
+    flow_control: Option<FlowStatus>,
+}
+
+/// ISO-TP channel between two peers, sending and receiving messages of up to `N` bytes
+pub struct IsoTp<const N: usize> {
+    config: Config,
+    tx: Sender<N>,
+    rx: Receiver<N>,
+}
+
+impl<const N: usize> IsoTp<N> {
+    /// Create a channel, `None` when the CAN FD frame length is not a valid data length
+    #[must_use]
+    pub fn new(config: Config) -> Option<Self> {
+        if let Framing::Fd(length) = config.framing {
+            if length <= CLASSIC_LENGTH || DataLenghtCode::from_length(length).is_none() {
+                return None;
+            }
+        }
+
+        Some(Self {
+            config,
+            tx: Sender {
+                data: [0; N],
+                length: 0,
+                offset: 0,
+                sequence: 0,
+                state: SenderState::Idle,
+                block_remaining: None,
+                separation_time: 0,
+                waits: 0,
+            },
+            rx: Receiver {
+                data: [0; N],
+                length: 0,
+                offset: 0,
+                sequence: 0,
+                block_count: 0,
+                state: ReceiverState::Idle,
+                flow_control: None,
+            },
+        })
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Whether a message given to [`IsoTp::send`] is being transmitted
+    #[must_use]
+    pub fn is_sending(&self) -> bool {
+        !matches!(self.tx.state, SenderState::Idle)
+    }
+
+    /// Whether a message segmented in consecutive frames is being received
+    #[must_use]
+    pub fn is_receiving(&self) -> bool {
+        matches!(self.rx.state, ReceiverState::ConsecutiveFrames { .. })
+    }
+
+    /// Start sending a message, transmitted by the following calls to [`IsoTp::poll`]
+    pub fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        if self.is_sending() {
+            return Err(Error::Busy);
+        }
+
+        let buffer = self
+            .tx
+            .data
+            .get_mut(..data.len())
+            .filter(|buffer| !buffer.is_empty())
+            .ok_or(Error::InvalidLength)?;
+        buffer.copy_from_slice(data);
+
+        self.tx.length = data.len();
+        self.tx.offset = 0;
+        self.tx.sequence = 0;
+        self.tx.block_remaining = None;
+        self.tx.separation_time = 0;
+        self.tx.waits = 0;
+        self.tx.state = SenderState::Ready;
+        Ok(())
+    }
+
+    /// Abort the message being sent, without notifying the receiver
+    pub fn cancel_send(&mut self) {
+        self.tx.state = SenderState::Idle;
+    }
+
+    /// Process a received frame, returning the message it completes.
+    ///
+    /// Frames with another identifier or address are ignored, so that all the received
+    /// frames can be given to the channel. The flow control frames answering the frame are
+    /// transmitted by the next call to [`IsoTp::poll`].
+    pub fn receive_frame(
+        &mut self,
+        frame: &impl embedded_can::Frame,
+        now: u32,
+    ) -> Result<Option<&[u8]>, Error> {
+        if frame.is_remote_frame() || frame.id() != self.config.rx_id {
+            return Ok(None);
+        }
+
+        let data = frame.data();
+        let offset = self.config.addressing.offset();
+        if let Some(address) = self.config.addressing.rx_address() {
+            if data.first() != Some(&address) {
+                return Ok(None);
+            }
+        }
+
+        let Some(pci) = data.get(offset..).filter(|pci| !pci.is_empty()) else {
+            return Ok(None);
+        };
+
+        let length = match pci.first().map_or(0, |byte| byte & 0xF0) {
+            PCI_SINGLE_FRAME => self.receive_single_frame(data.len(), pci)?,
+            PCI_FIRST_FRAME => {
+                self.receive_first_frame(data.len(), pci, now)?;
+                None
+            }
+            PCI_CONSECUTIVE_FRAME => self.receive_consecutive_frame(pci, now)?,
+            PCI_FLOW_CONTROL => {
+                self.receive_flow_control(pci, now);
+                None
+            }
+            _ => None,
+        };
+
+        Ok(length.and_then(|length| self.rx.data.get(..length)))
+    }
+
+    /// Transmit the pending frames and check the timeouts, to be called periodically.
+    ///
+    /// Consecutive frames are transmitted until the node is full or the separation time
+    /// requested by the receiver has to elapse. Frames replaced in the node by
+    /// [`embedded_can::nb::Can::transmit`] are dropped.
+    pub fn poll<C>(&mut self, can: &mut C, now: u32) -> Option<Event>
+    where
+        C: embedded_can::nb::Can<Frame = OwnedFrame>,
+    {
+        if let Some(status) = self.rx.flow_control {
+            match can.transmit(&self.flow_control_frame(status)) {
+                Ok(_) => self.rx.flow_control = None,
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(_)) => {
+                    self.rx.flow_control = None;
+                    self.rx.state = ReceiverState::Idle;
+                    return Some(Event::ReceiveFailed(Error::TransmitFailed));
+                }
+            }
+        }
+
+        if let ReceiverState::ConsecutiveFrames { since } = self.rx.state {
+            if now.wrapping_sub(since) > self.config.timeouts.n_cr {
+                self.rx.flow_control = None;
+                self.rx.state = ReceiverState::Idle;
+                return Some(Event::ReceiveFailed(Error::ConsecutiveFrameTimeout));
+            }
+        }
+
+        self.poll_sender(can, now)
+    }
+
+    fn poll_sender<C>(&mut self, can: &mut C, now: u32) -> Option<Event>
+    where
+        C: embedded_can::nb::Can<Frame = OwnedFrame>,
+    {
+        loop {
+            if let SenderState::WaitSeparationTime { since } = self.tx.state {
+                if now.wrapping_sub(since) >= self.tx.separation_time {
+                    self.tx.state = SenderState::Ready;
+                }
+            }
+
+            if let SenderState::Ready = self.tx.state {
+                let (frame, flow_control) = self.next_frame();
+                self.tx.state = SenderState::Transmit {
+                    frame,
+                    since: now,
+                    flow_control,
+                };
+            }
+
+            match self.tx.state {
+                SenderState::Transmit {
+                    frame,
+                    since,
+                    flow_control,
+                } => match can.transmit(&frame) {
+                    Ok(_) => {
+                        if self.tx.offset == self.tx.length {
+                            self.tx.state = SenderState::Idle;
+                            return Some(Event::Sent);
+                        }
+                        self.tx.state = if flow_control {
+                            SenderState::WaitFlowControl { since: now }
+                        } else {
+                            SenderState::WaitSeparationTime { since: now }
+                        };
+                    }
+                    Err(nb::Error::WouldBlock) => {
+                        if now.wrapping_sub(since) > self.config.timeouts.n_as {
+                            self.tx.state = SenderState::Idle;
+                            return Some(Event::SendFailed(Error::TransmitTimeout));
+                        }
+                        return None;
+                    }
+                    Err(nb::Error::Other(_)) => {
+                        self.tx.state = SenderState::Idle;
+                        return Some(Event::SendFailed(Error::TransmitFailed));
+                    }
+                },
+                SenderState::WaitFlowControl { since } => {
+                    if now.wrapping_sub(since) > self.config.timeouts.n_bs {
+                        self.tx.state = SenderState::Idle;
+                        return Some(Event::SendFailed(Error::FlowControlTimeout));
+                    }
+                    return None;
+                }
+                SenderState::Failed(error) => {
+                    self.tx.state = SenderState::Idle;
+                    return Some(Event::SendFailed(error));
+                }
+                SenderState::Idle | SenderState::Ready | SenderState::WaitSeparationTime { .. } => {
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Build the next frame of the message being sent, and whether the sender then waits
+    /// for a flow control frame
+    fn next_frame(&mut self) -> (OwnedFrame, bool) {
+        let offset = self.config.addressing.offset();
+        let max_length = self.config.framing.max_length();
+        let mut frame = FrameData::new(self.config.addressing.tx_address());
+        let mut flow_control = false;
+
+        if self.tx.offset == 0 {
+            let length = self.tx.length;
+            let [_, _, length_high, length_low] = length_bytes(length);
+
+            if length <= single_frame_capacity(max_length, offset) {
+                if length < CLASSIC_LENGTH - offset {
+                    frame.push(PCI_SINGLE_FRAME | length_low);
+                } else {
+                    frame.push(PCI_SINGLE_FRAME);
+                    frame.push(length_low);
+                }
+            } else if length <= FIRST_FRAME_LENGTH_MAX {
+                frame.push(PCI_FIRST_FRAME | length_high);
+                frame.push(length_low);
+                self.tx.sequence = 1;
+                flow_control = true;
+            } else {
+                frame.push(PCI_FIRST_FRAME);
+                frame.push(0);
+                frame.extend(&length_bytes(length));
+                self.tx.sequence = 1;
+                flow_control = true;
+            }
+        } else {
+            frame.push(PCI_CONSECUTIVE_FRAME | self.tx.sequence);
+            self.tx.sequence = (self.tx.sequence + 1) & 0x0F;
+
+            if let Some(remaining) = self.tx.block_remaining.as_mut() {
+                *remaining = remaining.saturating_sub(1);
+                flow_control = *remaining == 0;
+            }
+        }
+
+        let count = max_length
+            .saturating_sub(frame.length)
+            .min(self.tx.length - self.tx.offset);
+        let data = self
+            .tx
+            .data
+            .get(self.tx.offset..self.tx.offset + count)
+            .unwrap_or_default();
+        frame.extend(data);
+        self.tx.offset += count;
+
+        (
+            frame.finish(self.config.tx_id, self.config.padding, self.config.framing),
+            flow_control,
+        )
+    }
+
+    fn flow_control_frame(&self, status: FlowStatus) -> OwnedFrame {
+        let mut frame = FrameData::new(self.config.addressing.tx_address());
+        frame.push(PCI_FLOW_CONTROL | status.code());
+        frame.push(self.config.block_size);
+        frame.push(self.config.st_min);
+        frame.finish(self.config.tx_id, self.config.padding, self.config.framing)
+    }
+
+    fn receive_single_frame(
+        &mut self,
+        frame_length: usize,
+        pci: &[u8],
+    ) -> Result<Option<usize>, Error> {
+        let nibble = pci.first().map_or(0, |byte| byte & 0x0F);
+        // the escaped length is only used by CAN FD frames longer than 8 bytes
+        let (length, start) = match (nibble, frame_length > CLASSIC_LENGTH) {
+            (0, true) => (usize::from(pci.get(1).copied().unwrap_or(0)), 2),
+            (1.., false) => (usize::from(nibble), 1),
+            _ => return Ok(None),
+        };
+
+        let Some(data) = pci
+            .get(start..start + length)
+            .filter(|data| !data.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        // a single frame ends the message being received
+        self.rx.state = ReceiverState::Idle;
+        self.rx.flow_control = None;
+
+        let buffer = self
+            .rx
+            .data
+            .get_mut(..length)
+            .ok_or(Error::BufferOverflow)?;
+        buffer.copy_from_slice(data);
+        Ok(Some(length))
+    }
+
+    fn receive_first_frame(
+        &mut self,
+        frame_length: usize,
+        pci: &[u8],
+        now: u32,
+    ) -> Result<(), Error> {
+        if frame_length < CLASSIC_LENGTH || DataLenghtCode::from_length(frame_length).is_none() {
+            return Ok(());
+        }
+
+        let short_length = pci.get(..2).map_or(0, |bytes| {
+            usize::from(u16::from_be_bytes([
+                bytes.first().map_or(0, |byte| byte & 0x0F),
+                bytes.get(1).copied().unwrap_or(0),
+            ]))
+        });
+        let (length, start) = if short_length == 0 {
+            let length = pci
+                .get(2..6)
+                .and_then(|bytes| <[u8; 4]>::try_from(bytes).ok())
+                .map_or(0, u32::from_be_bytes);
+            (usize::try_from(length).unwrap_or(usize::MAX), 6)
+        } else {
+            (short_length, 2)
+        };
+
+        // a message fitting a single frame of the same length is invalid
+        if length <= single_frame_capacity(frame_length, self.config.addressing.offset()) {
+            return Ok(());
+        }
+
+        if length > N {
+            self.rx.state = ReceiverState::Idle;
+            self.rx.flow_control = Some(FlowStatus::Overflow);
+            return Err(Error::BufferOverflow);
+        }
+
+        let data = pci.get(start..).unwrap_or_default();
+        if let Some(buffer) = self.rx.data.get_mut(..data.len()) {
+            buffer.copy_from_slice(data);
+        }
+
+        self.rx.length = length;
+        self.rx.offset = data.len();
+        self.rx.sequence = 1;
+        self.rx.block_count = 0;
+        self.rx.state = ReceiverState::ConsecutiveFrames { since: now };
+        self.rx.flow_control = Some(FlowStatus::ContinueToSend);
+        Ok(())
+    }
+
+    fn receive_consecutive_frame(&mut self, pci: &[u8], now: u32) -> Result<Option<usize>, Error> {
+        let ReceiverState::ConsecutiveFrames { .. } = self.rx.state else {
+            return Ok(None);
+        };
+
+        if pci.first().map_or(0, |byte| byte & 0x0F) != self.rx.sequence {
+            self.rx.state = ReceiverState::Idle;
+            self.rx.flow_control = None;
+            return Err(Error::WrongSequenceNumber);
+        }
+
+        let data = pci.get(1..).unwrap_or_default();
+        let count = data.len().min(self.rx.length - self.rx.offset);
+        if let (Some(buffer), Some(data)) = (
+            self.rx.data.get_mut(self.rx.offset..self.rx.offset + count),
+            data.get(..count),
+        ) {
+            buffer.copy_from_slice(data);
+        }
+        self.rx.offset += count;
+        self.rx.sequence = (self.rx.sequence + 1) & 0x0F;
+
+        if self.rx.offset == self.rx.length {
+            self.rx.state = ReceiverState::Idle;
+            return Ok(Some(self.rx.length));
+        }
+
+        self.rx.state = ReceiverState::ConsecutiveFrames { since: now };
+        if self.config.block_size != 0 {
+            self.rx.block_count += 1;
+            if self.rx.block_count == self.config.block_size {
+                self.rx.block_count = 0;
+                self.rx.flow_control = Some(FlowStatus::ContinueToSend);
+            }
+        }
+        Ok(None)
+    }
+
+    fn receive_flow_control(&mut self, pci: &[u8], now: u32) {
+        let SenderState::WaitFlowControl { .. } = self.tx.state else {
+            return;
+        };
+        let (Some(&status), Some(&block_size), Some(&st_min)) =
+            (pci.first(), pci.get(1), pci.get(2))
+        else {
+            return;
+        };
+
+        self.tx.state = match status & 0x0F {
+            0 => {
+                self.tx.block_remaining = (block_size != 0).then_some(block_size);
+                self.tx.separation_time = separation_time(st_min);
+                self.tx.waits = 0;
+                SenderState::Ready
+            }
+            1 => {
+                self.tx.waits = self.tx.waits.saturating_add(1);
+                if self.tx.waits > self.config.max_wait_frames {
+                    SenderState::Failed(Error::WaitLimit)
+                } else {
+                    SenderState::WaitFlowControl { since: now }
+                }
+            }
+            2 => SenderState::Failed(Error::Overflow),
+            _ => SenderState::Failed(Error::InvalidFlowStatus),
+        };
+    }
+}
+
+/// Data of a frame being built
+struct FrameData {
+    data: [u8; 64],
+    length: usize,
+}
+
+impl FrameData {
+    fn new(address: Option<u8>) -> Self {
+        let mut frame = Self {
+            data: [0; 64],
+            length: 0,
+        };
+        if let Some(address) = address {
+            frame.push(address);
+        }
+        frame
+    }
+
+    fn push(&mut self, byte: u8) {
+        if let Some(slot) = self.data.get_mut(self.length) {
+            *slot = byte;
+            self.length += 1;
+        }
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.push(byte);
+        }
+    }
+
+    /// Pad the data to a valid data length and build a frame of the given framing
+    fn finish(mut self, id: Id, padding: Option<u8>, framing: Framing) -> OwnedFrame {
+        let length = if self.length > CLASSIC_LENGTH {
+            DataLenghtCode::from_length_padded(self.length).map_or(64, DataLenghtCode::to_length)
+        } else if padding.is_some() {
+            CLASSIC_LENGTH
+        } else {
+            self.length
+        };
+
+        let byte = padding.unwrap_or(FD_PADDING);
+        while self.length < length {
+            self.push(byte);
+        }
+
+        // the padded length is always a valid data length of the framing
+        let data = self.data.get(..length).unwrap_or_default();
+        match framing {
+            Framing::Classic => embedded_can::Frame::new(id, data),
+            Framing::Fd(_) => OwnedFrame::new_fd(id, data),
+        }
+        .unwrap_or(OwnedFrame::EMPTY)
+    }
+}
+
+/// Largest message sent in a single frame of `frame_length` bytes
+fn single_frame_capacity(frame_length: usize, offset: usize) -> usize {
+    if frame_length > CLASSIC_LENGTH {
+        frame_length - 2 - offset
+    } else {
+        CLASSIC_LENGTH - 1 - offset
+    }
+}
+
+fn length_bytes(length: usize) -> [u8; 4] {
+    u32::try_from(length).unwrap_or(u32::MAX).to_be_bytes()
+}
+
+/// Decode the minimum separation time of a flow control frame, in microseconds. Reserved
+/// values are handled as the longest time, 127 milliseconds.
+fn separation_time(st_min: u8) -> u32 {
+    match st_min {
+        0x00..=0x7F => u32::from(st_min) * 1000,
+        0xF1..=0xF9 => u32::from(st_min - 0xF0) * 100,
+        _ => 127_000,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::can::msg::FrameMode;
+    use crate::can::CanError;
+    use embedded_can::{ExtendedId, Frame as _, StandardId};
+
+    #[derive(Default)]
+    struct MockCan {
+        sent: Vec<OwnedFrame>,
+        busy: bool,
+    }
+
+    impl embedded_can::nb::Can for MockCan {
+        type Frame = OwnedFrame;
+        type Error = CanError;
+
+        fn transmit(&mut self, frame: &OwnedFrame) -> nb::Result<Option<OwnedFrame>, CanError> {
+            if self.busy {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.sent.push(*frame);
+            Ok(None)
+        }
+
+        fn receive(&mut self) -> nb::Result<OwnedFrame, CanError> {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn ids() -> (Id, Id) {
+        (
+            StandardId::new(0x7E0).unwrap().into(),
+            StandardId::new(0x7E8).unwrap().into(),
+        )
+    }
+
+    fn pair<const N: usize>(configure: impl Fn(&mut Config)) -> (IsoTp<N>, IsoTp<N>) {
+        let (a, b) = ids();
+        let mut tester = Config::new(a, b);
+        let mut ecu = Config::new(b, a);
+        configure(&mut tester);
+        configure(&mut ecu);
+        if let Addressing::Extended { tx, rx } = tester.addressing {
+            ecu.addressing = Addressing::Extended { tx: rx, rx: tx };
+        }
+        (IsoTp::new(tester).unwrap(), IsoTp::new(ecu).unwrap())
+    }
+
+    /// Exchange frames until the message sent by `sender` is transmitted, returning the
+    /// message received by `receiver`
+    fn transfer<const N: usize>(
+        sender: &mut IsoTp<N>,
+        receiver: &mut IsoTp<N>,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut sender_can = MockCan::default();
+        let mut receiver_can = MockCan::default();
+        let mut message = Vec::new();
+
+        sender.send(data).unwrap();
+        for now in 0..1000 {
+            let event = sender.poll(&mut sender_can, now * 1000);
+            for frame in sender_can.sent.drain(..) {
+                if let Some(received) = receiver.receive_frame(&frame, now * 1000).unwrap() {
+                    message = received.to_vec();
+                }
+            }
+            assert_eq!(receiver.poll(&mut receiver_can, now * 1000), None);
+            for frame in receiver_can.sent.drain(..) {
+                assert_eq!(sender.receive_frame(&frame, now * 1000), Ok(None));
+            }
+            if let Some(event) = event {
+                assert_eq!(event, Event::Sent);
+                return message;
+            }
+        }
+        panic!("message not sent");
+    }
+
+    fn message(length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|i| u8::try_from(i % 251).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_single_frame() {
+        let (mut tester, mut ecu) = pair::<64>(|config| config.padding = Some(0xAA));
+        let mut can = MockCan::default();
+
+        tester.send(&[0x10, 0x03]).unwrap();
+        assert_eq!(tester.send(&[0x3E]), Err(Error::Busy));
+        assert_eq!(tester.poll(&mut can, 0), Some(Event::Sent));
+        assert!(!tester.is_sending());
+
+        let frame = can.sent.first().unwrap();
+        assert_eq!(
+            frame.data(),
+            &[0x02, 0x10, 0x03, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA]
+        );
+        assert_eq!(ecu.receive_frame(frame, 0), Ok(Some(&[0x10, 0x03][..])));
+
+        assert_eq!(tester.send(&[]), Err(Error::InvalidLength));
+        assert_eq!(tester.send(&[0; 65]), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_frame_mode() {
+        for (framing, frame_mode) in [
+            (Framing::Classic, Some(FrameMode::Standard)),
+            (Framing::Fd(64), None),
+        ] {
+            let (mut tester, _) = pair::<64>(|config| config.framing = framing);
+            let mut can = MockCan::default();
+
+            tester.send(&[0x10, 0x03]).unwrap();
+            assert_eq!(tester.poll(&mut can, 0), Some(Event::Sent));
+            let frame = can.sent.first().unwrap();
+            assert_eq!(frame.as_frame().frame_mode, frame_mode);
+        }
+    }
+
+    #[test]
+    fn test_segmented_classic() {
+        let (mut tester, mut ecu) = pair::<256>(|config| config.block_size = 3);
+        let data = message(200);
+        assert_eq!(transfer(&mut tester, &mut ecu, &data), data);
+        for length in [7, 8] {
+            let data = message(length);
+            assert_eq!(transfer(&mut ecu, &mut tester, &data), data);
+        }
+    }
+
+    #[test]
+    fn test_segmented_fd() {
+        let (mut tester, mut ecu) = pair::<256>(|config| config.framing = Framing::Fd(64));
+        let data = message(130);
+        assert_eq!(transfer(&mut tester, &mut ecu, &data), data);
+
+        // escaped single frame
+        let mut can = MockCan::default();
+        let data = message(40);
+        tester.send(&data).unwrap();
+        assert_eq!(tester.poll(&mut can, 0), Some(Event::Sent));
+        let frame = can.sent.first().unwrap();
+        assert_eq!(frame.dlc(), 48);
+        assert_eq!(frame.data().get(..2), Some(&[0x00, 40][..]));
+        assert_eq!(frame.data().get(42..), Some(&[FD_PADDING; 6][..]));
+        assert_eq!(ecu.receive_frame(frame, 0), Ok(Some(&data[..])));
+    }
+
+    #[test]
+    fn test_long_first_frame() {
+        let (mut tester, mut ecu) = pair::<5000>(|config| config.framing = Framing::Fd(64));
+        let data = message(4500);
+        let mut can = MockCan::default();
+
+        tester.send(&data).unwrap();
+        assert_eq!(tester.poll(&mut can, 0), None);
+        let first = can.sent.first().unwrap();
+        assert_eq!(
+            first.data().get(..6),
+            Some(&[0x10, 0x00, 0x00, 0x00, 0x11, 0x94][..])
+        );
+        assert_eq!(ecu.receive_frame(first, 0), Ok(None));
+        assert!(ecu.is_receiving());
+
+        let (mut tester, mut ecu) = pair::<5000>(|config| config.framing = Framing::Fd(64));
+        assert_eq!(transfer(&mut tester, &mut ecu, &data), data);
+    }
+
+    #[test]
+    fn test_addressing() {
+        let (mut tester, mut ecu) =
+            pair::<128>(|config| config.addressing = Addressing::Extended { tx: 0x01, rx: 0xF1 });
+        let data = message(50);
+        assert_eq!(transfer(&mut tester, &mut ecu, &data), data);
+
+        let (mut tester, mut ecu) = pair::<128>(|config| {
+            config.addressing = Addressing::Mixed {
+                address_extension: 0x42,
+            }
+        });
+        assert_eq!(transfer(&mut tester, &mut ecu, &data), data);
+
+        // another address extension is ignored
+        let (id, _) = ids();
+        let frame = OwnedFrame::new(id, &[0x43, 0x01, 0x3E]).unwrap();
+        assert_eq!(ecu.receive_frame(&frame, 0), Ok(None));
+        let frame = OwnedFrame::new(id, &[0x42, 0x01, 0x3E]).unwrap();
+        assert_eq!(ecu.receive_frame(&frame, 0), Ok(Some(&[0x3E][..])));
+
+        // as are other identifiers
+        let other = ExtendedId::new(0x18DA_F110).unwrap();
+        let frame = OwnedFrame::new(other, &[0x42, 0x01, 0x3E]).unwrap();
+        assert_eq!(ecu.receive_frame(&frame, 0), Ok(None));
+    }
+
+    #[test]
+    fn test_separation_time() {
+        assert_eq!(separation_time(0x00), 0);
+        assert_eq!(separation_time(0x7F), 127_000);
+        assert_eq!(separation_time(0xF1), 100);
+        assert_eq!(separation_time(0xF9), 900);
+        assert_eq!(separation_time(0x80), 127_000);
+        assert_eq!(separation_time(0xFA), 127_000);
+
+        let (mut tester, mut ecu) = pair::<64>(|config| config.st_min = 5);
+        let mut tester_can = MockCan::default();
+        let mut ecu_can = MockCan::default();
+
+        tester.send(&message(20)).unwrap();
+        assert_eq!(tester.poll(&mut tester_can, 0), None);
+        ecu.receive_frame(tester_can.sent.first().unwrap(), 0)
+            .unwrap();
+        assert_eq!(ecu.poll(&mut ecu_can, 0), None);
+        assert_eq!(ecu_can.sent.first().unwrap().data(), &[0x30, 0x00, 0x05]);
+        tester
+            .receive_frame(ecu_can.sent.first().unwrap(), 0)
+            .unwrap();
+
+        // the first consecutive frame follows the flow control frame
+        assert_eq!(tester.poll(&mut tester_can, 100), None);
+        assert_eq!(tester_can.sent.len(), 2);
+        assert_eq!(tester.poll(&mut tester_can, 5099), None);
+        assert_eq!(tester_can.sent.len(), 2);
+        assert_eq!(tester.poll(&mut tester_can, 5100), Some(Event::Sent));
+        assert_eq!(tester_can.sent.len(), 3);
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let timeouts = Timeouts::default();
+
+        // N_As
+        let (mut tester, _) = pair::<64>(|_| {});
+        let mut can = MockCan {
+            busy: true,
+            ..MockCan::default()
+        };
+        tester.send(&[1, 2, 3]).unwrap();
+        assert_eq!(tester.poll(&mut can, u32::MAX - 10), None);
+        assert_eq!(tester.poll(&mut can, timeouts.n_as - 11), None);
+        assert_eq!(
+            tester.poll(&mut can, timeouts.n_as - 10),
+            Some(Event::SendFailed(Error::TransmitTimeout))
+        );
+
+        // N_Bs
+        let (mut tester, mut ecu) = pair::<64>(|_| {});
+        let mut can = MockCan::default();
+        tester.send(&message(20)).unwrap();
+        assert_eq!(tester.poll(&mut can, 0), None);
+        assert_eq!(tester.poll(&mut can, timeouts.n_bs), None);
+        assert_eq!(
+            tester.poll(&mut can, timeouts.n_bs + 1),
+            Some(Event::SendFailed(Error::FlowControlTimeout))
+        );
+
+        // N_Cr
+        ecu.receive_frame(can.sent.first().unwrap(), 0).unwrap();
+        assert_eq!(ecu.poll(&mut can, timeouts.n_cr), None);
+        assert_eq!(
+            ecu.poll(&mut can, timeouts.n_cr + 1),
+            Some(Event::ReceiveFailed(Error::ConsecutiveFrameTimeout))
+        );
+        assert!(!ecu.is_receiving());
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let (_, id) = ids();
+        let (mut tester, _) = pair::<64>(|config| config.max_wait_frames = 1);
+        let mut can = MockCan::default();
+        let wait = OwnedFrame::new(id, &[0x31, 0, 0]).unwrap();
+
+        tester.send(&message(20)).unwrap();
+        tester.poll(&mut can, 0);
+        tester.receive_frame(&wait, 0).unwrap();
+        assert_eq!(tester.poll(&mut can, 0), None);
+        tester.receive_frame(&wait, 0).unwrap();
+        assert_eq!(
+            tester.poll(&mut can, 0),
+            Some(Event::SendFailed(Error::WaitLimit))
+        );
+
+        let overflow = OwnedFrame::new(id, &[0x32, 0, 0]).unwrap();
+        tester.send(&message(20)).unwrap();
+        tester.poll(&mut can, 0);
+        tester.receive_frame(&overflow, 0).unwrap();
+        assert_eq!(
+            tester.poll(&mut can, 0),
+            Some(Event::SendFailed(Error::Overflow))
+        );
+
+        // block size of 2
+        let continue_to_send = OwnedFrame::new(id, &[0x30, 2, 0]).unwrap();
+        can.sent.clear();
+        tester.send(&message(30)).unwrap();
+        tester.poll(&mut can, 0);
+        tester.receive_frame(&continue_to_send, 0).unwrap();
+        assert_eq!(tester.poll(&mut can, 0), None);
+        assert_eq!(can.sent.len(), 3);
+        tester.receive_frame(&continue_to_send, 0).unwrap();
+        assert_eq!(tester.poll(&mut can, 0), Some(Event::Sent));
+        assert_eq!(can.sent.len(), 5);
+        let sequence: Vec<u8> = can
+            .sent
+            .iter()
+            .filter_map(|frame| frame.data().first().copied())
+            .collect();
+        assert_eq!(sequence, [0x10, 0x21, 0x22, 0x23, 0x24]);
+    }
+
+    #[test]
+    fn test_receive_errors() {
+        let (id, _) = ids();
+        let (_, mut ecu) = pair::<16>(|_| {});
+        let mut can = MockCan::default();
+
+        let first = OwnedFrame::new(id, &[0x10, 20, 0, 1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(ecu.receive_frame(&first, 0), Err(Error::BufferOverflow));
+        assert_eq!(ecu.poll(&mut can, 0), None);
+        assert_eq!(can.sent.first().unwrap().data(), &[0x32, 0, 0]);
+
+        let first = OwnedFrame::new(id, &[0x10, 14, 0, 1, 2, 3, 4, 5]).unwrap();
+        let lost = OwnedFrame::new(id, &[0x22, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        assert_eq!(ecu.receive_frame(&first, 0), Ok(None));
+        assert_eq!(ecu.receive_frame(&lost, 0), Err(Error::WrongSequenceNumber));
+        assert!(!ecu.is_receiving());
+
+        // a first frame fitting in a single frame is ignored
+        let first = OwnedFrame::new(id, &[0x10, 7, 0, 1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(ecu.receive_frame(&first, 0), Ok(None));
+        assert!(!ecu.is_receiving());
+    }
+}
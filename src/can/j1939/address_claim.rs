@@ -0,0 +1,302 @@
+//! Address claim procedure of J1939-81.
+
+use super::{frame_id, J1939Id, Pgn, GLOBAL_ADDRESS, NULL_ADDRESS};
+use crate::can::frame::OwnedFrame;
+
+/// Time without contention after which a claimed address can be used
+const CLAIM_TIMEOUT: u32 = 250_000;
+/// Priority of the address claimed messages
+const CLAIM_PRIORITY: u8 = 6;
+/// Addresses taken by the self-configurable controller applications
+const SELF_CONFIGURABLE_ADDRESSES: core::ops::RangeInclusive<u8> = 128..=247;
+
+/// NAME of a controller application. The controller application with the lowest NAME
+/// wins the arbitration of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Name(pub u64);
+
+impl Name {
+    /// Whether the controller application can claim another address when losing its own
+    #[must_use]
+    pub const fn is_arbitrary_address_capable(self) -> bool {
+        self.0 >> 63 != 0
+    }
+
+    fn to_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        <[u8; 8]>::try_from(data)
+            .ok()
+            .map(u64::from_le_bytes)
+            .map(Name)
+    }
+}
+
+/// Outcome of the address claim procedure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClaimEvent {
+    /// The address has been claimed without contention and can be used
+    Claimed(u8),
+    /// The claimed address has been taken by a controller application with a lower NAME.
+    /// The address must not be used anymore, another one is being claimed if the NAME is
+    /// arbitrary address capable.
+    Lost,
+    /// No address is available, the cannot claim address message has been sent
+    CannotClaim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Claiming { since: u32 },
+    Claimed,
+    CannotClaim,
+}
+
+/// Address claim state machine of a controller application
+pub struct AddressClaim {
+    name: Name,
+    preferred_address: u8,
+    address: u8,
+    state: State,
+    /// Addresses claimed by other controller applications, one bit per address
+    claimed: [u64; 4],
+    /// An address claimed or cannot claim message waits to be transmitted
+    pending: bool,
+}
+
+impl AddressClaim {
+    #[must_use]
+    pub fn new(name: Name, preferred_address: u8) -> Self {
+        Self {
+            name,
+            preferred_address,
+            address: preferred_address,
+            state: State::Idle,
+            claimed: [0; 4],
+            pending: false,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> Name {
+        self.name
+    }
+
+    /// The claimed address, once no contention has been seen for 250 ms
+    #[must_use]
+    pub fn address(&self) -> Option<u8> {
+        match self.state {
+            State::Claimed => Some(self.address),
+            State::Idle | State::Claiming { .. } | State::CannotClaim => None,
+        }
+    }
+
+    /// Start claiming the preferred address
+    pub fn start(&mut self, now: u32) {
+        self.address = self.preferred_address;
+        self.state = State::Claiming { since: now };
+        self.pending = true;
+    }
+
+    /// Process a received frame, answering the contending claims and the requests for the
+    /// address claimed PGN
+    pub fn receive(&mut self, frame: &impl embedded_can::Frame, now: u32) -> Option<ClaimEvent> {
+        let id = frame_id(frame)?;
+        if id.pgn == Pgn::REQUEST {
+            let requested =
+                frame.data().get(..3) == Some(Pgn::ADDRESS_CLAIMED.to_bytes().as_slice());
+            let destination = id.destination_address;
+            if requested
+                && self.state != State::Idle
+                && (destination == GLOBAL_ADDRESS || Some(destination) == self.address())
+            {
+                self.pending = true;
+            }
+            return None;
+        }
+
+        if id.pgn != Pgn::ADDRESS_CLAIMED {
+            return None;
+        }
+        let other = Name::from_bytes(frame.data())?;
+        let source_address = id.source_address;
+        if source_address == NULL_ADDRESS || other == self.name {
+            return None;
+        }
+
+        let contending = source_address == self.address
+            && matches!(self.state, State::Claiming { .. } | State::Claimed);
+        if contending && self.name < other {
+            // the other controller application must claim another address
+            self.pending = true;
+            return None;
+        }
+
+        self.set_claimed(source_address);
+        if contending {
+            self.claim_another(now)
+        } else {
+            None
+        }
+    }
+
+    /// Check the end of the claim, to be called periodically
+    pub fn poll(&mut self, now: u32) -> Option<ClaimEvent> {
+        match self.state {
+            State::Claiming { since } if now.wrapping_sub(since) >= CLAIM_TIMEOUT => {
+                self.state = State::Claimed;
+                Some(ClaimEvent::Claimed(self.address))
+            }
+            _ => None,
+        }
+    }
+
+    /// The address claimed or cannot claim message to transmit
+    pub fn next_frame(&mut self) -> Option<OwnedFrame> {
+        if !self.pending {
+            return None;
+        }
+        self.pending = false;
+
+        let source_address = match self.state {
+            State::Idle => return None,
+            State::Claiming { .. } | State::Claimed => self.address,
+            State::CannotClaim => NULL_ADDRESS,
+        };
+        let id = J1939Id::new(
+            CLAIM_PRIORITY,
+            Pgn::ADDRESS_CLAIMED,
+            source_address,
+            GLOBAL_ADDRESS,
+        );
+        Some(OwnedFrame::from_bytes(id, self.name.to_bytes()))
+    }
+
+    fn claim_another(&mut self, now: u32) -> Option<ClaimEvent> {
+        let was_claimed = self.state == State::Claimed;
+        let address = SELF_CONFIGURABLE_ADDRESSES
+            .clone()
+            .find(|&address| !self.is_claimed(address));
+
+        self.pending = true;
+        match address {
+            Some(address) if self.name.is_arbitrary_address_capable() => {
+                self.address = address;
+                self.state = State::Claiming { since: now };
+                was_claimed.then_some(ClaimEvent::Lost)
+            }
+            _ => {
+                self.state = State::CannotClaim;
+                Some(ClaimEvent::CannotClaim)
+            }
+        }
+    }
+
+    fn set_claimed(&mut self, address: u8) {
+        if let Some(bits) = self.claimed.get_mut(usize::from(address / 64)) {
+            *bits |= 1 << (address % 64);
+        }
+    }
+
+    fn is_claimed(&self, address: u8) -> bool {
+        self.claimed
+            .get(usize::from(address / 64))
+            .is_some_and(|bits| bits & (1 << (address % 64)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use embedded_can::Frame as _;
+
+    const NAME: Name = Name(0x8000_0000_0000_1000);
+
+    fn claim(name: Name, address: u8) -> OwnedFrame {
+        let id = J1939Id::new(6, Pgn::ADDRESS_CLAIMED, address, GLOBAL_ADDRESS);
+        OwnedFrame::from_bytes(id, name.to_bytes())
+    }
+
+    #[test]
+    fn test_claim() {
+        let mut claim_sm = AddressClaim::new(NAME, 0x80);
+        assert_eq!(claim_sm.next_frame(), None);
+
+        claim_sm.start(0);
+        assert_eq!(claim_sm.next_frame(), Some(claim(NAME, 0x80)));
+        assert_eq!(claim_sm.next_frame(), None);
+        assert_eq!(claim_sm.poll(CLAIM_TIMEOUT - 1), None);
+        assert_eq!(claim_sm.address(), None);
+        assert_eq!(
+            claim_sm.poll(CLAIM_TIMEOUT),
+            Some(ClaimEvent::Claimed(0x80))
+        );
+        assert_eq!(claim_sm.address(), Some(0x80));
+
+        // request for the address claimed PGN
+        let request = OwnedFrame::new(
+            J1939Id::new(6, Pgn::REQUEST, 0xF9, GLOBAL_ADDRESS),
+            &Pgn::ADDRESS_CLAIMED.to_bytes(),
+        )
+        .unwrap();
+        assert_eq!(claim_sm.receive(&request, 0), None);
+        assert_eq!(claim_sm.next_frame(), Some(claim(NAME, 0x80)));
+    }
+
+    #[test]
+    fn test_arbitration() {
+        let mut claim_sm = AddressClaim::new(NAME, 0x80);
+        claim_sm.start(0);
+        claim_sm.next_frame();
+        claim_sm.poll(CLAIM_TIMEOUT);
+
+        // a higher NAME loses, the address is claimed again
+        assert_eq!(claim_sm.receive(&claim(Name(NAME.0 + 1), 0x80), 0), None);
+        assert_eq!(claim_sm.next_frame(), Some(claim(NAME, 0x80)));
+        assert_eq!(claim_sm.address(), Some(0x80));
+
+        // a lower NAME wins, the next free address is claimed
+        claim_sm.receive(&claim(Name(1), 0x81), 0);
+        assert_eq!(
+            claim_sm.receive(&claim(Name(1), 0x80), 1000),
+            Some(ClaimEvent::Lost)
+        );
+        assert_eq!(claim_sm.address(), None);
+        assert_eq!(claim_sm.next_frame(), Some(claim(NAME, 0x82)));
+        assert_eq!(
+            claim_sm.poll(1000 + CLAIM_TIMEOUT),
+            Some(ClaimEvent::Claimed(0x82))
+        );
+    }
+
+    #[test]
+    fn test_cannot_claim() {
+        // not arbitrary address capable
+        let name = Name(0x1000);
+        let mut claim_sm = AddressClaim::new(name, 0x20);
+        claim_sm.start(0);
+        claim_sm.next_frame();
+
+        assert_eq!(
+            claim_sm.receive(&claim(Name(1), 0x20), 0),
+            Some(ClaimEvent::CannotClaim)
+        );
+        assert_eq!(claim_sm.next_frame(), Some(claim(name, NULL_ADDRESS)));
+        assert_eq!(claim_sm.poll(CLAIM_TIMEOUT), None);
+        assert_eq!(claim_sm.address(), None);
+
+        // no free self-configurable address
+        let mut claim_sm = AddressClaim::new(NAME, 0x80);
+        for address in SELF_CONFIGURABLE_ADDRESSES {
+            claim_sm.receive(&claim(Name(1), address), 0);
+        }
+        claim_sm.start(0);
+        assert_eq!(
+            claim_sm.receive(&claim(Name(1), 0x80), 0),
+            Some(ClaimEvent::CannotClaim)
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! SAE J1939 on 29 bit identifiers.
+//!
+//! [`J1939Id`] splits and composes the priority, parameter group number (PGN), source and
+//! destination addresses of an extended identifier. On top of it, [`AddressClaim`] claims
+//! the source address of a controller application (J1939-81), and [`Transport`] sends and
+//! receives messages of up to 1785 bytes with the BAM and CMDT transport protocol sessions
+//! (J1939-21). Both are [protocol layers](super#protocol-layers) exchanging
+//! [`OwnedFrame`](crate::can::OwnedFrame)s through their `receive` and `next_frame` methods.
+
+#![allow(clippy::module_name_repetitions)]
+
+mod address_claim;
+mod transport;
+
+pub use address_claim::{AddressClaim, ClaimEvent, Name};
+pub use transport::{AbortReason, Transport, TransportError, TransportEvent, MAX_LENGTH};
+
+use super::msg::{MessageId, MessageIdLength};
+
+/// Destination address of the messages sent to all the controller applications
+pub const GLOBAL_ADDRESS: u8 = 0xFF;
+/// Source address of a controller application without address
+pub const NULL_ADDRESS: u8 = 0xFE;
+
+/// First PDU format of the PDU2 format PGNs, which have no destination address
+const PDU2_FORMAT_MIN: u8 = 240;
+
+/// Parameter group number, on 18 bits. The PDU specific byte of the PDU1 format PGNs,
+/// holding the destination address in the identifier, is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pgn(u32);
+
+impl Pgn {
+    pub const REQUEST: Pgn = Pgn(0xEA00);
+    pub const ADDRESS_CLAIMED: Pgn = Pgn(0xEE00);
+    /// Transport protocol connection management
+    pub const TP_CM: Pgn = Pgn(0xEC00);
+    /// Transport protocol data transfer
+    pub const TP_DT: Pgn = Pgn(0xEB00);
+
+    /// Create a PGN, `None` if it exceeds 18 bits or has a PDU1 format with a non-zero
+    /// PDU specific byte
+    #[must_use]
+    pub const fn new(raw: u32) -> Option<Self> {
+        let pgn = Pgn(raw);
+        if raw > 0x3_FFFF || (pgn.is_pdu1() && pgn.pdu_specific() != 0) {
+            None
+        } else {
+            Some(pgn)
+        }
+    }
+
+    #[must_use]
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    #[must_use]
+    pub const fn pdu_format(self) -> u8 {
+        let [_, pdu_format, _, _] = self.0.to_le_bytes();
+        pdu_format
+    }
+
+    #[must_use]
+    pub const fn pdu_specific(self) -> u8 {
+        let [pdu_specific, _, _, _] = self.0.to_le_bytes();
+        pdu_specific
+    }
+
+    /// Whether the PGN is sent to a destination address, otherwise it is broadcast
+    #[must_use]
+    pub const fn is_pdu1(self) -> bool {
+        self.pdu_format() < PDU2_FORMAT_MIN
+    }
+
+    /// Little endian encoding, as in the request and transport protocol messages
+    fn to_bytes(self) -> [u8; 3] {
+        let [low, middle, high, _] = self.0.to_le_bytes();
+        [low, middle, high]
+    }
+
+    fn from_bytes([low, middle, high]: [u8; 3]) -> Option<Self> {
+        Self::new(u32::from_le_bytes([low, middle, high, 0]))
+    }
+}
+
+/// Fields of a J1939 extended identifier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct J1939Id {
+    /// From 0, the highest priority, to 7
+    pub priority: u8,
+    pub pgn: Pgn,
+    pub source_address: u8,
+    /// Destination of a PDU1 format PGN, [`GLOBAL_ADDRESS`] for all. Not encoded in the
+    /// identifier of PDU2 format PGNs, for which it is always [`GLOBAL_ADDRESS`] once
+    /// decoded.
+    pub destination_address: u8,
+}
+
+impl J1939Id {
+    #[must_use]
+    pub fn new(priority: u8, pgn: Pgn, source_address: u8, destination_address: u8) -> Self {
+        Self {
+            priority,
+            pgn,
+            source_address,
+            destination_address,
+        }
+    }
+
+    /// The 29 bit identifier
+    #[must_use]
+    pub fn raw(&self) -> u32 {
+        let pdu_specific = if self.pgn.is_pdu1() {
+            u32::from(self.destination_address)
+        } else {
+            u32::from(self.pgn.pdu_specific())
+        };
+
+        (u32::from(self.priority & 0x7) << 26)
+            | ((self.pgn.raw() & 0x3_FF00) << 8)
+            | (pdu_specific << 8)
+            | u32::from(self.source_address)
+    }
+
+    /// Split a 29 bit identifier, `None` if it exceeds 29 bits
+    #[must_use]
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        if raw > 0x1FFF_FFFF {
+            return None;
+        }
+
+        let [source_address, pdu_specific, _, priority] = raw.to_le_bytes();
+        let pgn = Pgn((raw >> 8) & 0x3_FFFF);
+        let (pgn, destination_address) = if pgn.is_pdu1() {
+            (Pgn(pgn.0 & 0x3_FF00), pdu_specific)
+        } else {
+            (pgn, GLOBAL_ADDRESS)
+        };
+
+        Some(Self {
+            priority: (priority >> 2) & 0x7,
+            pgn,
+            source_address,
+            destination_address,
+        })
+    }
+}
+
+impl From<J1939Id> for MessageId {
+    fn from(id: J1939Id) -> Self {
+        MessageId {
+            data: id.raw(),
+            length: MessageIdLength::Extended,
+        }
+    }
+}
+
+impl TryFrom<MessageId> for J1939Id {
+    type Error = ();
+
+    fn try_from(id: MessageId) -> Result<Self, Self::Error> {
+        match id.length {
+            MessageIdLength::Extended => J1939Id::from_raw(id.data).ok_or(()),
+            MessageIdLength::Standard | MessageIdLength::Both => Err(()),
+        }
+    }
+}
+
+impl From<J1939Id> for embedded_can::Id {
+    fn from(id: J1939Id) -> Self {
+        // the raw identifier never exceeds 29 bits
+        let id = embedded_can::ExtendedId::new(id.raw()).unwrap_or(embedded_can::ExtendedId::MAX);
+        embedded_can::Id::Extended(id)
+    }
+}
+
+impl TryFrom<embedded_can::Id> for J1939Id {
+    type Error = ();
+
+    fn try_from(id: embedded_can::Id) -> Result<Self, Self::Error> {
+        match id {
+            embedded_can::Id::Extended(id) => J1939Id::from_raw(id.as_raw()).ok_or(()),
+            embedded_can::Id::Standard(_) => Err(()),
+        }
+    }
+}
+
+/// Identifier of a received data frame, `None` for standard identifiers and remote frames
+fn frame_id(frame: &impl embedded_can::Frame) -> Option<J1939Id> {
+    if frame.is_remote_frame() {
+        return None;
+    }
+    J1939Id::try_from(frame.id()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::can::frame::OwnedFrame;
+    use crate::can::msg::FrameMode;
+    use embedded_can::{ExtendedId, Frame as _, Id, StandardId};
+
+    #[test]
+    fn test_id() {
+        // engine speed, PGN 61444 (0xF004), priority 3, from the engine
+        let id = J1939Id::from_raw(0x0CF0_0400).unwrap();
+        assert_eq!(id.priority, 3);
+        assert_eq!(id.pgn, Pgn::new(0xF004).unwrap());
+        assert_eq!(id.source_address, 0x00);
+        assert_eq!(id.destination_address, GLOBAL_ADDRESS);
+        assert_eq!(id.raw(), 0x0CF0_0400);
+
+        // request from 0xF9 to 0x00
+        let id = J1939Id::from_raw(0x18EA_00F9).unwrap();
+        assert_eq!(id.priority, 6);
+        assert_eq!(id.pgn, Pgn::REQUEST);
+        assert_eq!(id.source_address, 0xF9);
+        assert_eq!(id.destination_address, 0x00);
+        assert_eq!(id.raw(), 0x18EA_00F9);
+
+        // the data page bits belong to the PGN
+        let id = J1939Id::new(7, Pgn::new(0x1_FEF1).unwrap(), 0x21, GLOBAL_ADDRESS);
+        assert_eq!(id.raw(), 0x1DFE_F121);
+        assert_eq!(J1939Id::from_raw(id.raw()), Some(id));
+
+        assert_eq!(J1939Id::from_raw(0x2000_0000), None);
+        assert_eq!(
+            J1939Id::try_from(MessageId {
+                data: 0x123,
+                length: MessageIdLength::Standard,
+            }),
+            Err(())
+        );
+        let message_id = MessageId::from(id);
+        assert_eq!(message_id.length, MessageIdLength::Extended);
+        assert_eq!(J1939Id::try_from(message_id), Ok(id));
+    }
+
+    #[test]
+    fn test_pgn() {
+        assert!(Pgn::REQUEST.is_pdu1());
+        assert!(!Pgn::new(0xFEF1).unwrap().is_pdu1());
+        assert_eq!(Pgn::new(0xEA01), None);
+        assert_eq!(Pgn::new(0x4_0000), None);
+        assert_eq!(Pgn::TP_CM.to_bytes(), [0x00, 0xEC, 0x00]);
+        assert_eq!(Pgn::from_bytes([0xF1, 0xFE, 0x00]), Pgn::new(0xFEF1));
+    }
+
+    #[test]
+    fn test_frame_id() {
+        let id = J1939Id::new(6, Pgn::ADDRESS_CLAIMED, 0x80, GLOBAL_ADDRESS);
+        let frame = OwnedFrame::from_bytes(id, [0; 8]);
+        assert_eq!(
+            frame.id(),
+            Id::Extended(ExtendedId::new(0x18EE_FF80).unwrap())
+        );
+        assert_eq!(frame.as_frame().frame_mode, Some(FrameMode::Standard));
+        assert_eq!(frame_id(&frame), Some(id));
+
+        assert_eq!(frame_id(&OwnedFrame::new_remote(id, 8).unwrap()), None);
+        let standard = OwnedFrame::new(StandardId::new(0x123).unwrap(), &[]).unwrap();
+        assert_eq!(frame_id(&standard), None);
+    }
+}
@@ -0,0 +1,889 @@
+//! Transport protocol of J1939-21: broadcast (BAM) and connection mode (CMDT) sessions.
+
+use super::{frame_id, J1939Id, Pgn, GLOBAL_ADDRESS};
+use crate::can::frame::OwnedFrame;
+
+/// Bytes of data in a data transfer packet
+const PACKET_LENGTH: usize = 7;
+/// Largest message, sent in 255 packets
+pub const MAX_LENGTH: usize = 255 * PACKET_LENGTH;
+/// Smallest message, the shorter ones fit in a single frame
+const MIN_LENGTH: usize = 9;
+/// Priority of the transport protocol messages
+const PRIORITY: u8 = 7;
+
+const CONTROL_RTS: u8 = 16;
+const CONTROL_CTS: u8 = 17;
+const CONTROL_END_OF_MESSAGE_ACK: u8 = 19;
+const CONTROL_BAM: u8 = 32;
+const CONTROL_ABORT: u8 = 255;
+/// Value of the reserved bytes and of the unused bytes of the last data packet
+const RESERVED: u8 = 0xFF;
+
+/// Number of packets requested by a CTS
+const PACKETS_PER_CTS: u8 = 16;
+/// Time between the data packets of a broadcast
+const BAM_INTERVAL: u32 = 50_000;
+/// Timeout between two received data packets
+const T1: u32 = 750_000;
+/// Timeout of the first data packet after a CTS
+const T2: u32 = 1_250_000;
+/// Timeout of the CTS or end of message acknowledge after the last data packet sent
+const T3: u32 = 1_250_000;
+/// Timeout of the CTS after a CTS holding the connection open
+const T4: u32 = 1_050_000;
+
+/// Reason of a connection abort message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbortReason {
+    /// The receiver is already in a session and cannot open another one
+    AlreadyInSession,
+    /// The receiver has no resources for the message
+    SystemResources,
+    Timeout,
+    /// A CTS has been received while data packets were being sent
+    CtsWhileSending,
+    BadSequenceNumber,
+    Other(u8),
+}
+
+impl AbortReason {
+    fn code(self) -> u8 {
+        match self {
+            AbortReason::AlreadyInSession => 1,
+            AbortReason::SystemResources => 2,
+            AbortReason::Timeout => 3,
+            AbortReason::CtsWhileSending => 4,
+            AbortReason::BadSequenceNumber => 7,
+            AbortReason::Other(code) => code,
+        }
+    }
+
+    fn from_code(code: u8) -> Self {
+        match code {
+            1 => AbortReason::AlreadyInSession,
+            2 => AbortReason::SystemResources,
+            3 => AbortReason::Timeout,
+            4 => AbortReason::CtsWhileSending,
+            7 => AbortReason::BadSequenceNumber,
+            _ => AbortReason::Other(code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransportError {
+    /// A message is already being sent
+    Busy,
+    /// The message is shorter than 9 or longer than 1785 bytes
+    InvalidLength,
+    /// The peer has not answered in time
+    Timeout,
+    /// A data packet has been lost
+    BadSequenceNumber,
+    /// The session has been aborted
+    Aborted(AbortReason),
+}
+
+/// Outcome of a transport protocol session
+#[derive(Debug, PartialEq)]
+pub enum TransportEvent<'a> {
+    /// The message given to [`Transport::send`] has been transmitted, and acknowledged
+    /// by the receiver in connection mode
+    Sent,
+    SendFailed(TransportError),
+    /// A message has been received, from a broadcast if the destination address is
+    /// [`GLOBAL_ADDRESS`]
+    Received {
+        pgn: Pgn,
+        source_address: u8,
+        destination_address: u8,
+        data: &'a [u8],
+    },
+    ReceiveFailed(TransportError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SenderState {
+    Idle,
+    /// The BAM or RTS waits to be transmitted
+    Announce,
+    /// Data packets of a broadcast, the previous one sent at `since`
+    Broadcast {
+        since: u32,
+    },
+    WaitClearToSend {
+        since: u32,
+        timeout: u32,
+    },
+    /// Data packets requested by the last CTS
+    SendWindow,
+    WaitAcknowledge {
+        since: u32,
+    },
+    /// The session is over, reported by the next poll
+    Done(Option<TransportError>),
+}
+
+struct Sender {
+    data: [u8; MAX_LENGTH],
+    length: usize,
+    pgn: Pgn,
+    destination_address: u8,
+    state: SenderState,
+    /// Next data packet to send, from 1
+    next_packet: u8,
+    /// Last data packet requested by the CTS
+    last_packet: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReceiverState {
+    Idle,
+    Broadcast { since: u32 },
+    Connection { since: u32, timeout: u32 },
+}
+
+struct Receiver {
+    data: [u8; MAX_LENGTH],
+    length: usize,
+    packets: u8,
+    pgn: Pgn,
+    source_address: u8,
+    destination_address: u8,
+    state: ReceiverState,
+    /// Next expected data packet, from 1
+    next_packet: u8,
+    /// Last data packet requested by the CTS
+    last_packet: u8,
+    /// Number of packets requested by each CTS
+    window: u8,
+}
+
+/// Transport protocol sessions of a controller application: one message sent and one
+/// message received at a time, either broadcast or in connection mode
+pub struct Transport {
+    address: u8,
+    tx: Sender,
+    rx: Receiver,
+    /// Connection management messages waiting to be transmitted, dropped when full
+    control: [Option<OwnedFrame>; 4],
+}
+
+impl Transport {
+    /// Create the sessions of a controller application with the given source address
+    #[must_use]
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            tx: Sender {
+                data: [0; MAX_LENGTH],
+                length: 0,
+                pgn: Pgn(0),
+                destination_address: GLOBAL_ADDRESS,
+                state: SenderState::Idle,
+                next_packet: 1,
+                last_packet: 0,
+            },
+            rx: Receiver {
+                data: [0; MAX_LENGTH],
+                length: 0,
+                packets: 0,
+                pgn: Pgn(0),
+                source_address: GLOBAL_ADDRESS,
+                destination_address: GLOBAL_ADDRESS,
+                state: ReceiverState::Idle,
+                next_packet: 1,
+                last_packet: 0,
+                window: PACKETS_PER_CTS,
+            },
+            control: [None; 4],
+        }
+    }
+
+    #[must_use]
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Change the source address, e.g. once claimed with
+    /// [`AddressClaim`](super::AddressClaim)
+    pub fn set_address(&mut self, address: u8) {
+        self.address = address;
+    }
+
+    /// Whether a message given to [`Transport::send`] is being transmitted
+    #[must_use]
+    pub fn is_sending(&self) -> bool {
+        self.tx.state != SenderState::Idle
+    }
+
+    #[must_use]
+    pub fn is_receiving(&self) -> bool {
+        self.rx.state != ReceiverState::Idle
+    }
+
+    /// Start sending a message of 9 to 1785 bytes, broadcast when the destination is
+    /// [`GLOBAL_ADDRESS`]. The frames are then returned by [`Transport::next_frame`].
+    pub fn send(
+        &mut self,
+        pgn: Pgn,
+        destination_address: u8,
+        data: &[u8],
+    ) -> Result<(), TransportError> {
+        if self.is_sending() {
+            return Err(TransportError::Busy);
+        }
+        if data.len() < MIN_LENGTH {
+            return Err(TransportError::InvalidLength);
+        }
+        self.tx
+            .data
+            .get_mut(..data.len())
+            .ok_or(TransportError::InvalidLength)?
+            .copy_from_slice(data);
+
+        self.tx.length = data.len();
+        self.tx.pgn = pgn;
+        self.tx.destination_address = destination_address;
+        self.tx.next_packet = 1;
+        self.tx.last_packet = 0;
+        self.tx.state = SenderState::Announce;
+        Ok(())
+    }
+
+    /// Process a received frame. Frames of other PGNs or addressed to other controller
+    /// applications are ignored.
+    pub fn receive(
+        &mut self,
+        frame: &impl embedded_can::Frame,
+        now: u32,
+    ) -> Option<TransportEvent<'_>> {
+        let id = frame_id(frame)?;
+        if id.pgn == Pgn::TP_CM {
+            let data = <[u8; 8]>::try_from(frame.data()).ok()?;
+            self.receive_control(id, data, now)
+        } else if id.pgn == Pgn::TP_DT {
+            self.receive_data(id, frame.data(), now)
+        } else {
+            None
+        }
+    }
+
+    /// Check the timeouts and report the end of the message being sent, to be called
+    /// periodically
+    pub fn poll(&mut self, now: u32) -> Option<TransportEvent<'static>> {
+        match self.rx.state {
+            ReceiverState::Broadcast { since } if now.wrapping_sub(since) > T1 => {
+                self.rx.state = ReceiverState::Idle;
+                return Some(TransportEvent::ReceiveFailed(TransportError::Timeout));
+            }
+            ReceiverState::Connection { since, timeout } if now.wrapping_sub(since) > timeout => {
+                self.rx.state = ReceiverState::Idle;
+                self.abort(self.rx.source_address, self.rx.pgn, AbortReason::Timeout);
+                return Some(TransportEvent::ReceiveFailed(TransportError::Timeout));
+            }
+            _ => {}
+        }
+
+        let timed_out = match self.tx.state {
+            SenderState::WaitClearToSend { since, timeout } => now.wrapping_sub(since) > timeout,
+            SenderState::WaitAcknowledge { since } => now.wrapping_sub(since) > T3,
+            SenderState::Done(result) => {
+                self.tx.state = SenderState::Idle;
+                return Some(result.map_or(TransportEvent::Sent, TransportEvent::SendFailed));
+            }
+            _ => false,
+        };
+
+        if timed_out {
+            self.tx.state = SenderState::Idle;
+            self.abort(
+                self.tx.destination_address,
+                self.tx.pgn,
+                AbortReason::Timeout,
+            );
+            return Some(TransportEvent::SendFailed(TransportError::Timeout));
+        }
+        None
+    }
+
+    /// The next frame to transmit: connection management messages first, then the
+    /// announce and data packets of the message being sent
+    pub fn next_frame(&mut self, now: u32) -> Option<OwnedFrame> {
+        if let Some(frame) = self.control.first_mut().and_then(Option::take) {
+            self.control.rotate_left(1);
+            return Some(frame);
+        }
+
+        let packets = packet_count(self.tx.length);
+        match self.tx.state {
+            SenderState::Announce => {
+                let [size_low, size_high] = length_bytes(self.tx.length);
+                let [pgn_low, pgn_middle, pgn_high] = self.tx.pgn.to_bytes();
+                let control = if self.tx.destination_address == GLOBAL_ADDRESS {
+                    self.tx.state = SenderState::Broadcast { since: now };
+                    CONTROL_BAM
+                } else {
+                    self.tx.state = SenderState::WaitClearToSend {
+                        since: now,
+                        timeout: T3,
+                    };
+                    CONTROL_RTS
+                };
+                Some(self.control_frame(
+                    self.tx.destination_address,
+                    [
+                        control, size_low, size_high, packets, RESERVED, pgn_low, pgn_middle,
+                        pgn_high,
+                    ],
+                ))
+            }
+            SenderState::Broadcast { since } if now.wrapping_sub(since) >= BAM_INTERVAL => {
+                let (frame, packet) = self.data_frame();
+                self.tx.state = if packet == packets {
+                    SenderState::Done(None)
+                } else {
+                    SenderState::Broadcast { since: now }
+                };
+                Some(frame)
+            }
+            SenderState::SendWindow => {
+                let (frame, packet) = self.data_frame();
+                if packet == packets {
+                    self.tx.state = SenderState::WaitAcknowledge { since: now };
+                } else if packet == self.tx.last_packet {
+                    self.tx.state = SenderState::WaitClearToSend {
+                        since: now,
+                        timeout: T3,
+                    };
+                }
+                Some(frame)
+            }
+            _ => None,
+        }
+    }
+
+    fn receive_control(
+        &mut self,
+        id: J1939Id,
+        [control, byte1, byte2, byte3, byte4, pgn_low, pgn_middle, pgn_high]: [u8; 8],
+        now: u32,
+    ) -> Option<TransportEvent<'_>> {
+        let pgn = Pgn::from_bytes([pgn_low, pgn_middle, pgn_high])?;
+        let source_address = id.source_address;
+        let to_us = id.destination_address == self.address;
+
+        match control {
+            CONTROL_BAM if id.destination_address == GLOBAL_ADDRESS => {
+                self.start_receive(id, pgn, [byte1, byte2], byte3, None, now);
+            }
+            CONTROL_RTS if to_us => {
+                self.start_receive(id, pgn, [byte1, byte2], byte3, Some(byte4), now);
+            }
+            CONTROL_CTS if to_us => {
+                self.receive_clear_to_send(source_address, pgn, byte1, byte2, now);
+            }
+            CONTROL_END_OF_MESSAGE_ACK
+                if to_us
+                    && matches!(self.tx.state, SenderState::WaitAcknowledge { .. })
+                    && self.is_tx_session(source_address, pgn) =>
+            {
+                self.tx.state = SenderState::Done(None);
+            }
+            CONTROL_ABORT if to_us => {
+                let reason = AbortReason::from_code(byte1);
+                if self.is_tx_session(source_address, pgn) && self.is_sending() {
+                    self.tx.state = SenderState::Done(Some(TransportError::Aborted(reason)));
+                }
+                if matches!(self.rx.state, ReceiverState::Connection { .. })
+                    && self.rx.source_address == source_address
+                    && self.rx.pgn == pgn
+                {
+                    self.rx.state = ReceiverState::Idle;
+                    return Some(TransportEvent::ReceiveFailed(TransportError::Aborted(
+                        reason,
+                    )));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Open a session on a BAM, or on a RTS holding the maximum number of packets per CTS
+    fn start_receive(
+        &mut self,
+        id: J1939Id,
+        pgn: Pgn,
+        size: [u8; 2],
+        packets: u8,
+        max_packets_per_cts: Option<u8>,
+        now: u32,
+    ) {
+        let source_address = id.source_address;
+        let connection = max_packets_per_cts.is_some();
+
+        if self.is_receiving() && self.rx.source_address != source_address {
+            if connection {
+                self.abort(source_address, pgn, AbortReason::AlreadyInSession);
+            }
+            return;
+        }
+
+        let length = usize::from(u16::from_le_bytes(size));
+        if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) || packets != packet_count(length) {
+            self.rx.state = ReceiverState::Idle;
+            if connection {
+                self.abort(source_address, pgn, AbortReason::SystemResources);
+            }
+            return;
+        }
+
+        self.rx.length = length;
+        self.rx.packets = packets;
+        self.rx.pgn = pgn;
+        self.rx.source_address = source_address;
+        self.rx.destination_address = id.destination_address;
+        self.rx.next_packet = 1;
+
+        if let Some(max_packets) = max_packets_per_cts {
+            self.rx.window = max_packets.clamp(1, PACKETS_PER_CTS);
+            self.clear_to_send(now);
+        } else {
+            self.rx.state = ReceiverState::Broadcast { since: now };
+        }
+    }
+
+    /// Request the next packets of the connection
+    fn clear_to_send(&mut self, now: u32) {
+        let next_packet = self.rx.next_packet;
+        let count = self.rx.window.min(self.rx.packets - next_packet + 1);
+        self.rx.last_packet = next_packet + (count - 1);
+        self.rx.state = ReceiverState::Connection {
+            since: now,
+            timeout: T2,
+        };
+
+        let [pgn_low, pgn_middle, pgn_high] = self.rx.pgn.to_bytes();
+        let frame = self.control_frame(
+            self.rx.source_address,
+            [
+                CONTROL_CTS,
+                count,
+                next_packet,
+                RESERVED,
+                RESERVED,
+                pgn_low,
+                pgn_middle,
+                pgn_high,
+            ],
+        );
+        self.push_control(frame);
+    }
+
+    fn receive_clear_to_send(
+        &mut self,
+        source_address: u8,
+        pgn: Pgn,
+        count: u8,
+        next_packet: u8,
+        now: u32,
+    ) {
+        if !self.is_tx_session(source_address, pgn) {
+            return;
+        }
+
+        match self.tx.state {
+            SenderState::WaitClearToSend { .. } if count == 0 => {
+                // the receiver holds the connection open
+                self.tx.state = SenderState::WaitClearToSend {
+                    since: now,
+                    timeout: T4,
+                };
+            }
+            SenderState::WaitClearToSend { .. } => {
+                let packets = packet_count(self.tx.length);
+                if next_packet == 0 || next_packet > packets {
+                    return;
+                }
+                self.tx.next_packet = next_packet;
+                self.tx.last_packet = next_packet.saturating_add(count - 1).min(packets);
+                self.tx.state = SenderState::SendWindow;
+            }
+            SenderState::SendWindow => {
+                self.abort(source_address, pgn, AbortReason::CtsWhileSending);
+                self.tx.state =
+                    SenderState::Done(Some(TransportError::Aborted(AbortReason::CtsWhileSending)));
+            }
+            _ => {}
+        }
+    }
+
+    fn receive_data(&mut self, id: J1939Id, data: &[u8], now: u32) -> Option<TransportEvent<'_>> {
+        let connection = match self.rx.state {
+            ReceiverState::Idle => return None,
+            ReceiverState::Broadcast { .. } => false,
+            ReceiverState::Connection { .. } => true,
+        };
+        if id.source_address != self.rx.source_address
+            || id.destination_address != self.rx.destination_address
+        {
+            return None;
+        }
+
+        let (&sequence, packet) = data.split_first()?;
+        if sequence != self.rx.next_packet {
+            self.rx.state = ReceiverState::Idle;
+            if connection {
+                self.abort(
+                    self.rx.source_address,
+                    self.rx.pgn,
+                    AbortReason::BadSequenceNumber,
+                );
+            }
+            return Some(TransportEvent::ReceiveFailed(
+                TransportError::BadSequenceNumber,
+            ));
+        }
+
+        let offset = usize::from(sequence - 1) * PACKET_LENGTH;
+        let count = (self.rx.length - offset)
+            .min(PACKET_LENGTH)
+            .min(packet.len());
+        if let (Some(buffer), Some(packet)) = (
+            self.rx.data.get_mut(offset..offset + count),
+            packet.get(..count),
+        ) {
+            buffer.copy_from_slice(packet);
+        }
+        self.rx.next_packet = sequence.wrapping_add(1);
+
+        if sequence == self.rx.packets {
+            self.rx.state = ReceiverState::Idle;
+            if connection {
+                self.end_of_message_acknowledge();
+            }
+            return Some(TransportEvent::Received {
+                pgn: self.rx.pgn,
+                source_address: self.rx.source_address,
+                destination_address: self.rx.destination_address,
+                data: self.rx.data.get(..self.rx.length).unwrap_or_default(),
+            });
+        }
+
+        if !connection {
+            self.rx.state = ReceiverState::Broadcast { since: now };
+        } else if sequence == self.rx.last_packet {
+            self.clear_to_send(now);
+        } else {
+            self.rx.state = ReceiverState::Connection {
+                since: now,
+                timeout: T1,
+            };
+        }
+        None
+    }
+
+    fn end_of_message_acknowledge(&mut self) {
+        let [size_low, size_high] = length_bytes(self.rx.length);
+        let [pgn_low, pgn_middle, pgn_high] = self.rx.pgn.to_bytes();
+        let frame = self.control_frame(
+            self.rx.source_address,
+            [
+                CONTROL_END_OF_MESSAGE_ACK,
+                size_low,
+                size_high,
+                self.rx.packets,
+                RESERVED,
+                pgn_low,
+                pgn_middle,
+                pgn_high,
+            ],
+        );
+        self.push_control(frame);
+    }
+
+    fn abort(&mut self, destination_address: u8, pgn: Pgn, reason: AbortReason) {
+        let [pgn_low, pgn_middle, pgn_high] = pgn.to_bytes();
+        let frame = self.control_frame(
+            destination_address,
+            [
+                CONTROL_ABORT,
+                reason.code(),
+                RESERVED,
+                RESERVED,
+                RESERVED,
+                pgn_low,
+                pgn_middle,
+                pgn_high,
+            ],
+        );
+        self.push_control(frame);
+    }
+
+    /// Next data packet of the message being sent, and its sequence number
+    fn data_frame(&mut self) -> (OwnedFrame, u8) {
+        let sequence = self.tx.next_packet;
+        let offset = usize::from(sequence.saturating_sub(1)) * PACKET_LENGTH;
+        let end = self.tx.length.min(offset + PACKET_LENGTH);
+        let packet = self.tx.data.get(offset..end).unwrap_or_default();
+
+        let mut data = [RESERVED; 8];
+        if let Some((first, rest)) = data.split_first_mut() {
+            *first = sequence;
+            if let Some(bytes) = rest.get_mut(..packet.len()) {
+                bytes.copy_from_slice(packet);
+            }
+        }
+        self.tx.next_packet = sequence.wrapping_add(1);
+
+        let destination_address = self.tx.destination_address;
+        let id = J1939Id::new(PRIORITY, Pgn::TP_DT, self.address, destination_address);
+        (OwnedFrame::from_bytes(id, data), sequence)
+    }
+
+    fn control_frame(&self, destination_address: u8, data: [u8; 8]) -> OwnedFrame {
+        let id = J1939Id::new(PRIORITY, Pgn::TP_CM, self.address, destination_address);
+        OwnedFrame::from_bytes(id, data)
+    }
+
+    fn push_control(&mut self, frame: OwnedFrame) {
+        if let Some(slot) = self.control.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(frame);
+        }
+    }
+
+    fn is_tx_session(&self, source_address: u8, pgn: Pgn) -> bool {
+        self.tx.destination_address == source_address && self.tx.pgn == pgn
+    }
+}
+
+fn packet_count(length: usize) -> u8 {
+    u8::try_from(length.div_ceil(PACKET_LENGTH)).unwrap_or(u8::MAX)
+}
+
+fn length_bytes(length: usize) -> [u8; 2] {
+    u16::try_from(length).unwrap_or(u16::MAX).to_le_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use embedded_can::Frame as _;
+
+    const PGN: Pgn = Pgn(0xFECA);
+    const TESTER: u8 = 0xF9;
+    const ECU: u8 = 0x00;
+
+    /// Message whose bytes hold the sequence number of their data packet, 0 past the 255th
+    fn message(length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|i| u8::try_from(i / PACKET_LENGTH + 1).unwrap_or_default())
+            .collect()
+    }
+
+    /// Run a session between two controller applications, the frames going both ways every
+    /// 10 ms, until the message is sent. The identifier of every frame is checked, and the
+    /// message received by `receiver` is returned.
+    fn session(
+        sender: &mut Transport,
+        receiver: &mut Transport,
+        destination: u8,
+        data: &[u8],
+    ) -> Vec<u8> {
+        sender.send(PGN, destination, data).unwrap();
+        let mut delivered = None;
+        let mut now = 0;
+
+        loop {
+            while let Some(frame) = sender.next_frame(now) {
+                let id = frame_id(&frame).unwrap();
+                assert_eq!(id.priority, PRIORITY);
+                assert!(id.pgn == Pgn::TP_CM || id.pgn == Pgn::TP_DT);
+                assert_eq!(id.source_address, sender.address());
+                assert_eq!(id.destination_address, destination);
+
+                match receiver.receive(&frame, now) {
+                    None => {}
+                    Some(TransportEvent::Received {
+                        pgn,
+                        source_address,
+                        destination_address,
+                        data,
+                    }) if pgn == PGN
+                        && source_address == sender.address()
+                        && destination_address == destination =>
+                    {
+                        delivered = Some(data.to_vec());
+                    }
+                    Some(event) => panic!("unexpected {event:?}"),
+                }
+            }
+
+            // only the connection management messages of a connection mode session
+            while let Some(frame) = receiver.next_frame(now) {
+                let id = frame_id(&frame).unwrap();
+                assert_eq!(id.pgn, Pgn::TP_CM);
+                assert_ne!(destination, GLOBAL_ADDRESS);
+                assert_eq!(id.destination_address, sender.address());
+                assert_eq!(sender.receive(&frame, now), None);
+            }
+
+            assert_eq!(receiver.poll(now), None);
+            match sender.poll(now) {
+                None => now += 10_000,
+                Some(TransportEvent::Sent) => return delivered.unwrap(),
+                Some(event) => panic!("unexpected {event:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut tester = Transport::new(TESTER);
+        let mut ecu = Transport::new(ECU);
+
+        let data = message(20);
+        tester.send(PGN, GLOBAL_ADDRESS, &data).unwrap();
+        let bam = tester.next_frame(0).unwrap();
+        assert_eq!(frame_id(&bam).unwrap().raw(), 0x1CEC_FFF9);
+        assert_eq!(bam.data(), &[32, 20, 0, 3, 0xFF, 0xCA, 0xFE, 0x00]);
+        assert_eq!(tester.next_frame(BAM_INTERVAL - 1), None);
+        let first = tester.next_frame(BAM_INTERVAL).unwrap();
+        assert_eq!(frame_id(&first).unwrap().raw(), 0x1CEB_FFF9);
+        assert_eq!(first.data(), &[1, 1, 1, 1, 1, 1, 1, 1]);
+        tester.next_frame(2 * BAM_INTERVAL).unwrap();
+        let last = tester.next_frame(3 * BAM_INTERVAL).unwrap();
+        assert_eq!(last.data(), &[3, 3, 3, 3, 3, 3, 3, 0xFF]);
+        assert_eq!(tester.poll(3 * BAM_INTERVAL), Some(TransportEvent::Sent));
+        assert!(!tester.is_sending());
+
+        let mut tester = Transport::new(TESTER);
+        assert_eq!(session(&mut tester, &mut ecu, GLOBAL_ADDRESS, &data), data);
+        let data = message(MAX_LENGTH);
+        assert_eq!(session(&mut tester, &mut ecu, GLOBAL_ADDRESS, &data), data);
+    }
+
+    #[test]
+    fn test_connection() {
+        let mut tester = Transport::new(TESTER);
+        let mut ecu = Transport::new(ECU);
+
+        for length in [9, 100, MAX_LENGTH] {
+            let data = message(length);
+            assert_eq!(session(&mut tester, &mut ecu, ECU, &data), data);
+        }
+
+        assert_eq!(
+            tester.send(PGN, ECU, &message(8)),
+            Err(TransportError::InvalidLength)
+        );
+        assert_eq!(
+            tester.send(PGN, ECU, &message(MAX_LENGTH + 1)),
+            Err(TransportError::InvalidLength)
+        );
+        tester.send(PGN, ECU, &message(9)).unwrap();
+        assert_eq!(
+            tester.send(PGN, ECU, &message(9)),
+            Err(TransportError::Busy)
+        );
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut tester = Transport::new(TESTER);
+        let mut other = Transport::new(0x80);
+        let mut ecu = Transport::new(ECU);
+
+        tester.send(PGN, ECU, &message(100)).unwrap();
+        ecu.receive(&tester.next_frame(0).unwrap(), 0);
+        let cts = ecu.next_frame(0).unwrap();
+        assert_eq!(cts.data(), &[17, 15, 1, 0xFF, 0xFF, 0xCA, 0xFE, 0x00]);
+
+        // a second connection is refused
+        other.send(PGN, ECU, &message(100)).unwrap();
+        ecu.receive(&other.next_frame(0).unwrap(), 0);
+        let abort = ecu.next_frame(0).unwrap();
+        assert_eq!(frame_id(&abort).unwrap().destination_address, 0x80);
+        assert_eq!(abort.data(), &[255, 1, 0xFF, 0xFF, 0xFF, 0xCA, 0xFE, 0x00]);
+        assert_eq!(other.receive(&abort, 0), None);
+        assert_eq!(
+            other.poll(0),
+            Some(TransportEvent::SendFailed(TransportError::Aborted(
+                AbortReason::AlreadyInSession
+            )))
+        );
+
+        // a lost packet aborts the connection
+        tester.receive(&cts, 0);
+        tester.next_frame(0).unwrap();
+        let second = tester.next_frame(0).unwrap();
+        assert_eq!(
+            ecu.receive(&second, 0),
+            Some(TransportEvent::ReceiveFailed(
+                TransportError::BadSequenceNumber
+            ))
+        );
+        let abort = ecu.next_frame(0).unwrap();
+        assert_eq!(abort.data().get(..2), Some(&[255, 7][..]));
+        tester.receive(&abort, 0);
+        assert_eq!(
+            tester.poll(0),
+            Some(TransportEvent::SendFailed(TransportError::Aborted(
+                AbortReason::BadSequenceNumber
+            )))
+        );
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let mut tester = Transport::new(TESTER);
+        let mut ecu = Transport::new(ECU);
+
+        // no CTS
+        tester.send(PGN, ECU, &message(100)).unwrap();
+        let rts = tester.next_frame(0).unwrap();
+        assert_eq!(tester.poll(T3), None);
+        assert_eq!(
+            tester.poll(T3 + 1),
+            Some(TransportEvent::SendFailed(TransportError::Timeout))
+        );
+        let abort = tester.next_frame(T3 + 1).unwrap();
+        assert_eq!(abort.data().get(..2), Some(&[255, 3][..]));
+
+        // no data packet after the CTS
+        ecu.receive(&rts, 0);
+        assert!(ecu.is_receiving());
+        assert_eq!(ecu.poll(T2), None);
+        assert_eq!(
+            ecu.poll(T2 + 1),
+            Some(TransportEvent::ReceiveFailed(TransportError::Timeout))
+        );
+
+        // a CTS of 0 packets holds the connection open
+        tester.send(PGN, ECU, &message(100)).unwrap();
+        tester.next_frame(0).unwrap();
+        let hold = OwnedFrame::from_bytes(
+            J1939Id::new(PRIORITY, Pgn::TP_CM, ECU, TESTER),
+            [17, 0, 1, 0xFF, 0xFF, 0xCA, 0xFE, 0x00],
+        );
+        tester.receive(&hold, 1000);
+        assert_eq!(tester.next_frame(1000), None);
+        assert_eq!(tester.poll(1000 + T4), None);
+        assert_eq!(
+            tester.poll(1001 + T4),
+            Some(TransportEvent::SendFailed(TransportError::Timeout))
+        );
+
+        // broadcast interrupted
+        tester.send(PGN, GLOBAL_ADDRESS, &message(100)).unwrap();
+        ecu.receive(&tester.next_frame(0).unwrap(), 0);
+        ecu.receive(&tester.next_frame(BAM_INTERVAL).unwrap(), BAM_INTERVAL);
+        assert_eq!(ecu.poll(BAM_INTERVAL + T1), None);
+        assert_eq!(
+            ecu.poll(BAM_INTERVAL + T1 + 1),
+            Some(TransportEvent::ReceiveFailed(TransportError::Timeout))
+        );
+    }
+}
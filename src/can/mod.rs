@@ -1,4 +1,11 @@
 //! Controller Area Network (CAN) bus
+//!
+//! # Protocol layers
+//!
+//! The [`isotp`], [`j1939`] and [`canopen`] layers are state machines which do not own the
+//! node: the received frames are given to them, they return the frames to transmit, and
+//! they are polled periodically to check their timeouts. Time is supplied by the caller as
+//! a free running microsecond counter wrapping around.
 
 // TODO Remove this once the module is more complete
 #![allow(dead_code)]
@@ -6,14 +13,21 @@
 mod baud_rate;
 mod can_module;
 mod can_node;
+pub mod canopen;
 mod frame;
 mod internals;
+pub mod isotp;
+pub mod j1939;
 pub mod msg;
 pub mod pin_map;
+mod ram_layout;
 mod reg;
+mod rx_queue;
 
 pub use baud_rate::*;
 pub use can_module::*;
 pub use can_node::*;
-pub use frame::Frame;
+pub use frame::{Frame, OwnedFrame};
 pub use msg::{FrameMode, MessageId};
+pub use ram_layout::*;
+pub use rx_queue::{RxConsumer, RxProducer, RxQueue};
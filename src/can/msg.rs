@@ -40,7 +40,27 @@ impl From<embedded_can::ExtendedId> for MessageId {
     }
 }
 
-#[derive(PartialEq, Debug, Default, Copy, Clone)]
+impl TryFrom<MessageId> for embedded_can::Id {
+    type Error = ();
+
+    fn try_from(id: MessageId) -> Result<Self, Self::Error> {
+        match id.length {
+            MessageIdLength::Standard => u16::try_from(id.data)
+                .ok()
+                .and_then(embedded_can::StandardId::new)
+                .map(embedded_can::Id::Standard)
+                .ok_or(()),
+            MessageIdLength::Extended => embedded_can::ExtendedId::new(id.data)
+                .map(embedded_can::Id::Extended)
+                .ok_or(()),
+            MessageIdLength::Both => Err(()),
+        }
+    }
+}
+
+/// Format of a frame. Variants are ordered, a node configured with a mode can
+/// transmit frames of this mode and of the previous ones
+#[derive(PartialEq, PartialOrd, Debug, Default, Copy, Clone)]
 pub enum FrameMode {
     #[default]
     Standard,
@@ -48,6 +68,15 @@ pub enum FrameMode {
     FdLongAndFast,
 }
 
+/// Timestamp of a received frame or Tx event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp {
+    /// Value of the 16 bit timestamp counter
+    pub raw: u16,
+    /// Raw value converted to nanoseconds, only known for the internal timestamp source
+    pub nanos: Option<u64>,
+}
+
 #[derive(Debug)]
 pub struct RxMessage {
     pub buffer_id: RxBufferId,
@@ -55,6 +84,12 @@ pub struct RxMessage {
     pub data_length_code: DataLenghtCode,
     pub from: ReadFrom,
     pub frame_mode: FrameMode,
+    /// The frame is a remote frame, it carries no data
+    pub remote_transmit_request: bool,
+    /// The transmitter was error passive, only set for CAN FD frames
+    pub error_state_indicator: bool,
+    /// Timestamp captured at the start of frame reception
+    pub timestamp: Timestamp,
 }
 
 #[derive(Debug)]
@@ -87,6 +122,30 @@ impl Default for TxMessage {
     }
 }
 
+/// Type of a Tx Event FIFO element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TxEventType {
+    /// The frame has been transmitted
+    Transmission,
+    /// The frame has been transmitted in spite of a cancellation request
+    TransmissionInSpiteOfCancellation,
+}
+
+/// An element read from the Tx Event FIFO
+#[derive(Debug, Clone, Copy)]
+pub struct TxEvent {
+    pub id: MessageId,
+    /// The message marker given when the frame was transmitted
+    pub marker: u8,
+    pub data_length_code: DataLenghtCode,
+    pub frame_mode: FrameMode,
+    pub remote_transmit_request: bool,
+    pub error_state_indicator: bool,
+    pub event_type: TxEventType,
+    /// Timestamp captured at the start of frame transmission
+    pub timestamp: Timestamp,
+}
+
 #[derive(Clone, Copy)]
 pub enum FrameType {
     Receive,
@@ -119,8 +178,9 @@ impl From<TxBufferId> for u8 {
     }
 }
 
+/// Index of a dedicated Rx buffer, the first one by default
 #[repr(transparent)]
-#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug, Default)]
 pub struct RxBufferId(u8);
 
 impl RxBufferId {
@@ -148,6 +208,42 @@ impl From<RxBufferId> for u8 {
     }
 }
 
+/// Dedicated Rx buffers holding a message not read yet, taken at once from the new data
+/// registers. Iterates the buffers in index order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RxBuffersNewData(u64);
+
+impl RxBuffersNewData {
+    pub(crate) fn new(ndat1: u32, ndat2: u32) -> Self {
+        Self(u64::from(ndat1) | (u64::from(ndat2) << 32))
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    pub fn contains(&self, id: RxBufferId) -> bool {
+        self.0 & (1 << id.0) != 0
+    }
+}
+
+impl Iterator for RxBuffersNewData {
+    type Item = RxBufferId;
+
+    fn next(&mut self) -> Option<RxBufferId> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let index = self.0.trailing_zeros();
+        // clear the lowest set bit
+        self.0 &= self.0 - 1;
+        RxBufferId::new(u8::try_from(index).ok()?)
+    }
+}
+
 impl From<RxBufferId> for u16 {
     fn from(value: RxBufferId) -> Self {
         value.0.into()
@@ -179,6 +275,21 @@ pub enum FilterElementConfiguration {
     StoreInRxBuffer,
 }
 
+impl From<FilterElementConfiguration> for u8 {
+    fn from(value: FilterElementConfiguration) -> Self {
+        match value {
+            FilterElementConfiguration::Disable => 0,
+            FilterElementConfiguration::StoreInRxFifo0 => 1,
+            FilterElementConfiguration::StoreInRxFifo1 => 2,
+            FilterElementConfiguration::RejectId => 3,
+            FilterElementConfiguration::SetPriority => 4,
+            FilterElementConfiguration::SetPriorityAndStoreInFifo0 => 5,
+            FilterElementConfiguration::SetPriorityAndStoreInFifo1 => 6,
+            FilterElementConfiguration::StoreInRxBuffer => 7,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum FilterType {
     Range,
@@ -187,12 +298,66 @@ pub enum FilterType {
     None,
 }
 
+/// Acceptance filter element
+///
+/// `number` is the index of the element in the standard or extended filter list,
+/// depending on `id_length`. For [`FilterType::Classic`] filters `id1` is the ID and `id2`
+/// the mask, for [`FilterType::Range`] filters `id1..=id2` is the accepted range and for
+/// [`FilterType::Dualid`] filters both IDs are accepted. When storing into a dedicated
+/// Rx buffer, `id1` is the ID to match and `rx_buffer_offset` the target buffer.
 #[derive(Clone, Copy)]
 pub struct Filter {
     pub number: u8,
     pub element_configuration: FilterElementConfiguration,
     pub typ: FilterType,
+    pub id_length: MessageIdLength,
     pub id1: u32,
     pub id2: u32,
     pub rx_buffer_offset: RxBufferId,
 }
+
+/// What to do with frames not matching any filter element
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum NonMatchingFrames {
+    #[default]
+    AcceptInRxFifo0,
+    AcceptInRxFifo1,
+    Reject,
+}
+
+impl From<NonMatchingFrames> for u8 {
+    fn from(value: NonMatchingFrames) -> Self {
+        match value {
+            NonMatchingFrames::AcceptInRxFifo0 => 0,
+            NonMatchingFrames::AcceptInRxFifo1 => 1,
+            NonMatchingFrames::Reject => 2,
+        }
+    }
+}
+
+/// Global filter configuration, applied to frames not matching any filter element
+/// and to remote frames
+#[derive(Clone, Copy, Default)]
+pub struct GlobalFilterConfig {
+    pub non_matching_standard: NonMatchingFrames,
+    pub non_matching_extended: NonMatchingFrames,
+    pub reject_remote_standard: bool,
+    pub reject_remote_extended: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rx_buffers_new_data() {
+        let new_data = RxBuffersNewData::new(0b1001, 1 << 31);
+        assert!(new_data.contains(RxBufferId(3)));
+        assert!(!new_data.contains(RxBufferId(1)));
+
+        let ids: Vec<u8> = new_data.map(u8::from).collect();
+        assert_eq!(ids, [0, 3, 63]);
+
+        assert_eq!(RxBuffersNewData::new(0, 0).next(), None);
+    }
+}
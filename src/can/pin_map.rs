@@ -1,82 +1,88 @@
-use crate::can::can_node::{OutputIdx, PortNumber, RxSel, RxdIn, TxdOut};
-use crate::can::{Module0, Module1};
-use crate::can::{Node0, Node1, Node2, Node3};
-use PortNumber as P;
+//! Connection of the gpio pins to the CAN nodes.
+//!
+//! The pins which can be connected to a node are the variants of [`crate::gpio::alt::can0`]
+//! and [`crate::gpio::alt::can1`] enums, see [`crate::gpio::alt::CanCommon`]. This module
+//! maps each receive pin to the input of the node RXD multiplexer it is connected to.
 
-// TODO It's not safe to let these be public, because the resource behind them is the same (GPIO pin) of the gpio module.
+use crate::can::can_node::RxSel;
+use crate::gpio::alt::{can0, can1};
+use crate::gpio::{PinPull, ReadPin};
 
-pub const PIN_RX_0_0_P02_1: RxdIn<Module0, Node0> = RxdIn::new(P::_02, 1, RxSel::_A);
-pub const PIN_RX_0_0_P20_7: RxdIn<Module0, Node0> = RxdIn::new(P::_20, 7, RxSel::_B);
-pub const PIN_RX_0_0_P12_0: RxdIn<Module0, Node0> = RxdIn::new(P::_12, 0, RxSel::_C);
-pub const PIN_RX_0_0_P33_12: RxdIn<Module0, Node0> = RxdIn::new(P::_33, 12, RxSel::_D);
-pub const PIN_RX_0_0_P33_7: RxdIn<Module0, Node0> = RxdIn::new(P::_33, 7, RxSel::_E);
-pub const PIN_RX_0_0_P34_2: RxdIn<Module0, Node0> = RxdIn::new(P::_34, 2, RxSel::_G);
-pub const PIN_RX_0_1_P15_3: RxdIn<Module0, Node1> = RxdIn::new(P::_15, 3, RxSel::_A);
-pub const PIN_RX_0_1_P14_1: RxdIn<Module0, Node1> = RxdIn::new(P::_14, 1, RxSel::_B);
-pub const PIN_RX_0_1_P01_4: RxdIn<Module0, Node1> = RxdIn::new(P::_01, 4, RxSel::_C);
-pub const PIN_RX_0_1_P33_10: RxdIn<Module0, Node1> = RxdIn::new(P::_33, 10, RxSel::_D);
-pub const PIN_RX_0_1_P02_10: RxdIn<Module0, Node1> = RxdIn::new(P::_02, 10, RxSel::_E);
-pub const PIN_RX_0_2_P15_1: RxdIn<Module0, Node2> = RxdIn::new(P::_15, 1, RxSel::_A);
-pub const PIN_RX_0_2_P02_3: RxdIn<Module0, Node2> = RxdIn::new(P::_02, 3, RxSel::_B);
-pub const PIN_RX_0_2_P32_6: RxdIn<Module0, Node2> = RxdIn::new(P::_32, 6, RxSel::_C);
-pub const PIN_RX_0_2_P14_8: RxdIn<Module0, Node2> = RxdIn::new(P::_14, 8, RxSel::_D);
-pub const PIN_RX_0_2_P10_2: RxdIn<Module0, Node2> = RxdIn::new(P::_10, 2, RxSel::_E);
-pub const PIN_RX_0_3_P00_3: RxdIn<Module0, Node3> = RxdIn::new(P::_00, 3, RxSel::_A);
-pub const PIN_RX_0_3_P32_2: RxdIn<Module0, Node3> = RxdIn::new(P::_32, 2, RxSel::_B);
-pub const PIN_RX_0_3_P20_0: RxdIn<Module0, Node3> = RxdIn::new(P::_20, 0, RxSel::_C);
-pub const PIN_RX_0_3_P11_10: RxdIn<Module0, Node3> = RxdIn::new(P::_11, 10, RxSel::_D);
-pub const PIN_RX_0_3_P20_9: RxdIn<Module0, Node3> = RxdIn::new(P::_20, 9, RxSel::_E);
-pub const PIN_RX_1_0_P00_1: RxdIn<Module1, Node0> = RxdIn::new(P::_00, 1, RxSel::_A);
-pub const PIN_RX_1_0_P14_7: RxdIn<Module1, Node0> = RxdIn::new(P::_14, 7, RxSel::_B);
-pub const PIN_RX_1_0_P23_0: RxdIn<Module1, Node0> = RxdIn::new(P::_23, 0, RxSel::_C);
-pub const PIN_RX_1_0_P13_1: RxdIn<Module1, Node0> = RxdIn::new(P::_13, 1, RxSel::_D);
-pub const PIN_RX_1_1_P02_4: RxdIn<Module1, Node1> = RxdIn::new(P::_02, 4, RxSel::_A);
-pub const PIN_RX_1_1_P00_5: RxdIn<Module1, Node1> = RxdIn::new(P::_00, 5, RxSel::_B);
-pub const PIN_RX_1_1_P23_7: RxdIn<Module1, Node1> = RxdIn::new(P::_23, 7, RxSel::_C);
-pub const PIN_RX_1_1_P11_7: RxdIn<Module1, Node1> = RxdIn::new(P::_11, 7, RxSel::_D);
-pub const PIN_RX_1_2_P20_6: RxdIn<Module1, Node2> = RxdIn::new(P::_20, 6, RxSel::_A);
-pub const PIN_RX_1_2_P10_8: RxdIn<Module1, Node2> = RxdIn::new(P::_10, 8, RxSel::_B);
-pub const PIN_RX_1_2_P23_3: RxdIn<Module1, Node2> = RxdIn::new(P::_23, 3, RxSel::_C);
-pub const PIN_RX_1_2_P11_8: RxdIn<Module1, Node2> = RxdIn::new(P::_11, 8, RxSel::_D);
-pub const PIN_RX_1_3_P14_7: RxdIn<Module1, Node3> = RxdIn::new(P::_14, 7, RxSel::_A);
-pub const PIN_RX_1_3_P33_5: RxdIn<Module1, Node3> = RxdIn::new(P::_33, 5, RxSel::_B);
-pub const PIN_RX_1_3_P22_5: RxdIn<Module1, Node3> = RxdIn::new(P::_22, 5, RxSel::_C);
-pub const PIN_RX_1_3_P11_13: RxdIn<Module1, Node3> = RxdIn::new(P::_11, 13, RxSel::_D);
+/// Receive pin of a node
+pub trait RxdIn: ReadPin + PinPull {
+    /// Input of the node RXD multiplexer connected to the pin, `None` without pin
+    fn select(&self) -> Option<RxSel>;
+}
 
-pub const PIN_TX_0_0_P02_0: TxdOut<Module0, Node0> = TxdOut::new(P::_02, 0, OutputIdx::ALT5);
-pub const PIN_TX_0_0_P12_1: TxdOut<Module0, Node0> = TxdOut::new(P::_12, 1, OutputIdx::ALT5);
-pub const PIN_TX_0_0_P20_8: TxdOut<Module0, Node0> = TxdOut::new(P::_20, 8, OutputIdx::ALT5);
-pub const PIN_TX_0_0_P33_13: TxdOut<Module0, Node0> = TxdOut::new(P::_33, 13, OutputIdx::ALT5);
-pub const PIN_TX_0_0_P33_8: TxdOut<Module0, Node0> = TxdOut::new(P::_33, 8, OutputIdx::ALT5);
-pub const PIN_TX_0_0_P34_1: TxdOut<Module0, Node0> = TxdOut::new(P::_34, 1, OutputIdx::ALT4);
-pub const PIN_TX_0_1_P01_3: TxdOut<Module0, Node1> = TxdOut::new(P::_01, 3, OutputIdx::ALT5);
-pub const PIN_TX_0_1_P02_9: TxdOut<Module0, Node1> = TxdOut::new(P::_02, 9, OutputIdx::ALT5);
-pub const PIN_TX_0_1_P14_0: TxdOut<Module0, Node1> = TxdOut::new(P::_14, 0, OutputIdx::ALT5);
-pub const PIN_TX_0_1_P15_2: TxdOut<Module0, Node1> = TxdOut::new(P::_15, 2, OutputIdx::ALT5);
-pub const PIN_TX_0_1_P33_9: TxdOut<Module0, Node1> = TxdOut::new(P::_33, 9, OutputIdx::ALT5);
-pub const PIN_TX_0_2_P02_2: TxdOut<Module0, Node2> = TxdOut::new(P::_02, 2, OutputIdx::ALT5);
-pub const PIN_TX_0_2_P10_3: TxdOut<Module0, Node2> = TxdOut::new(P::_10, 3, OutputIdx::ALT6);
-pub const PIN_TX_0_2_P14_10: TxdOut<Module0, Node2> = TxdOut::new(P::_14, 10, OutputIdx::ALT5);
-pub const PIN_TX_0_2_P15_0: TxdOut<Module0, Node2> = TxdOut::new(P::_15, 0, OutputIdx::ALT5);
-pub const PIN_TX_0_2_P32_5: TxdOut<Module0, Node2> = TxdOut::new(P::_32, 5, OutputIdx::ALT6);
-pub const PIN_TX_0_3_P00_2: TxdOut<Module0, Node3> = TxdOut::new(P::_00, 2, OutputIdx::ALT5);
-pub const PIN_TX_0_3_P11_12: TxdOut<Module0, Node3> = TxdOut::new(P::_11, 12, OutputIdx::ALT5);
-pub const PIN_TX_0_3_P20_10: TxdOut<Module0, Node3> = TxdOut::new(P::_20, 10, OutputIdx::ALT5);
-pub const PIN_TX_0_3_P20_3: TxdOut<Module0, Node3> = TxdOut::new(P::_20, 3, OutputIdx::ALT5);
-pub const PIN_TX_0_3_P32_3: TxdOut<Module0, Node3> = TxdOut::new(P::_32, 3, OutputIdx::ALT5);
-pub const PIN_TX_1_0_P00_0: TxdOut<Module1, Node0> = TxdOut::new(P::_00, 0, OutputIdx::ALT5);
-pub const PIN_TX_1_0_P13_0: TxdOut<Module1, Node0> = TxdOut::new(P::_13, 0, OutputIdx::ALT7);
-pub const PIN_TX_1_0_P14_9: TxdOut<Module1, Node0> = TxdOut::new(P::_14, 9, OutputIdx::ALT4);
-pub const PIN_TX_1_0_P23_1: TxdOut<Module1, Node0> = TxdOut::new(P::_23, 1, OutputIdx::ALT5);
-pub const PIN_TX_1_1_P00_4: TxdOut<Module1, Node1> = TxdOut::new(P::_00, 4, OutputIdx::ALT3);
-pub const PIN_TX_1_1_P02_5: TxdOut<Module1, Node1> = TxdOut::new(P::_02, 5, OutputIdx::ALT2);
-pub const PIN_TX_1_1_P11_0: TxdOut<Module1, Node1> = TxdOut::new(P::_11, 0, OutputIdx::ALT5);
-pub const PIN_TX_1_1_P23_6: TxdOut<Module1, Node1> = TxdOut::new(P::_23, 6, OutputIdx::ALT5);
-pub const PIN_TX_1_2_P10_7: TxdOut<Module1, Node2> = TxdOut::new(P::_10, 7, OutputIdx::ALT6);
-pub const PIN_TX_1_2_P11_1: TxdOut<Module1, Node2> = TxdOut::new(P::_11, 1, OutputIdx::ALT5);
-pub const PIN_TX_1_2_P20_7: TxdOut<Module1, Node2> = TxdOut::new(P::_20, 7, OutputIdx::ALT5);
-pub const PIN_TX_1_2_P23_2: TxdOut<Module1, Node2> = TxdOut::new(P::_23, 2, OutputIdx::ALT5);
-pub const PIN_TX_1_3_P11_4: TxdOut<Module1, Node3> = TxdOut::new(P::_11, 4, OutputIdx::ALT5);
-pub const PIN_TX_1_3_P14_6: TxdOut<Module1, Node3> = TxdOut::new(P::_14, 6, OutputIdx::ALT4);
-pub const PIN_TX_1_3_P22_4: TxdOut<Module1, Node3> = TxdOut::new(P::_22, 4, OutputIdx::ALT6);
-pub const PIN_TX_1_3_P33_4: TxdOut<Module1, Node3> = TxdOut::new(P::_33, 4, OutputIdx::ALT7);
+macro_rules! rxd_in {
+    ($($Rx:ty: [$($PX:ident => $select:ident,)+],)+) => {
+        $(
+            impl RxdIn for $Rx {
+                fn select(&self) -> Option<RxSel> {
+                    match self {
+                        Self::None(_) => None,
+                        $(
+                            Self::$PX(_) => Some(RxSel::$select),
+                        )+
+                    }
+                }
+            }
+        )+
+    };
+}
+
+rxd_in! {
+    can0::Rx0: [
+        P02_1 => _A,
+        P20_7 => _B,
+        P12_0 => _C,
+        P33_12 => _D,
+        P33_7 => _E,
+        P34_2 => _G,
+    ],
+    can0::Rx1: [
+        P15_3 => _A,
+        P14_1 => _B,
+        P01_4 => _C,
+        P33_10 => _D,
+        P02_10 => _E,
+    ],
+    can0::Rx2: [
+        P15_1 => _A,
+        P02_3 => _B,
+        P32_6 => _C,
+        P14_8 => _D,
+        P10_2 => _E,
+    ],
+    can0::Rx3: [
+        P00_3 => _A,
+        P32_2 => _B,
+        P20_0 => _C,
+        P11_10 => _D,
+        P20_9 => _E,
+    ],
+    can1::Rx0: [
+        P00_1 => _A,
+        P14_7 => _B,
+        P23_0 => _C,
+        P13_1 => _D,
+    ],
+    can1::Rx1: [
+        P02_4 => _A,
+        P00_5 => _B,
+        P23_7 => _C,
+        P11_7 => _D,
+    ],
+    can1::Rx2: [
+        P20_6 => _A,
+        P10_8 => _B,
+        P23_3 => _C,
+        P11_8 => _D,
+    ],
+    can1::Rx3: [
+        P14_7 => _A,
+        P33_5 => _B,
+        P22_5 => _C,
+        P11_13 => _D,
+    ],
+}
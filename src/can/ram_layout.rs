@@ -0,0 +1,401 @@
+//! Message RAM layout planner
+//!
+//! Allocates the filter lists, Rx FIFOs, Rx buffers, Tx event FIFO and Tx
+//! buffers of all nodes of a CAN module in the module message RAM.
+
+#![allow(clippy::module_name_repetitions)]
+#![allow(clippy::cast_possible_truncation)]
+
+use super::{DataFieldSize, NodeId, RxConfig, TxConfig};
+
+/// Size of the message RAM of a CAN module in bytes
+pub const MESSAGE_RAM_SIZE: u32 = 0x8000;
+
+const NODE_COUNT: usize = 4;
+
+const STANDARD_FILTER_ELEMENT_SIZE: u32 = 4;
+const EXTENDED_FILTER_ELEMENT_SIZE: u32 = 8;
+const TX_EVENT_ELEMENT_SIZE: u32 = 8;
+const ELEMENT_HEADER_SIZE: u32 = 8;
+
+const STANDARD_FILTER_MAX: u8 = 128;
+const EXTENDED_FILTER_MAX: u8 = 64;
+const RX_FIFO_MAX: u8 = 64;
+const RX_BUFFER_MAX: u8 = 64;
+const TX_EVENT_FIFO_MAX: u8 = 32;
+const TX_BUFFER_MAX: u8 = 32;
+
+/// Number and size of the message RAM elements required by a node
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NodeRamConfig {
+    pub standard_filters: u8,
+    pub extended_filters: u8,
+    pub rx_fifo0_size: u8,
+    pub rx_fifo0_data_field_size: DataFieldSize,
+    pub rx_fifo1_size: u8,
+    pub rx_fifo1_data_field_size: DataFieldSize,
+    pub rx_buffers: u8,
+    pub rx_buffer_data_field_size: DataFieldSize,
+    pub tx_event_fifo_size: u8,
+    pub dedicated_tx_buffers: u8,
+    pub tx_fifo_queue_size: u8,
+    pub tx_buffer_data_field_size: DataFieldSize,
+}
+
+impl NodeRamConfig {
+    fn validate(&self) -> Result<(), RamLayoutError> {
+        let tx_buffers = u16::from(self.dedicated_tx_buffers) + u16::from(self.tx_fifo_queue_size);
+
+        if self.standard_filters > STANDARD_FILTER_MAX
+            || self.extended_filters > EXTENDED_FILTER_MAX
+            || self.rx_fifo0_size > RX_FIFO_MAX
+            || self.rx_fifo1_size > RX_FIFO_MAX
+            || self.rx_buffers > RX_BUFFER_MAX
+            || self.tx_event_fifo_size > TX_EVENT_FIFO_MAX
+            || tx_buffers > u16::from(TX_BUFFER_MAX)
+        {
+            return Err(RamLayoutError::TooManyElements);
+        }
+
+        Ok(())
+    }
+
+    fn size(&self) -> u32 {
+        let tx_buffers = u32::from(self.dedicated_tx_buffers) + u32::from(self.tx_fifo_queue_size);
+
+        u32::from(self.standard_filters) * STANDARD_FILTER_ELEMENT_SIZE
+            + u32::from(self.extended_filters) * EXTENDED_FILTER_ELEMENT_SIZE
+            + u32::from(self.rx_fifo0_size) * element_size(self.rx_fifo0_data_field_size)
+            + u32::from(self.rx_fifo1_size) * element_size(self.rx_fifo1_data_field_size)
+            + u32::from(self.rx_buffers) * element_size(self.rx_buffer_data_field_size)
+            + u32::from(self.tx_event_fifo_size) * TX_EVENT_ELEMENT_SIZE
+            + tx_buffers * element_size(self.tx_buffer_data_field_size)
+    }
+}
+
+fn element_size(data_field_size: DataFieldSize) -> u32 {
+    ELEMENT_HEADER_SIZE + data_field_size.to_length()
+}
+
+/// Addresses, relative to the module message RAM, of the sections of a node
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeRamLayout {
+    config_start: u16,
+    pub standard_filter_list_start_address: u16,
+    pub extended_filter_list_start_address: u16,
+    pub rx_fifo0_start_address: u16,
+    pub rx_fifo1_start_address: u16,
+    pub rx_buffers_start_address: u16,
+    pub tx_event_fifo_start_address: u16,
+    pub tx_buffers_start_address: u16,
+    /// First address after the sections of the node
+    pub end_address: u16,
+}
+
+impl NodeRamLayout {
+    fn new(start: u32, config: &NodeRamConfig) -> Result<Self, RamLayoutError> {
+        let end = start + config.size();
+        if end > MESSAGE_RAM_SIZE {
+            return Err(RamLayoutError::Overflow);
+        }
+
+        let mut next = start;
+        let mut allocate = |count: u8, size: u32| {
+            let address = next;
+            next += u32::from(count) * size;
+            // Addresses are below MESSAGE_RAM_SIZE, checked above
+            address as u16
+        };
+
+        Ok(Self {
+            config_start: start as u16,
+            standard_filter_list_start_address: allocate(
+                config.standard_filters,
+                STANDARD_FILTER_ELEMENT_SIZE,
+            ),
+            extended_filter_list_start_address: allocate(
+                config.extended_filters,
+                EXTENDED_FILTER_ELEMENT_SIZE,
+            ),
+            rx_fifo0_start_address: allocate(
+                config.rx_fifo0_size,
+                element_size(config.rx_fifo0_data_field_size),
+            ),
+            rx_fifo1_start_address: allocate(
+                config.rx_fifo1_size,
+                element_size(config.rx_fifo1_data_field_size),
+            ),
+            rx_buffers_start_address: allocate(
+                config.rx_buffers,
+                element_size(config.rx_buffer_data_field_size),
+            ),
+            tx_event_fifo_start_address: allocate(config.tx_event_fifo_size, TX_EVENT_ELEMENT_SIZE),
+            tx_buffers_start_address: allocate(
+                config.dedicated_tx_buffers + config.tx_fifo_queue_size,
+                element_size(config.tx_buffer_data_field_size),
+            ),
+            end_address: end as u16,
+        })
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.config_start < other.end_address && other.config_start < self.end_address
+    }
+}
+
+/// Message RAM layout of the nodes of a CAN module, see [`MessageRamLayout::build`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModuleRamLayout {
+    nodes: [Option<(NodeRamConfig, NodeRamLayout)>; NODE_COUNT],
+}
+
+impl ModuleRamLayout {
+    /// Layout of a node, `None` if the node has not been added to the builder
+    #[must_use]
+    pub fn node(&self, node_id: &impl NodeId) -> Option<NodeRamLayout> {
+        self.nodes
+            .get(node_id.as_index())
+            .copied()
+            .flatten()
+            .map(|(_, layout)| layout)
+    }
+
+    /// Tx configuration with the element counts, data field size and addresses of a node
+    #[must_use]
+    pub fn tx_config(&self, node_id: &impl NodeId, config: TxConfig) -> Option<TxConfig> {
+        let (ram, layout) = self.nodes.get(node_id.as_index()).copied().flatten()?;
+        Some(TxConfig {
+            dedicated_tx_buffers_number: ram.dedicated_tx_buffers,
+            fifo_queue_size: ram.tx_fifo_queue_size,
+            buffer_data_field_size: ram.tx_buffer_data_field_size,
+            event_fifo_size: ram.tx_event_fifo_size,
+            tx_event_fifo_start_address: layout.tx_event_fifo_start_address,
+            tx_buffers_start_address: layout.tx_buffers_start_address,
+            ..config
+        })
+    }
+
+    /// Rx configuration with the element counts, data field sizes and addresses of a node
+    #[must_use]
+    pub fn rx_config(&self, node_id: &impl NodeId, config: RxConfig) -> Option<RxConfig> {
+        let (ram, layout) = self.nodes.get(node_id.as_index()).copied().flatten()?;
+        Some(RxConfig {
+            buffer_data_field_size: ram.rx_buffer_data_field_size,
+            fifo0_data_field_size: ram.rx_fifo0_data_field_size,
+            fifo1_data_field_size: ram.rx_fifo1_data_field_size,
+            fifo0_size: ram.rx_fifo0_size,
+            fifo1_size: ram.rx_fifo1_size,
+            rx_fifo0_start_address: layout.rx_fifo0_start_address,
+            rx_fifo1_start_address: layout.rx_fifo1_start_address,
+            rx_buffers_start_address: layout.rx_buffers_start_address,
+            standard_filter_list_start_address: layout.standard_filter_list_start_address,
+            extended_filter_list_start_address: layout.extended_filter_list_start_address,
+            ..config
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RamLayoutError {
+    /// A section has more elements than supported by the hardware
+    TooManyElements,
+    /// The sections do not fit in the message RAM
+    Overflow,
+    /// The sections of two nodes overlap
+    Overlap,
+    /// A start address is not word aligned
+    Misaligned,
+}
+
+/// Builder of the message RAM layout of all nodes of a CAN module.
+///
+/// Nodes added with [`MessageRamLayout::node`] are placed right after the previously
+/// added node, nodes added with [`MessageRamLayout::node_at`] at the given address.
+#[derive(Clone, Copy, Default)]
+pub struct MessageRamLayout {
+    nodes: [Option<(Option<u16>, NodeRamConfig)>; NODE_COUNT],
+    order: [usize; NODE_COUNT],
+    count: usize,
+}
+
+impl MessageRamLayout {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate the sections of a node after the previously added node
+    #[must_use]
+    pub fn node(self, node_id: &impl NodeId, config: NodeRamConfig) -> Self {
+        self.add(node_id.as_index(), None, config)
+    }
+
+    /// Allocate the sections of a node starting at the given address
+    #[must_use]
+    pub fn node_at(self, node_id: &impl NodeId, start_address: u16, config: NodeRamConfig) -> Self {
+        self.add(node_id.as_index(), Some(start_address), config)
+    }
+
+    fn add(mut self, index: usize, start: Option<u16>, config: NodeRamConfig) -> Self {
+        if let Some(slot) = self.nodes.get_mut(index) {
+            if slot.is_none() {
+                if let Some(order) = self.order.get_mut(self.count) {
+                    *order = index;
+                    self.count += 1;
+                }
+            }
+            *slot = Some((start, config));
+        }
+        self
+    }
+
+    /// Compute the addresses of all nodes, checking the result fits in the message RAM
+    pub fn build(&self) -> Result<ModuleRamLayout, RamLayoutError> {
+        let mut nodes: [Option<(NodeRamConfig, NodeRamLayout)>; NODE_COUNT] = [None; NODE_COUNT];
+        let mut next = 0u32;
+
+        for &index in self.order.get(..self.count).unwrap_or_default() {
+            let Some(Some((start, config))) = self.nodes.get(index) else {
+                continue;
+            };
+
+            config.validate()?;
+
+            let start = start.map_or(next, u32::from);
+            if start % 4 != 0 {
+                return Err(RamLayoutError::Misaligned);
+            }
+
+            let layout = NodeRamLayout::new(start, config)?;
+            if nodes
+                .iter()
+                .flatten()
+                .any(|(_, other)| other.overlaps(&layout))
+            {
+                return Err(RamLayoutError::Overlap);
+            }
+
+            next = u32::from(layout.end_address);
+            if let Some(slot) = nodes.get_mut(index) {
+                *slot = Some((*config, layout));
+            }
+        }
+
+        Ok(ModuleRamLayout { nodes })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::can::{Node0, Node1, Node2};
+
+    fn config() -> NodeRamConfig {
+        NodeRamConfig {
+            standard_filters: 2,
+            extended_filters: 1,
+            rx_fifo0_size: 4,
+            rx_fifo0_data_field_size: DataFieldSize::_8,
+            rx_buffers: 2,
+            rx_buffer_data_field_size: DataFieldSize::_64,
+            tx_event_fifo_size: 3,
+            dedicated_tx_buffers: 2,
+            tx_fifo_queue_size: 2,
+            tx_buffer_data_field_size: DataFieldSize::_8,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sequential_layout() {
+        let layout = MessageRamLayout::new()
+            .node(&Node0, config())
+            .node(&Node1, config())
+            .build()
+            .unwrap();
+
+        let node0 = layout.node(&Node0).unwrap();
+        assert_eq!(node0.standard_filter_list_start_address, 0x00);
+        assert_eq!(node0.extended_filter_list_start_address, 0x08);
+        assert_eq!(node0.rx_fifo0_start_address, 0x10);
+        assert_eq!(node0.rx_fifo1_start_address, 0x50);
+        assert_eq!(node0.rx_buffers_start_address, 0x50);
+        assert_eq!(node0.tx_event_fifo_start_address, 0xE0);
+        assert_eq!(node0.tx_buffers_start_address, 0xF8);
+        assert_eq!(node0.end_address, 0x138);
+
+        let node1 = layout.node(&Node1).unwrap();
+        assert_eq!(node1.standard_filter_list_start_address, 0x138);
+        assert_eq!(node1.end_address, 0x270);
+
+        assert!(layout.node(&Node2).is_none());
+    }
+
+    #[test]
+    fn test_node_at() {
+        let layout = MessageRamLayout::new()
+            .node_at(&Node1, 0x1000, config())
+            .node(&Node0, config())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            layout
+                .node(&Node1)
+                .unwrap()
+                .standard_filter_list_start_address,
+            0x1000
+        );
+        assert_eq!(
+            layout
+                .node(&Node0)
+                .unwrap()
+                .standard_filter_list_start_address,
+            0x1138
+        );
+    }
+
+    #[test]
+    fn test_overlap() {
+        let result = MessageRamLayout::new()
+            .node_at(&Node0, 0x100, config())
+            .node_at(&Node1, 0x200, config())
+            .build();
+        assert_eq!(result, Err(RamLayoutError::Overlap));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = NodeRamConfig {
+            rx_fifo0_size: 64,
+            rx_fifo0_data_field_size: DataFieldSize::_64,
+            rx_fifo1_size: 64,
+            rx_fifo1_data_field_size: DataFieldSize::_64,
+            rx_buffers: 64,
+            rx_buffer_data_field_size: DataFieldSize::_64,
+            ..Default::default()
+        };
+        let result = MessageRamLayout::new()
+            .node(&Node0, big)
+            .node(&Node1, big)
+            .node(&Node2, big)
+            .build();
+        assert_eq!(result, Err(RamLayoutError::Overflow));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let too_many = NodeRamConfig {
+            dedicated_tx_buffers: 16,
+            tx_fifo_queue_size: 17,
+            ..Default::default()
+        };
+        let result = MessageRamLayout::new().node(&Node0, too_many).build();
+        assert_eq!(result, Err(RamLayoutError::TooManyElements));
+
+        let result = MessageRamLayout::new()
+            .node_at(&Node0, 0x102, config())
+            .build();
+        assert_eq!(result, Err(RamLayoutError::Misaligned));
+    }
+}
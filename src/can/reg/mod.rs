@@ -2,8 +2,10 @@
 #![allow(clippy::undocumented_unsafe_blocks)]
 #![allow(clippy::cast_possible_truncation)]
 
+pub(crate) mod msg_filter;
 pub(crate) mod msg_rx;
 pub(crate) mod msg_tx;
+pub(crate) mod msg_tx_event;
 
 use core::convert::From;
 use core::marker::PhantomData;
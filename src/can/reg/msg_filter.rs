@@ -0,0 +1,146 @@
+// TODO Remove this once the code is stable
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+// Note: this module try to mimic the behavior of the pac module, for message SRAM access
+// Note: transmute is used to create a Reg from a pointer, because the pac module does not support creating Reg from pointers
+
+use super::{hidden::RegValue, Reg, RegisterField, RW};
+use core::mem::transmute;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub(crate) struct StdFilterMsg(pub(super) *mut u8);
+unsafe impl Send for StdFilterMsg {}
+unsafe impl Sync for StdFilterMsg {}
+impl StdFilterMsg {
+    #[inline(always)]
+    pub(crate) fn s0(self) -> Reg<S0, RW> {
+        let ptr = unsafe { self.0.add(0usize) };
+        unsafe { transmute(ptr) }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub(crate) struct ExtFilterMsg(pub(super) *mut u8);
+unsafe impl Send for ExtFilterMsg {}
+unsafe impl Sync for ExtFilterMsg {}
+impl ExtFilterMsg {
+    #[inline(always)]
+    pub(crate) fn f0(self) -> Reg<F0, RW> {
+        let ptr = unsafe { self.0.add(0usize) };
+        unsafe { transmute(ptr) }
+    }
+    #[inline(always)]
+    pub(crate) fn f1(self) -> Reg<F1, RW> {
+        let ptr = unsafe { self.0.add(4usize) };
+        unsafe { transmute(ptr) }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+pub(crate) struct S0(u32, u32);
+impl RegValue for S0 {
+    type DataType = u32;
+    #[inline(always)]
+    fn data_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.0
+    }
+    #[inline(always)]
+    fn data(&self) -> Self::DataType {
+        self.0
+    }
+    #[inline(always)]
+    fn get_mask_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.1
+    }
+    #[inline(always)]
+    fn new(data: Self::DataType, write_mask: Self::DataType) -> Self {
+        Self(data, write_mask)
+    }
+}
+impl S0 {
+    #[inline(always)]
+    pub(crate) fn sfid2(self) -> RegisterField<0, 0x7FF, 1, 0, u16, S0, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn sfid1(self) -> RegisterField<16, 0x7FF, 1, 0, u16, S0, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn sfec(self) -> RegisterField<27, 0x7, 1, 0, u8, S0, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn sft(self) -> RegisterField<30, 0x3, 1, 0, u8, S0, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+pub(crate) struct F0(u32, u32);
+impl RegValue for F0 {
+    type DataType = u32;
+    #[inline(always)]
+    fn data_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.0
+    }
+    #[inline(always)]
+    fn data(&self) -> Self::DataType {
+        self.0
+    }
+    #[inline(always)]
+    fn get_mask_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.1
+    }
+    #[inline(always)]
+    fn new(data: Self::DataType, write_mask: Self::DataType) -> Self {
+        Self(data, write_mask)
+    }
+}
+impl F0 {
+    #[inline(always)]
+    pub(crate) fn efid1(self) -> RegisterField<0, 0x1FFF_FFFF, 1, 0, u32, F0, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn efec(self) -> RegisterField<29, 0x7, 1, 0, u8, F0, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+pub(crate) struct F1(u32, u32);
+impl RegValue for F1 {
+    type DataType = u32;
+    #[inline(always)]
+    fn data_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.0
+    }
+    #[inline(always)]
+    fn data(&self) -> Self::DataType {
+        self.0
+    }
+    #[inline(always)]
+    fn get_mask_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.1
+    }
+    #[inline(always)]
+    fn new(data: Self::DataType, write_mask: Self::DataType) -> Self {
+        Self(data, write_mask)
+    }
+}
+impl F1 {
+    #[inline(always)]
+    pub(crate) fn efid2(self) -> RegisterField<0, 0x1FFF_FFFF, 1, 0, u32, F1, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn eft(self) -> RegisterField<30, 0x3, 1, 0, u8, F1, RW> {
+        unsafe { transmute((self, 1)) }
+    }
+}
@@ -0,0 +1,121 @@
+// TODO Remove this once the code is stable
+#![allow(clippy::undocumented_unsafe_blocks)]
+
+// Note: this module try to mimic the behavior of the pac module, for message SRAM access
+// Note: transmute is used to create a Reg from a pointer, because the pac module does not support creating Reg from pointers
+
+use super::{hidden::RegValue, Reg, RegisterField, RegisterFieldBool, R};
+use core::mem::transmute;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub(crate) struct TxEventMsg(pub(super) *mut u8);
+unsafe impl Send for TxEventMsg {}
+unsafe impl Sync for TxEventMsg {}
+impl TxEventMsg {
+    #[inline(always)]
+    pub(crate) fn e0(self) -> Reg<E0, R> {
+        let ptr = unsafe { self.0.add(0usize) };
+        unsafe { transmute(ptr) }
+    }
+    #[inline(always)]
+    pub(crate) fn e1(self) -> Reg<E1, R> {
+        let ptr = unsafe { self.0.add(4usize) };
+        unsafe { transmute(ptr) }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+pub(crate) struct E0(u32, u32);
+impl RegValue for E0 {
+    type DataType = u32;
+    #[inline(always)]
+    fn data_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.0
+    }
+    #[inline(always)]
+    fn data(&self) -> Self::DataType {
+        self.0
+    }
+    #[inline(always)]
+    fn get_mask_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.1
+    }
+    #[inline(always)]
+    fn new(data: Self::DataType, write_mask: Self::DataType) -> Self {
+        Self(data, write_mask)
+    }
+}
+impl E0 {
+    #[inline(always)]
+    pub(crate) fn id(self) -> RegisterField<0, 0x1FFF_FFFF, 1, 0, u32, E0, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn rtr(self) -> RegisterFieldBool<29, 1, 0, E0, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn xtd(self) -> RegisterFieldBool<30, 1, 0, E0, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn esi(self) -> RegisterFieldBool<31, 1, 0, E0, R> {
+        unsafe { transmute((self, 1)) }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+pub(crate) struct E1(u32, u32);
+impl RegValue for E1 {
+    type DataType = u32;
+    #[inline(always)]
+    fn data_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.0
+    }
+    #[inline(always)]
+    fn data(&self) -> Self::DataType {
+        self.0
+    }
+    #[inline(always)]
+    fn get_mask_mut_ref(&mut self) -> &mut Self::DataType {
+        &mut self.1
+    }
+    #[inline(always)]
+    fn new(data: Self::DataType, write_mask: Self::DataType) -> Self {
+        Self(data, write_mask)
+    }
+}
+impl E1 {
+    #[inline(always)]
+    pub(crate) fn txts(self) -> RegisterField<0, 0xffff, 1, 0, u16, E1, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn dlc(self) -> RegisterField<16, 0xf, 1, 0, u8, E1, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn brs(self) -> RegisterFieldBool<20, 1, 0, E1, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn fdf(self) -> RegisterFieldBool<21, 1, 0, E1, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn et(self) -> RegisterField<22, 0x3, 1, 0, u8, E1, R> {
+        unsafe { transmute((self, 1)) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn mm(self) -> RegisterField<24, 0xff, 1, 0, u8, E1, R> {
+        unsafe { transmute((self, 1)) }
+    }
+}
@@ -0,0 +1,207 @@
+//! Lock-free receive queue, filled from the CAN node interrupt handler.
+//!
+//! The queue is split into a [`RxProducer`], given to
+//! [`Node::drain_rx`](crate::can::Node::drain_rx) in the interrupt handler, and a
+//! [`RxConsumer`] used by the application to take the received frames.
+
+#![allow(clippy::module_name_repetitions)]
+
+use super::frame::OwnedFrame;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// Single producer, single consumer ring of received frames.
+///
+/// One slot is kept free to tell a full queue from an empty one, so the queue holds
+/// up to `N - 1` frames.
+pub struct RxQueue<const N: usize> {
+    frames: UnsafeCell<[OwnedFrame; N]>,
+    /// Index of the next slot to write, only written by the producer
+    head: AtomicUsize,
+    /// Index of the next slot to read, only written by the consumer
+    tail: AtomicUsize,
+    overflows: AtomicU32,
+    lost: AtomicU32,
+}
+
+// SAFETY: the frames are only accessed through the producer and consumer, which are
+// unique since split takes the queue by mutable reference. The producer only writes
+// free slots and the consumer only reads filled slots, ownership of a slot being
+// transferred by the release store of head or tail.
+unsafe impl<const N: usize> Sync for RxQueue<N> {}
+
+impl<const N: usize> RxQueue<N> {
+    /// Evaluated in [`RxQueue::new`], the indexes are computed modulo `N`
+    const NOT_EMPTY: () = assert!(N > 0, "the queue needs at least one slot");
+
+    #[must_use]
+    pub const fn new() -> Self {
+        let () = Self::NOT_EMPTY;
+        Self {
+            frames: UnsafeCell::new([OwnedFrame::EMPTY; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflows: AtomicU32::new(0),
+            lost: AtomicU32::new(0),
+        }
+    }
+
+    /// Split the queue into its producer and consumer halves
+    pub fn split(&mut self) -> (RxProducer<'_, N>, RxConsumer<'_, N>) {
+        let queue: &Self = self;
+        (RxProducer { queue }, RxConsumer { queue })
+    }
+
+    /// Pointer to a slot, index is always in range [0, N)
+    fn slot(&self, index: usize) -> *mut OwnedFrame {
+        self.frames.get().cast::<OwnedFrame>().wrapping_add(index)
+    }
+
+    fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        (head + N - tail) % N
+    }
+
+    /// Number of frames dropped because the queue was full
+    #[must_use]
+    pub fn overflow_count(&self) -> u32 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames lost before reaching the queue: the Rx FIFOs of the node signalled
+    /// a message lost, or a received element could not be read as a frame
+    #[must_use]
+    pub fn lost_count(&self) -> u32 {
+        self.lost.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize> Default for RxQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writing half of a [`RxQueue`], to be used in the interrupt handler
+pub struct RxProducer<'a, const N: usize> {
+    queue: &'a RxQueue<N>,
+}
+
+impl<const N: usize> RxProducer<'_, N> {
+    /// Append a frame, returning it back if the queue is full
+    pub fn push(&mut self, frame: OwnedFrame) -> Result<(), OwnedFrame> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+        let next = (head + 1) % N;
+
+        if next == self.queue.tail.load(Ordering::Acquire) {
+            self.queue.overflows.fetch_add(1, Ordering::Relaxed);
+            return Err(frame);
+        }
+
+        // SAFETY: head is in range [0, N), the slot at head is not accessed by the
+        // consumer until head is advanced
+        unsafe { self.queue.slot(head).write(frame) };
+        self.queue.head.store(next, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Count a frame lost by the node, see [`RxQueue::lost_count`]
+    pub(crate) fn record_lost(&mut self) {
+        self.queue.lost.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn overflow_count(&self) -> u32 {
+        self.queue.overflow_count()
+    }
+
+    #[must_use]
+    pub fn lost_count(&self) -> u32 {
+        self.queue.lost_count()
+    }
+}
+
+/// Reading half of a [`RxQueue`], to be used by the application
+pub struct RxConsumer<'a, const N: usize> {
+    queue: &'a RxQueue<N>,
+}
+
+impl<const N: usize> RxConsumer<'_, N> {
+    /// Take the oldest received frame
+    pub fn pop(&mut self) -> Option<OwnedFrame> {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+
+        if tail == self.queue.head.load(Ordering::Acquire) {
+            return None;
+        }
+
+        // SAFETY: tail is in range [0, N), the slot at tail has been released by the
+        // producer and is not written again until tail is advanced
+        let frame = unsafe { self.queue.slot(tail).read() };
+        self.queue.tail.store((tail + 1) % N, Ordering::Release);
+
+        Some(frame)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    pub fn overflow_count(&self) -> u32 {
+        self.queue.overflow_count()
+    }
+
+    #[must_use]
+    pub fn lost_count(&self) -> u32 {
+        self.queue.lost_count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use embedded_can::{Frame, StandardId};
+
+    fn frame(byte: u8) -> OwnedFrame {
+        OwnedFrame::new(StandardId::new(0x100).unwrap(), &[byte]).unwrap()
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut queue = RxQueue::<4>::new();
+        let (mut producer, mut consumer) = queue.split();
+
+        assert!(consumer.pop().is_none());
+
+        for i in 0..3 {
+            assert!(producer.push(frame(i)).is_ok());
+        }
+        assert_eq!(consumer.len(), 3);
+        assert!(producer.push(frame(3)).is_err());
+        assert_eq!(consumer.overflow_count(), 1);
+
+        for i in 0..3 {
+            assert_eq!(consumer.pop().unwrap().data(), &[i]);
+        }
+        assert!(consumer.is_empty());
+
+        // wrap around
+        for i in 10..13 {
+            assert!(producer.push(frame(i)).is_ok());
+            assert_eq!(consumer.pop().unwrap().data(), &[i]);
+        }
+
+        producer.record_lost();
+        assert_eq!(consumer.lost_count(), 1);
+        assert_eq!(consumer.overflow_count(), 1);
+    }
+}
@@ -288,11 +288,95 @@ macro_rules! pin {
             )*
         )*
     };
+
+    ( $($(#[$docs:meta])* <$name:ident> input for $(no: $NoPin:ident,)? [$(
+            $(#[$attr:meta])* $PX:ident,
+    )*],)*) => {
+        $(
+            #[derive(Debug)]
+            $(#[$docs])*
+            pub enum $name {
+                $(
+                    None($NoPin<$crate::gpio::Input>),
+                )?
+
+                $(
+                    $(#[$attr])*
+                    $PX(gpio::$PX<$crate::gpio::Input>),
+                )*
+            }
+
+            impl crate::Sealed for $name { }
+
+            #[allow(unreachable_patterns)]
+            impl $crate::gpio::ReadPin for $name {
+                fn is_low(&self) -> bool {
+                    match self {
+                        $(
+                            $(#[$attr])*
+                            Self::$PX(p) => p.is_low(),
+                        )*
+                        _ => false,
+                    }
+                }
+            }
+
+            #[allow(unreachable_patterns)]
+            impl $crate::gpio::PinPull for $name {
+                fn set_internal_resistor(&mut self, _pull: $crate::gpio::Pull) {
+                    match self {
+                        $(
+                            $(#[$attr])*
+                            Self::$PX(p) => p.set_internal_resistor(_pull),
+                        )*
+                        _ => {}
+                    }
+                }
+            }
+
+            $(
+                impl From<$NoPin<$crate::gpio::Input>> for $name {
+                    fn from(p: $NoPin<$crate::gpio::Input>) -> Self {
+                        Self::None(p)
+                    }
+                }
+            )?
+
+            $(
+                $(#[$attr])*
+                impl<MODE> From<gpio::$PX<MODE>> for $name
+                where
+                    MODE: $crate::gpio::marker::NotAlt + $crate::gpio::PinMode
+                {
+                    fn from(p: gpio::$PX<MODE>) -> Self {
+                        Self::$PX(p.into_mode())
+                    }
+                }
+
+                $(#[$attr])*
+                #[allow(irrefutable_let_patterns)]
+                impl<MODE> TryFrom<$name> for gpio::$PX<MODE>
+                where
+                    MODE: $crate::gpio::PinMode,
+                {
+                    type Error = ();
+
+                    fn try_from(a: $name) -> Result<Self, Self::Error> {
+                        if let $name::$PX(p) = a {
+                            Ok(p.into_mode())
+                        } else {
+                            Err(())
+                        }
+                    }
+                }
+            )*
+        )*
+    };
 }
 use pin;
 
-// CAN pins
-pub trait CanCommon {
+// CAN pins of node `N` of a module
+pub trait CanCommon<N = crate::can::Node0> {
     type Rx;
     type Tx;
 }
@@ -1,22 +1,181 @@
 use super::*;
+use crate::can::{Node0, Node1, Node2, Node3};
 use crate::gpio::{self, NoPin, OpenDrain, PushPull};
 
 pub mod can0 {
     use super::*;
 
     pin! {
-        <Rx, PushPull> for no:NoPin, [
-            // FIXME Alternate function here?
-            P20_7<1>, // CAN00:RXDB = P20.7:IN
+        <Rx0> input for no:NoPin, [
+            P02_1,  // CAN00:RXDA = P02.1:IN
+            P20_7,  // CAN00:RXDB = P20.7:IN
+            P12_0,  // CAN00:RXDC = P12.0:IN
+            P33_12, // CAN00:RXDD = P33.12:IN
+            P33_7,  // CAN00:RXDE = P33.7:IN
+            P34_2,  // CAN00:RXDG = P34.2:IN
         ],
 
-        <Tx, PushPull> for no:NoPin, [
-            P20_8<5>, // CAN00:TXD = P20.8:ALT(5)
+        <Rx1> input for no:NoPin, [
+            P15_3,  // CAN01:RXDA = P15.3:IN
+            P14_1,  // CAN01:RXDB = P14.1:IN
+            P01_4,  // CAN01:RXDC = P01.4:IN
+            P33_10, // CAN01:RXDD = P33.10:IN
+            P02_10, // CAN01:RXDE = P02.10:IN
         ],
+
+        <Rx2> input for no:NoPin, [
+            P15_1,  // CAN02:RXDA = P15.1:IN
+            P02_3,  // CAN02:RXDB = P02.3:IN
+            P32_6,  // CAN02:RXDC = P32.6:IN
+            P14_8,  // CAN02:RXDD = P14.8:IN
+            P10_2,  // CAN02:RXDE = P10.2:IN
+        ],
+
+        <Rx3> input for no:NoPin, [
+            P00_3,  // CAN03:RXDA = P00.3:IN
+            P32_2,  // CAN03:RXDB = P32.2:IN
+            P20_0,  // CAN03:RXDC = P20.0:IN
+            P11_10, // CAN03:RXDD = P11.10:IN
+            P20_9,  // CAN03:RXDE = P20.9:IN
+        ],
+    }
+
+    pin! {
+        <Tx0, PushPull> for no:NoPin, [
+            P02_0<5>,  // CAN00:TXD = P02.0:ALT(5)
+            P12_1<5>,  // CAN00:TXD = P12.1:ALT(5)
+            P20_8<5>,  // CAN00:TXD = P20.8:ALT(5)
+            P33_13<5>, // CAN00:TXD = P33.13:ALT(5)
+            P33_8<5>,  // CAN00:TXD = P33.8:ALT(5)
+            P34_1<4>,  // CAN00:TXD = P34.1:ALT(4)
+        ],
+
+        <Tx1, PushPull> for no:NoPin, [
+            P01_3<5>,  // CAN01:TXD = P01.3:ALT(5)
+            P02_9<5>,  // CAN01:TXD = P02.9:ALT(5)
+            P14_0<5>,  // CAN01:TXD = P14.0:ALT(5)
+            P15_2<5>,  // CAN01:TXD = P15.2:ALT(5)
+            P33_9<5>,  // CAN01:TXD = P33.9:ALT(5)
+        ],
+
+        <Tx2, PushPull> for no:NoPin, [
+            P02_2<5>,  // CAN02:TXD = P02.2:ALT(5)
+            P10_3<6>,  // CAN02:TXD = P10.3:ALT(6)
+            P14_10<5>, // CAN02:TXD = P14.10:ALT(5)
+            P15_0<5>,  // CAN02:TXD = P15.0:ALT(5)
+            P32_5<6>,  // CAN02:TXD = P32.5:ALT(6)
+        ],
+
+        <Tx3, PushPull> for no:NoPin, [
+            P00_2<5>,  // CAN03:TXD = P00.2:ALT(5)
+            P11_12<5>, // CAN03:TXD = P11.12:ALT(5)
+            P20_10<5>, // CAN03:TXD = P20.10:ALT(5)
+            P20_3<5>,  // CAN03:TXD = P20.3:ALT(5)
+            P32_3<5>,  // CAN03:TXD = P32.3:ALT(5)
+        ],
+    }
+
+    impl CanCommon<Node0> for crate::pac::can0::Can0 {
+        type Rx = Rx0;
+        type Tx = Tx0;
+    }
+
+    impl CanCommon<Node1> for crate::pac::can0::Can0 {
+        type Rx = Rx1;
+        type Tx = Tx1;
+    }
+
+    impl CanCommon<Node2> for crate::pac::can0::Can0 {
+        type Rx = Rx2;
+        type Tx = Tx2;
+    }
+
+    impl CanCommon<Node3> for crate::pac::can0::Can0 {
+        type Rx = Rx3;
+        type Tx = Tx3;
+    }
+}
+
+pub mod can1 {
+    use super::*;
+
+    pin! {
+        <Rx0> input for no:NoPin, [
+            P00_1,  // CAN10:RXDA = P00.1:IN
+            P14_7,  // CAN10:RXDB = P14.7:IN
+            P23_0,  // CAN10:RXDC = P23.0:IN
+            P13_1,  // CAN10:RXDD = P13.1:IN
+        ],
+
+        <Rx1> input for no:NoPin, [
+            P02_4,  // CAN11:RXDA = P02.4:IN
+            P00_5,  // CAN11:RXDB = P00.5:IN
+            P23_7,  // CAN11:RXDC = P23.7:IN
+            P11_7,  // CAN11:RXDD = P11.7:IN
+        ],
+
+        <Rx2> input for no:NoPin, [
+            P20_6,  // CAN12:RXDA = P20.6:IN
+            P10_8,  // CAN12:RXDB = P10.8:IN
+            P23_3,  // CAN12:RXDC = P23.3:IN
+            P11_8,  // CAN12:RXDD = P11.8:IN
+        ],
+
+        <Rx3> input for no:NoPin, [
+            P14_7,  // CAN13:RXDA = P14.7:IN
+            P33_5,  // CAN13:RXDB = P33.5:IN
+            P22_5,  // CAN13:RXDC = P22.5:IN
+            P11_13, // CAN13:RXDD = P11.13:IN
+        ],
+    }
+
+    pin! {
+        <Tx0, PushPull> for no:NoPin, [
+            P00_0<5>,  // CAN10:TXD = P00.0:ALT(5)
+            P13_0<7>,  // CAN10:TXD = P13.0:ALT(7)
+            P14_9<4>,  // CAN10:TXD = P14.9:ALT(4)
+            P23_1<5>,  // CAN10:TXD = P23.1:ALT(5)
+        ],
+
+        <Tx1, PushPull> for no:NoPin, [
+            P00_4<3>,  // CAN11:TXD = P00.4:ALT(3)
+            P02_5<2>,  // CAN11:TXD = P02.5:ALT(2)
+            P11_0<5>,  // CAN11:TXD = P11.0:ALT(5)
+            P23_6<5>,  // CAN11:TXD = P23.6:ALT(5)
+        ],
+
+        <Tx2, PushPull> for no:NoPin, [
+            P10_7<6>,  // CAN12:TXD = P10.7:ALT(6)
+            P11_1<5>,  // CAN12:TXD = P11.1:ALT(5)
+            P20_7<5>,  // CAN12:TXD = P20.7:ALT(5)
+            P23_2<5>,  // CAN12:TXD = P23.2:ALT(5)
+        ],
+
+        <Tx3, PushPull> for no:NoPin, [
+            P11_4<5>,  // CAN13:TXD = P11.4:ALT(5)
+            P14_6<4>,  // CAN13:TXD = P14.6:ALT(4)
+            P22_4<6>,  // CAN13:TXD = P22.4:ALT(6)
+            P33_4<7>,  // CAN13:TXD = P33.4:ALT(7)
+        ],
+    }
+
+    impl CanCommon<Node0> for crate::pac::can1::Can1 {
+        type Rx = Rx0;
+        type Tx = Tx0;
+    }
+
+    impl CanCommon<Node1> for crate::pac::can1::Can1 {
+        type Rx = Rx1;
+        type Tx = Tx1;
+    }
+
+    impl CanCommon<Node2> for crate::pac::can1::Can1 {
+        type Rx = Rx2;
+        type Tx = Tx2;
     }
 
-    impl CanCommon for crate::pac::can0::Can0 {
-        type Rx = Rx;
-        type Tx = Tx;
+    impl CanCommon<Node3> for crate::pac::can1::Can1 {
+        type Rx = Rx3;
+        type Tx = Tx3;
     }
 }
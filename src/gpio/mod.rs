@@ -68,6 +68,7 @@ use core::marker::PhantomData;
 use core::mem::transmute;
 
 use crate::pac::RegisterValue;
+use crate::scu::wdt_call;
 pub use embedded_hal::digital::PinState;
 
 pub use convert::PinMode;
@@ -280,6 +281,9 @@ pub struct Pin<const P: PortIndex, const N: PinIndex, MODE = DefaultMode> {
 }
 
 impl<const P: PortIndex, const N: PinIndex, MODE> Pin<P, N, MODE> {
+    /// Evaluated where the pin index selects a register field, ports have 16 pins
+    const VALID_INDEX: () = assert!(N < 16, "pin index out of range");
+
     const fn new() -> Self {
         Self { _mode: PhantomData }
     }
@@ -354,16 +358,51 @@ impl<const P: PortIndex, const N: PinIndex, MODE> Pin<P, N, MODE>
 where
     MODE: marker::OutputSpeed,
 {
-    /// Set pin speed
+    /// Set pin speed, which selects the CMOS automotive pad driver speed grade 1 to 4
     pub fn set_speed(&mut self, speed: Speed) {
-        let offset = 2 * { N };
+        let () = Self::VALID_INDEX;
+        let speed = speed as u8;
+
+        // SAFETY: Gpio::<P>::ptr() will panic if P is not a valid port index, all Port instances have the same layout as P00
+        let port = unsafe { (*Gpio::<P>::ptr()) };
 
-        // TODO Implement set speed
-        // unsafe {
-        //     (*Gpio::<P>::ptr())
-        //         .ospeedr
-        //         .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | ((speed as u32) << offset)));
-        // }
+        // PDR registers are CPU ENDINIT protected
+        wdt_call::call_without_cpu_endinit(|| match N {
+            // SAFETY: speed is in range [0, 4)
+            0 => unsafe { port.pdr0().modify_atomic(|r| r.pd0().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            1 => unsafe { port.pdr0().modify_atomic(|r| r.pd1().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            2 => unsafe { port.pdr0().modify_atomic(|r| r.pd2().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            3 => unsafe { port.pdr0().modify_atomic(|r| r.pd3().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            4 => unsafe { port.pdr0().modify_atomic(|r| r.pd4().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            5 => unsafe { port.pdr0().modify_atomic(|r| r.pd5().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            6 => unsafe { port.pdr0().modify_atomic(|r| r.pd6().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            7 => unsafe { port.pdr0().modify_atomic(|r| r.pd7().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            8 => unsafe { port.pdr1().modify_atomic(|r| r.pd8().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            9 => unsafe { port.pdr1().modify_atomic(|r| r.pd9().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            10 => unsafe { port.pdr1().modify_atomic(|r| r.pd10().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            11 => unsafe { port.pdr1().modify_atomic(|r| r.pd11().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            12 => unsafe { port.pdr1().modify_atomic(|r| r.pd12().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            13 => unsafe { port.pdr1().modify_atomic(|r| r.pd13().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            14 => unsafe { port.pdr1().modify_atomic(|r| r.pd14().set(speed)) },
+            // SAFETY: speed is in range [0, 4)
+            15 => unsafe { port.pdr1().modify_atomic(|r| r.pd15().set(speed)) },
+            // rejected at compile time by VALID_INDEX
+            _ => {}
+        });
     }
 
     /// Set pin speed
@@ -390,6 +429,7 @@ where
 {
     /// Set the internal pull-up and pull-down resistor
     pub fn set_internal_resistor(&mut self, resistor: Pull) {
+        let () = Self::VALID_INDEX;
         let mode = match resistor {
             Pull::None => 0b00,
             Pull::Up => 0b10,
@@ -432,7 +472,8 @@ where
             14 => unsafe { port.iocr12().modify_atomic(|r| r.pc14().set(mode)) },
             // SAFETY: mode is in range [0, 3)
             15 => unsafe { port.iocr12().modify_atomic(|r| r.pc15().set(mode)) },
-            _ => unimplemented!(),
+            // rejected at compile time by VALID_INDEX
+            _ => {}
         }
     }
 
@@ -25,8 +25,105 @@ gpio!(gpio01, crate::pac::p01::P01, 1, P01n, [
     P01_7 : (p01_7 , 7 , [  0,     1,     2,     3,     4,     5,     6,     7  ]),
 ]);
 
+gpio!(gpio02, crate::pac::p02::P02, 2, P02n, [
+    P02_0 : (p02_0 , 0 , [ 5 ]),
+    P02_1 : (p02_1 , 1 , [  ]),
+    P02_2 : (p02_2 , 2 , [ 5 ]),
+    P02_3 : (p02_3 , 3 , [  ]),
+    P02_4 : (p02_4 , 4 , [  ]),
+    P02_5 : (p02_5 , 5 , [ 2 ]),
+    P02_9 : (p02_9 , 9 , [ 5 ]),
+    P02_10: (p02_10, 10, [  ]),
+]);
+
+gpio!(gpio10, crate::pac::p10::P10, 10, P10n, [
+    P10_2 : (p10_2 , 2 , [  ]),
+    P10_3 : (p10_3 , 3 , [ 6 ]),
+    P10_7 : (p10_7 , 7 , [ 6 ]),
+    P10_8 : (p10_8 , 8 , [  ]),
+]);
+
+gpio!(gpio11, crate::pac::p11::P11, 11, P11n, [
+    P11_0 : (p11_0 , 0 , [ 5 ]),
+    P11_1 : (p11_1 , 1 , [ 5 ]),
+    P11_4 : (p11_4 , 4 , [ 5 ]),
+    P11_7 : (p11_7 , 7 , [  ]),
+    P11_8 : (p11_8 , 8 , [  ]),
+    P11_10: (p11_10, 10, [  ]),
+    P11_12: (p11_12, 12, [ 5 ]),
+    P11_13: (p11_13, 13, [  ]),
+]);
+
+gpio!(gpio12, crate::pac::p12::P12, 12, P12n, [
+    P12_0 : (p12_0 , 0 , [  ]),
+    P12_1 : (p12_1 , 1 , [ 5 ]),
+]);
+
+gpio!(gpio13, crate::pac::p13::P13, 13, P13n, [
+    P13_0 : (p13_0 , 0 , [ 7 ]),
+    P13_1 : (p13_1 , 1 , [  ]),
+]);
+
+gpio!(gpio14, crate::pac::p14::P14, 14, P14n, [
+    P14_0 : (p14_0 , 0 , [ 5 ]),
+    P14_1 : (p14_1 , 1 , [  ]),
+    P14_6 : (p14_6 , 6 , [ 4 ]),
+    P14_7 : (p14_7 , 7 , [  ]),
+    P14_8 : (p14_8 , 8 , [  ]),
+    P14_9 : (p14_9 , 9 , [ 4 ]),
+    P14_10: (p14_10, 10, [ 5 ]),
+]);
+
+gpio!(gpio15, crate::pac::p15::P15, 15, P15n, [
+    P15_0 : (p15_0 , 0 , [ 5 ]),
+    P15_1 : (p15_1 , 1 , [  ]),
+    P15_2 : (p15_2 , 2 , [ 5 ]),
+    P15_3 : (p15_3 , 3 , [  ]),
+]);
+
 gpio!(gpio20, crate::pac::p20::P20, 20, P20n, [
+    P20_0 : (p20_0 , 0 , [  ]),
+    P20_3 : (p20_3 , 3 , [ 5 ]),
     P20_6 : (p20_6 , 6 , [  ]),
-    P20_7 : (p20_7 , 7 , [  ]),
+    P20_7 : (p20_7 , 7 , [ 5 ]),
     P20_8 : (p20_8 , 8 , [ 5 ]),
+    P20_9 : (p20_9 , 9 , [  ]),
+    P20_10: (p20_10, 10, [ 5 ]),
+]);
+
+gpio!(gpio22, crate::pac::p22::P22, 22, P22n, [
+    P22_4 : (p22_4 , 4 , [ 6 ]),
+    P22_5 : (p22_5 , 5 , [  ]),
+]);
+
+gpio!(gpio23, crate::pac::p23::P23, 23, P23n, [
+    P23_0 : (p23_0 , 0 , [  ]),
+    P23_1 : (p23_1 , 1 , [ 5 ]),
+    P23_2 : (p23_2 , 2 , [ 5 ]),
+    P23_3 : (p23_3 , 3 , [  ]),
+    P23_6 : (p23_6 , 6 , [ 5 ]),
+    P23_7 : (p23_7 , 7 , [  ]),
+]);
+
+gpio!(gpio32, crate::pac::p32::P32, 32, P32n, [
+    P32_2 : (p32_2 , 2 , [  ]),
+    P32_3 : (p32_3 , 3 , [ 5 ]),
+    P32_5 : (p32_5 , 5 , [ 6 ]),
+    P32_6 : (p32_6 , 6 , [  ]),
+]);
+
+gpio!(gpio33, crate::pac::p33::P33, 33, P33n, [
+    P33_4 : (p33_4 , 4 , [ 7 ]),
+    P33_5 : (p33_5 , 5 , [  ]),
+    P33_7 : (p33_7 , 7 , [  ]),
+    P33_8 : (p33_8 , 8 , [ 5 ]),
+    P33_9 : (p33_9 , 9 , [ 5 ]),
+    P33_10: (p33_10, 10, [  ]),
+    P33_12: (p33_12, 12, [  ]),
+    P33_13: (p33_13, 13, [ 5 ]),
+]);
+
+gpio!(gpio34, crate::pac::p34::P34, 34, P34n, [
+    P34_1 : (p34_1 , 1 , [ 4 ]),
+    P34_2 : (p34_2 , 2 , [  ]),
 ]);
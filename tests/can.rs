@@ -1,15 +1,15 @@
 use bw_r_drivers_tc37x::can::Tos;
 use bw_r_drivers_tc37x::can::{
-    config::NodeInterruptConfig,
-    pin_map::{PIN_RX_0_0_P20_7, PIN_TX_0_0_P20_8},
-    AutoBitTiming, BitTimingConfig, DataFieldSize, Interrupt, InterruptGroup, InterruptLine,
-    Module, Module0, Node0, NodeConfig, Pins, RxConfig, RxFifoMode, RxMode, TxConfig, TxMode,
+    config::NodeInterruptConfig, AutoBitTiming, BitTimingConfig, DataFieldSize, Interrupt,
+    InterruptGroup, InterruptLine, Module, Module0, Node0, NodeConfig, RxConfig, RxFifoMode,
+    RxMode, TxConfig, TxMode,
 };
 use bw_r_drivers_tc37x::cpu::Priority;
+use bw_r_drivers_tc37x::gpio::GpioExt;
 use bw_r_drivers_tc37x::pac;
 use bw_r_drivers_tc37x::tracing::log::Report;
 
-use pac::{CAN0, SCU, SRC};
+use pac::{CAN0, P20, SCU, SRC};
 
 // TODO fix values of can_module.enable reads
 // TODO add report comments with actual registers' name
@@ -121,9 +121,11 @@ fn test_setup_can0() {
     // txbc0 for set_dedicated_tx_buffers_number for setup_tx
     report.expect_read(CAN0.n()[0].tx().txbci().ptr(), 4, 0b100_0100_0000);
 
-    // txbtie0 for enable_tx_buffer_transmission_interrupt for setup_tx
+    // txbtie0 and txbcie0 for enable_tx_buffer_interrupts for setup_tx
     report.expect_read(CAN0.n()[0].tx().txbtiei().ptr(), 4, 0b0);
+    report.expect_read(CAN0.n()[0].tx().txbciei().ptr(), 4, 0b0);
     report.expect_read(CAN0.n()[0].tx().txbtiei().ptr(), 4, 0b1);
+    report.expect_read(CAN0.n()[0].tx().txbciei().ptr(), 4, 0b1);
 
     // txefc0 for set_tx_event_fifo_start_address for setup_tx
     report.expect_read(CAN0.n()[0].tx().txefci().ptr(), 4, 0b0);
@@ -189,14 +191,10 @@ fn test_setup_can0() {
         rx_fifo0_start_address: 0x100,
         rx_fifo1_start_address: 0x200,
         rx_buffers_start_address: 0x300,
+        standard_filter_list_start_address: 0x000,
+        extended_filter_list_start_address: 0x080,
     });
 
-    // clear_cpu_endinit
-    report.expect_read(SCU.wdtcpu()[0].wdtcpuycon0().ptr(), 4, 0b11);
-
-    // set_cpu_endinit
-    report.expect_read(SCU.wdtcpu()[0].wdtcpuycon0().ptr(), 4, 0b10);
-
     report.expect_read(CAN0.n()[0].npcri().ptr(), 4, 0b0);
 
     // clear_cpu_endinit
@@ -205,10 +203,8 @@ fn test_setup_can0() {
     // set_cpu_endinit
     report.expect_read(SCU.wdtcpu()[0].wdtcpuycon0().ptr(), 4, 0b10);
 
-    node.setup_pins(Some(&Pins {
-        tx: PIN_TX_0_0_P20_8,
-        rx: PIN_RX_0_0_P20_7,
-    }));
+    let port = P20.split();
+    node.setup_pins(port.p20_8, port.p20_7);
 
     report.expect_read(CAN0.n()[0].grint2i().ptr(), 4, 0b0);
 